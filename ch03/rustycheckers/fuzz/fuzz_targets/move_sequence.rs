@@ -0,0 +1,55 @@
+//! Feeds arbitrary move sequences to `GameEngine`.
+//!
+//! Every four input bytes form one move. A byte with its high bit set picks
+//! a legal move by index instead, which keeps games going long enough to
+//! reach crowning and late-game positions. Run with
+//! `cargo fuzz run move_sequence` from `ch03/rustycheckers`.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rustycheckers::board::{Move, PieceColor};
+use rustycheckers::game::GameEngine;
+
+fn count(engine: &GameEngine, color: PieceColor) -> usize {
+    engine
+        .pieces()
+        .iter()
+        .filter(|(_location, piece)| piece.color == color)
+        .count()
+}
+
+fuzz_target!(|data: &[u8]| {
+    let mut engine = GameEngine::new();
+
+    for chunk in data.chunks_exact(4) {
+        let movement = if chunk[0] & 0x80 != 0 {
+            let legal = engine.valid_moves();
+            if legal.is_empty() {
+                return;
+            }
+            legal[chunk[1] as usize % legal.len()]
+        } else {
+            // Allow coordinates just off the board.
+            let coordinate = |byte: u8| (byte % 10) as usize;
+            Move::new(
+                (coordinate(chunk[0]), coordinate(chunk[1])),
+                (coordinate(chunk[2]), coordinate(chunk[3])),
+            )
+        };
+
+        let mover = engine.current_turn();
+        let opponent = mover.opponent();
+        let is_capture = engine.valid_moves().contains(&movement) && engine.is_capture(&movement);
+        let before = (count(&engine, mover), count(&engine, opponent));
+
+        let accepted = engine.move_piece(&movement).is_ok();
+        let after = (count(&engine, mover), count(&engine, opponent));
+
+        assert_eq!(after.0, before.0);
+        assert_eq!(after.1 + usize::from(is_capture), before.1);
+        assert!(accepted || !is_capture);
+
+        let regenerated = GameEngine::from_position(&engine.pieces(), engine.current_turn());
+        assert_eq!(engine.valid_moves(), regenerated.valid_moves());
+    }
+});
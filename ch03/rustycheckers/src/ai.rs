@@ -0,0 +1,301 @@
+use super::board::{Coordinate, Move, PieceColor};
+use super::game::GameEngine;
+
+/// Material value of an uncrowned piece.
+pub const MAN_VALUE: i32 = 100;
+/// Material value of a crowned piece.
+pub const KING_VALUE: i32 = 160;
+/// Score of a won position. Wins found sooner score higher.
+pub const WIN_SCORE: i32 = 100_000;
+
+/// The outcome of a search: the move to play and the score it leads to, from
+/// the perspective of the side to move.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SearchResult {
+    pub best_move: Option<Move>,
+    pub score: i32,
+    pub nodes: u64,
+}
+
+/// Tunable terms for the evaluation function.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Weights {
+    /// Value of an uncrowned piece.
+    pub man: i32,
+    /// Value of a crowned piece.
+    pub king: i32,
+    /// Bonus per row an uncrowned piece has advanced from its back row.
+    pub advancement: i32,
+    /// Bonus for each uncrowned piece still guarding its back row.
+    pub back_row: i32,
+}
+
+impl Default for Weights {
+    fn default() -> Weights {
+        Weights {
+            man: MAN_VALUE,
+            king: KING_VALUE,
+            advancement: 0,
+            back_row: 0,
+        }
+    }
+}
+
+/// Something that scores positions for the search, from the perspective of
+/// the side to move, on the same scale as `MAN_VALUE`.
+pub trait Evaluator {
+    fn evaluate(&self, engine: &GameEngine) -> i32;
+}
+
+impl Evaluator for Weights {
+    fn evaluate(&self, engine: &GameEngine) -> i32 {
+        evaluate_with(engine, self)
+    }
+}
+
+/// Scores a position by material, from the perspective of the side to move.
+pub fn evaluate(engine: &GameEngine) -> i32 {
+    evaluate_with(engine, &Weights::default())
+}
+
+/// Scores a position with the given weights, from the perspective of the
+/// side to move.
+pub fn evaluate_with(engine: &GameEngine, weights: &Weights) -> i32 {
+    let turn = engine.current_turn();
+
+    engine
+        .pieces()
+        .iter()
+        .map(|(location, piece)| {
+            let value = if piece.crowned {
+                weights.king
+            } else {
+                let advanced = rows_advanced(piece.color, *location);
+                let guarding = if advanced == 0 { weights.back_row } else { 0 };
+
+                weights.man + weights.advancement * advanced + guarding
+            };
+
+            if piece.color == turn {
+                value
+            } else {
+                -value
+            }
+        })
+        .sum()
+}
+
+/// Returns how many rows a piece has moved away from its own back row.
+fn rows_advanced(color: PieceColor, location: Coordinate) -> i32 {
+    let Coordinate(_x, y) = location;
+
+    color.home_row().abs_diff(y) as i32
+}
+
+/// Searches the game tree to the given depth and returns the best move.
+///
+/// Uses negamax with alpha-beta pruning, trying captures first. Ties are
+/// broken by move order, so the result is deterministic for a position.
+pub fn search(engine: &GameEngine, depth: u8) -> SearchResult {
+    search_with(engine, depth, &Weights::default())
+}
+
+/// Searches the game tree to the given depth, evaluating leaves with the
+/// given evaluator.
+pub fn search_with<E: Evaluator + ?Sized>(
+    engine: &GameEngine,
+    depth: u8,
+    evaluator: &E,
+) -> SearchResult {
+    let mut nodes = 0;
+    let mut best_move = None;
+    let mut alpha = -WIN_SCORE - 1;
+    let beta = WIN_SCORE + 1;
+
+    for movement in ordered_moves(engine) {
+        let mut child = engine.clone();
+        if child.move_piece(&movement).is_err() {
+            continue;
+        }
+
+        let score = score_child(
+            engine,
+            &child,
+            depth.saturating_sub(1),
+            1,
+            alpha,
+            beta,
+            evaluator,
+            &mut nodes,
+        );
+
+        if best_move.is_none() || score > alpha {
+            alpha = score;
+            best_move = Some(movement);
+        }
+    }
+
+    let score = if best_move.is_some() {
+        alpha
+    } else {
+        -WIN_SCORE
+    };
+
+    SearchResult {
+        best_move,
+        score,
+        nodes,
+    }
+}
+
+/// Returns the legal moves for the side to move, captures first.
+fn ordered_moves(engine: &GameEngine) -> Vec<Move> {
+    let (mut captures, mut quiet): (Vec<Move>, Vec<Move>) = engine
+        .valid_moves()
+        .into_iter()
+        .partition(|movement| engine.is_capture(movement));
+
+    captures.append(&mut quiet);
+    captures
+}
+
+/// Scores a child position from the point of view of the side to move in
+/// `parent`. A multi-jump leaves the same side to move, so its score isn't
+/// negated, and finishing the jump doesn't use up depth.
+#[allow(clippy::too_many_arguments)]
+fn score_child<E: Evaluator + ?Sized>(
+    parent: &GameEngine,
+    child: &GameEngine,
+    depth: u8,
+    ply: i32,
+    alpha: i32,
+    beta: i32,
+    evaluator: &E,
+    nodes: &mut u64,
+) -> i32 {
+    if child.current_turn() == parent.current_turn() {
+        negamax(child, depth + 1, ply, alpha, beta, evaluator, nodes)
+    } else {
+        -negamax(child, depth, ply, -beta, -alpha, evaluator, nodes)
+    }
+}
+
+fn negamax<E: Evaluator + ?Sized>(
+    engine: &GameEngine,
+    depth: u8,
+    ply: i32,
+    mut alpha: i32,
+    beta: i32,
+    evaluator: &E,
+    nodes: &mut u64,
+) -> i32 {
+    *nodes += 1;
+
+    let moves = ordered_moves(engine);
+
+    if moves.is_empty() {
+        return -WIN_SCORE + ply;
+    }
+
+    if depth == 0 {
+        return evaluator.evaluate(engine);
+    }
+
+    for movement in moves {
+        let mut child = engine.clone();
+        if child.move_piece(&movement).is_err() {
+            continue;
+        }
+
+        let score = score_child(
+            engine,
+            &child,
+            depth - 1,
+            ply + 1,
+            alpha,
+            beta,
+            evaluator,
+            nodes,
+        );
+
+        if score >= beta {
+            return beta;
+        }
+
+        if score > alpha {
+            alpha = score;
+        }
+    }
+
+    alpha
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::board::Move;
+    use super::super::board::{Coordinate, GamePiece, PieceColor};
+    use super::super::game::{GameEngine, Rules};
+    use super::{evaluate, evaluate_with, search, Weights};
+
+    #[test]
+    fn starting_position_is_even() {
+        let engine = GameEngine::new();
+        assert_eq!(evaluate(&engine), 0);
+    }
+
+    #[test]
+    fn weights_reward_advancement() {
+        let mut engine = GameEngine::new();
+        engine.move_piece(&Move::new((2, 5), (3, 4))).unwrap();
+
+        let weights = Weights {
+            advancement: 5,
+            ..Weights::default()
+        };
+
+        // White to move: black has advanced one man a row further than white.
+        assert_eq!(evaluate(&engine), 0);
+        assert_eq!(evaluate_with(&engine, &weights), -5);
+    }
+
+    #[test]
+    fn search_returns_legal_move() {
+        let engine = GameEngine::new();
+        let result = search(&engine, 3);
+        let best = result.best_move.unwrap();
+
+        assert!(engine.valid_moves().contains(&best));
+        assert!(result.nodes > 0);
+    }
+
+    #[test]
+    fn search_takes_free_piece() {
+        let mut engine = GameEngine::new();
+        engine.move_piece(&Move::new((2, 5), (3, 4))).unwrap();
+        engine.move_piece(&Move::new((5, 2), (4, 3))).unwrap();
+
+        let result = search(&engine, 1);
+        assert_eq!(result.best_move, Some(Move::new((3, 4), (5, 2))));
+        assert_eq!(result.score, 100);
+    }
+
+    #[test]
+    fn search_scores_multi_jumps_for_the_side_that_jumps() {
+        let black = GamePiece::new(PieceColor::Black);
+        let white = GamePiece::new(PieceColor::White);
+        let pieces = [
+            (Coordinate(1, 6), black),
+            (Coordinate(2, 5), white),
+            (Coordinate(4, 3), white),
+            (Coordinate(7, 0), white),
+        ];
+        let engine =
+            GameEngine::from_position(&pieces, PieceColor::Black).with_rules(Rules::Standard);
+
+        let result = search(&engine, 1);
+        assert_eq!(result.best_move, Some(Move::new((1, 6), (3, 4))));
+        // Both captures count within the one ply searched, evening the
+        // material from two men down.
+        assert_eq!(result.score, 0);
+    }
+}
@@ -0,0 +1,247 @@
+use super::ai::{self, Evaluator, Weights};
+use super::board::Move;
+use super::game::GameEngine;
+
+/// Search depth used for hints and evaluations unless one is given.
+pub const DEFAULT_DEPTH: u8 = 6;
+/// Deepest search accepted from the UI, which runs on the main thread.
+pub const MAX_DEPTH: u8 = 12;
+/// How far a move must drop the evaluation to count as a blunder: a little
+/// more than a man.
+pub const BLUNDER_THRESHOLD: i32 = 150;
+
+/// A legal move and the score it leads to, from the perspective of the
+/// player making it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ScoredMove {
+    pub movement: Move,
+    pub score: i32,
+}
+
+/// An evaluation of a position for the side to move.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Analysis {
+    /// Every legal move, best first.
+    pub moves: Vec<ScoredMove>,
+    /// The score of the position, which is the score of the best move. A
+    /// position with no legal moves is lost.
+    pub score: i32,
+}
+
+impl Analysis {
+    /// The move to suggest as a hint.
+    pub fn best_move(&self) -> Option<Move> {
+        self.moves.first().map(|scored| scored.movement)
+    }
+
+    /// Looks up the score of one of the legal moves.
+    pub fn score_of(&self, movement: &Move) -> Option<i32> {
+        self.moves
+            .iter()
+            .find(|scored| scored.movement == *movement)
+            .map(|scored| scored.score)
+    }
+}
+
+/// Scores every legal move by searching the position it leads to.
+///
+/// Moves with equal scores keep the engine's move order, so the ranking is
+/// stable from one call to the next.
+pub fn analyze(engine: &GameEngine, depth: u8) -> Analysis {
+    analyze_with(engine, depth, &Weights::default())
+}
+
+/// Like `analyze`, scoring positions with the given evaluator.
+pub fn analyze_with<E: Evaluator + ?Sized>(
+    engine: &GameEngine,
+    depth: u8,
+    evaluator: &E,
+) -> Analysis {
+    let mut moves: Vec<ScoredMove> = engine
+        .valid_moves()
+        .into_iter()
+        .map(|movement| ScoredMove {
+            movement,
+            score: score_move_with(engine, &movement, depth, evaluator),
+        })
+        .collect();
+
+    moves.sort_by_key(|scored| std::cmp::Reverse(scored.score));
+
+    let score = moves.first().map_or(-ai::WIN_SCORE, |best| best.score);
+    Analysis { moves, score }
+}
+
+/// Scores a single move, searching `depth` plies including the move itself.
+pub fn score_move(engine: &GameEngine, movement: &Move, depth: u8) -> i32 {
+    score_move_with(engine, movement, depth, &Weights::default())
+}
+
+/// Like `score_move`, scoring positions with the given evaluator.
+pub fn score_move_with<E: Evaluator + ?Sized>(
+    engine: &GameEngine,
+    movement: &Move,
+    depth: u8,
+    evaluator: &E,
+) -> i32 {
+    let mut after = engine.clone();
+
+    if after.move_piece(movement).is_err() {
+        return -ai::WIN_SCORE;
+    }
+
+    // A search always looks at least one ply ahead, so the last ply is
+    // scored by evaluating the position directly.
+    if depth <= 1 && after.winner().is_none() {
+        -evaluator.evaluate(&after)
+    } else {
+        -ai::search_with(&after, depth.saturating_sub(1), evaluator).score
+    }
+}
+
+/// A move that dropped the evaluation sharply.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Blunder {
+    /// Index of the move in the game, counting from zero.
+    pub ply: usize,
+    pub played: Move,
+    pub best: Move,
+    /// How much worse the played move scored than the best one.
+    pub loss: i32,
+}
+
+/// Replays a game from `start` and reports the moves that scored at least
+/// `threshold` below the best move available.
+///
+/// Replay stops at the first illegal move.
+pub fn find_blunders(
+    start: &GameEngine,
+    moves: &[Move],
+    depth: u8,
+    threshold: i32,
+) -> Vec<Blunder> {
+    find_blunders_with(start, moves, depth, threshold, &Weights::default())
+}
+
+/// Like `find_blunders`, scoring positions with the given evaluator.
+pub fn find_blunders_with<E: Evaluator + ?Sized>(
+    start: &GameEngine,
+    moves: &[Move],
+    depth: u8,
+    threshold: i32,
+    evaluator: &E,
+) -> Vec<Blunder> {
+    let mut engine = start.clone();
+    let mut blunders = Vec::new();
+
+    for (ply, played) in moves.iter().enumerate() {
+        let analysis = analyze_with(&engine, depth, evaluator);
+        let (best, score) = match (analysis.moves.first(), analysis.score_of(played)) {
+            (Some(best), Some(score)) => (best, score),
+            _ => break,
+        };
+
+        if best.score - score >= threshold {
+            blunders.push(Blunder {
+                ply,
+                played: *played,
+                best: best.movement,
+                loss: best.score - score,
+            });
+        }
+
+        let _ = engine.move_piece(played);
+    }
+
+    blunders
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::ai::Weights;
+    use super::super::board::{Coordinate, GamePiece, Move, PieceColor};
+    use super::super::game::GameEngine;
+    use super::{analyze, find_blunders, find_blunders_with, BLUNDER_THRESHOLD};
+
+    /// After 11-15 22-18, Black can take the man on 18.
+    fn capture_position() -> GameEngine {
+        let mut engine = GameEngine::new();
+        engine.move_piece(&Move::new((2, 5), (3, 4))).unwrap();
+        engine.move_piece(&Move::new((5, 2), (4, 3))).unwrap();
+        engine
+    }
+
+    #[test]
+    fn ranks_every_legal_move() {
+        let engine = capture_position();
+        let analysis = analyze(&engine, 1);
+
+        assert_eq!(analysis.moves.len(), engine.valid_moves().len());
+        assert_eq!(analysis.best_move(), Some(Move::new((3, 4), (5, 2))));
+        assert_eq!(analysis.score, analysis.moves[0].score);
+        assert!(analysis
+            .moves
+            .windows(2)
+            .all(|pair| pair[0].score >= pair[1].score));
+    }
+
+    #[test]
+    fn lost_position_has_no_moves() {
+        let engine = GameEngine::from_position(&[], PieceColor::Black);
+        let analysis = analyze(&engine, 2);
+
+        assert!(analysis.moves.is_empty());
+        assert_eq!(analysis.best_move(), None);
+        assert!(analysis.score < 0);
+    }
+
+    #[test]
+    fn flags_missed_capture() {
+        let start = GameEngine::from_position(
+            &[
+                (
+                    Coordinate(4, 5),
+                    GamePiece::crown(GamePiece::new(PieceColor::Black)),
+                ),
+                (Coordinate(3, 4), GamePiece::new(PieceColor::White)),
+                (Coordinate(6, 3), GamePiece::new(PieceColor::White)),
+            ],
+            PieceColor::Black,
+        );
+        let moves = [
+            // Ignores the man on 15 and walks into a capture.
+            Move::new((4, 5), (5, 4)),
+            Move::new((6, 3), (4, 5)),
+        ];
+        let blunders = find_blunders(&start, &moves, 2, BLUNDER_THRESHOLD);
+
+        assert_eq!(blunders.len(), 1);
+        assert_eq!(blunders[0].ply, 0);
+        assert_eq!(blunders[0].best, Move::new((4, 5), (2, 3)));
+        assert!(blunders[0].loss >= BLUNDER_THRESHOLD);
+    }
+
+    #[test]
+    fn blunders_follow_the_evaluator() {
+        let start = GameEngine::from_position(
+            &[
+                (Coordinate(0, 7), GamePiece::new(PieceColor::Black)),
+                (Coordinate(6, 5), GamePiece::new(PieceColor::Black)),
+                (Coordinate(1, 0), GamePiece::new(PieceColor::White)),
+            ],
+            PieceColor::Black,
+        );
+        // Leaves the back row while the other man could have moved.
+        let moves = [Move::new((0, 7), (1, 6))];
+        let guarding = Weights {
+            back_row: 2 * BLUNDER_THRESHOLD,
+            ..Weights::default()
+        };
+
+        assert!(find_blunders(&start, &moves, 2, BLUNDER_THRESHOLD).is_empty());
+
+        let blunders = find_blunders_with(&start, &moves, 2, BLUNDER_THRESHOLD, &guarding);
+        assert_eq!(blunders.len(), 1);
+        assert_eq!(blunders[0].best.from, Coordinate(6, 5));
+    }
+}
@@ -0,0 +1,101 @@
+use std::io::{self, BufReader};
+use std::net::TcpListener;
+use std::process;
+
+use rustycheckers::board::PieceColor;
+use rustycheckers::protocol::dxp::{Connection, Outcome, Player};
+use rustycheckers::protocol::hub::{self, DEFAULT_DEPTH, ENGINE_NAME};
+
+const USAGE: &str = "\
+Usage:
+    checkers_engine hub [--depth N]
+    checkers_engine dxp-listen <address> [--depth N]
+    checkers_engine dxp-connect <address> [--depth N] [--color black|white]";
+
+struct Options {
+    mode: String,
+    address: Option<String>,
+    depth: u8,
+    color: PieceColor,
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut args = args.iter();
+    let mode = args.next().ok_or("missing mode")?.clone();
+
+    let mut options = Options {
+        mode,
+        address: None,
+        depth: DEFAULT_DEPTH,
+        color: PieceColor::Black,
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--depth" => {
+                options.depth = args
+                    .next()
+                    .and_then(|depth| depth.parse().ok())
+                    .ok_or("--depth needs a number")?;
+            }
+            "--color" => {
+                options.color = match args.next().map(String::as_str) {
+                    Some("black") => PieceColor::Black,
+                    Some("white") => PieceColor::White,
+                    _ => return Err("--color needs black or white".to_owned()),
+                };
+            }
+            address if options.address.is_none() => options.address = Some(address.to_owned()),
+            other => return Err(format!("unexpected argument {}", other)),
+        }
+    }
+
+    Ok(options)
+}
+
+fn report(outcome: Outcome) {
+    eprintln!("Game over: {:?}", outcome);
+}
+
+fn run(options: Options) -> io::Result<()> {
+    let player = Player::new(ENGINE_NAME, options.depth);
+
+    match (options.mode.as_str(), options.address) {
+        ("hub", _) => {
+            let stdin = io::stdin();
+            hub::run(BufReader::new(stdin.lock()), io::stdout(), options.depth)
+        }
+        ("dxp-listen", Some(address)) => {
+            let listener = TcpListener::bind(address)?;
+            let mut connection = Connection::accept(&listener)?;
+            report(connection.play_as_follower(&player)?);
+            Ok(())
+        }
+        ("dxp-connect", Some(address)) => {
+            let mut connection = Connection::connect(address)?;
+            report(connection.play_as_initiator(&player, options.color)?);
+            Ok(())
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+
+    if let Err(error) = run(options) {
+        eprintln!("Error: {}", error);
+        process::exit(1);
+    }
+}
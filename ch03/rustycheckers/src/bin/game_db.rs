@@ -0,0 +1,208 @@
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, ErrorKind};
+use std::process;
+
+use rustycheckers::database::{Database, Tally};
+use rustycheckers::game::GameEngine;
+use rustycheckers::protocol::{format_move, parse_move, parse_position};
+
+const USAGE: &str = "\
+Usage:
+    game_db import DB FILE.pdn [...]
+    game_db explore DB [MOVE ...]
+    game_db games DB [MOVE ...] [--limit N]
+    game_db show DB ID
+
+DB is created by `import` if it does not exist. The position is given by
+the moves leading to it from the start (e.g. `11-15 22-18`), or with
+`--position` followed by a Hub position string. Results are listed as
+Black wins / draws / White wins.";
+
+enum Command {
+    Import(Vec<String>),
+    Explore(GameEngine),
+    Games(GameEngine, usize),
+    Show(usize),
+}
+
+struct Options {
+    database: String,
+    command: Command,
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: Option<&String>) -> Result<T, String> {
+    value
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| format!("{} needs a number", flag))
+}
+
+/// Parses the position arguments and any `--limit`.
+fn parse_position_args(args: &[String]) -> Result<(GameEngine, usize), String> {
+    let mut engine = GameEngine::new();
+    let mut limit = 20;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--limit" => limit = parse_number(arg, args.next())?,
+            "--position" => {
+                let position = args.next().ok_or("--position needs a position string")?;
+                engine = parse_position(position)
+                    .ok_or_else(|| format!("bad position string {}", position))?;
+            }
+            notation => {
+                let movement = parse_move(&engine, notation)
+                    .ok_or_else(|| format!("illegal move {}", notation))?;
+                engine
+                    .move_piece(&movement)
+                    .map_err(|_| format!("illegal move {}", notation))?;
+            }
+        }
+    }
+
+    Ok((engine, limit))
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let (command, database, rest) = match args {
+        [command, database, rest @ ..] => (command.as_str(), database.clone(), rest),
+        _ => return Err("a command and a database are needed".to_owned()),
+    };
+
+    let command = match command {
+        "import" if !rest.is_empty() => Command::Import(rest.to_vec()),
+        "import" => return Err("import needs at least one PDN file".to_owned()),
+        "explore" => Command::Explore(parse_position_args(rest)?.0),
+        "games" => {
+            let (engine, limit) = parse_position_args(rest)?;
+            Command::Games(engine, limit)
+        }
+        "show" => Command::Show(parse_number("show", rest.first())?),
+        other => return Err(format!("unknown command {}", other)),
+    };
+
+    Ok(Options { database, command })
+}
+
+fn open(path: &str, create: bool) -> Result<Database, String> {
+    match File::open(path) {
+        Ok(file) => Database::load(&mut BufReader::new(file))
+            .map_err(|error| format!("Could not read {}: {}", path, error)),
+        Err(error) if create && error.kind() == ErrorKind::NotFound => Ok(Database::new()),
+        Err(error) => Err(format!("Could not open {}: {}", path, error)),
+    }
+}
+
+fn save(database: &Database, path: &str) -> Result<(), String> {
+    File::create(path)
+        .and_then(|file| database.save(&mut BufWriter::new(file)))
+        .map_err(|error| format!("Could not write {}: {}", path, error))
+}
+
+fn format_tally(tally: &Tally) -> String {
+    format!(
+        "{:>6} {:>5} {:>5} {:>5}",
+        tally.games(),
+        tally.black_wins,
+        tally.draws,
+        tally.white_wins
+    )
+}
+
+fn run(options: Options) -> Result<(), String> {
+    let path = &options.database;
+
+    match options.command {
+        Command::Import(files) => {
+            let mut database = open(path, true)?;
+
+            for file in files {
+                let text = fs::read_to_string(&file)
+                    .map_err(|error| format!("Could not read {}: {}", file, error))?;
+                let summary = database
+                    .import_pdn(&text)
+                    .map_err(|error| format!("{}: {}", file, error))?;
+
+                println!(
+                    "{}: {} imported, {} duplicates, {} rejected",
+                    file,
+                    summary.imported,
+                    summary.duplicates,
+                    summary.rejected.len()
+                );
+                for (index, reason) in summary.rejected {
+                    eprintln!("{}: game {}: {}", file, index + 1, reason);
+                }
+            }
+
+            save(&database, path)?;
+            println!(
+                "{} games, {} positions",
+                database.len(),
+                database.positions()
+            );
+        }
+        Command::Explore(engine) => {
+            let database = open(path, false)?;
+            let explorer = database.explore(engine.position_hash());
+
+            println!(
+                "{:<8} {:>6} {:>5} {:>5} {:>5}",
+                "Move", "Games", "Black", "Draw", "White"
+            );
+            for continuation in &explorer.continuations {
+                println!(
+                    "{:<8} {}",
+                    format_move(&continuation.movement),
+                    format_tally(&continuation.results)
+                );
+            }
+            println!("{:<8} {}", "Total", format_tally(&explorer.results));
+        }
+        Command::Games(engine, limit) => {
+            let database = open(path, false)?;
+            let games = database.games_reaching(engine.position_hash());
+
+            for &id in games.iter().take(limit) {
+                let record = database.game(id).ok_or("corrupt index")?;
+                println!(
+                    "{:>6}  {} - {}  {}  {} ({})",
+                    id,
+                    record.tag("Black").unwrap_or("?"),
+                    record.tag("White").unwrap_or("?"),
+                    record.result.as_str(),
+                    record.tag("Event").unwrap_or("?"),
+                    record.tag("Date").unwrap_or("?")
+                );
+            }
+            if games.len() > limit {
+                println!("... and {} more", games.len() - limit);
+            }
+        }
+        Command::Show(id) => {
+            let database = open(path, false)?;
+            let record = database.game(id).ok_or_else(|| format!("no game {}", id))?;
+
+            print!("{}", record.to_pdn());
+        }
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+
+    if let Err(message) = run(options) {
+        eprintln!("{}", message);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,119 @@
+use std::fs;
+use std::process;
+
+use rustycheckers::tournament::{self, Contestant, Settings, Strategy};
+
+const USAGE: &str = "\
+Usage:
+    tournament --engine NAME=SPEC --engine NAME=SPEC [...] [options]
+
+SPEC is a comma-separated list such as `depth=4,king=200` (alpha-beta with
+man, king, advancement and back_row weights), `depth=4,network=FILE`
+(alpha-beta with a network from `train_network`, or `embedded` for the
+built-in one) or `mcts=2000` (playouts).
+
+Options:
+    --openings N     play at most N of the 49 two-ply openings
+    --max-plies N    draw games still going after N plies (default 200)
+    --seed N         seed for Monte Carlo searches (default 1)
+    --pdn FILE       write every game to FILE as PDN";
+
+struct Options {
+    contestants: Vec<Contestant>,
+    settings: Settings,
+    pdn: Option<String>,
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: Option<&String>) -> Result<T, String> {
+    value
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| format!("{} needs a number", flag))
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        contestants: Vec::new(),
+        settings: Settings::default(),
+        pdn: None,
+    };
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--engine" => {
+                let (name, spec) = args
+                    .next()
+                    .and_then(|engine| engine.split_once('='))
+                    .ok_or("--engine needs NAME=SPEC")?;
+
+                options.contestants.push(Contestant {
+                    name: name.to_owned(),
+                    strategy: Strategy::parse(spec)?,
+                });
+            }
+            "--openings" => options.settings.openings = parse_number(arg, args.next())?,
+            "--max-plies" => options.settings.max_plies = parse_number(arg, args.next())?,
+            "--seed" => options.settings.seed = parse_number(arg, args.next())?,
+            "--pdn" => options.pdn = Some(args.next().ok_or("--pdn needs a file")?.clone()),
+            other => return Err(format!("unexpected argument {}", other)),
+        }
+    }
+
+    if options.contestants.len() < 2 {
+        return Err("at least two engines are needed".to_owned());
+    }
+
+    Ok(options)
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+
+    let contestants = &options.contestants;
+    let games = tournament::round_robin(contestants, &options.settings, |game| {
+        println!(
+            "Round {:>4}: {} - {} {} ({:?}, {} plies)",
+            game.record.tag("Round").unwrap_or("?"),
+            contestants[game.black].name,
+            contestants[game.white].name,
+            game.record.result.as_str(),
+            game.termination,
+            game.record.moves.len()
+        );
+    });
+
+    println!();
+    println!(
+        "{:<20} {:>5} {:>5} {:>5} {:>5} {:>7} {:>14}",
+        "Engine", "Games", "Won", "Drawn", "Lost", "Score", "Elo"
+    );
+
+    for standing in tournament::standings(contestants, &games) {
+        println!(
+            "{:<20} {:>5} {:>5} {:>5} {:>5} {:>7.1} {:>+7.0} +/- {:<3.0}",
+            standing.name,
+            standing.games,
+            standing.wins,
+            standing.draws,
+            standing.losses,
+            standing.score,
+            standing.elo,
+            standing.elo_error
+        );
+    }
+
+    if let Some(path) = options.pdn {
+        if let Err(error) = fs::write(&path, tournament::to_pdn(&games)) {
+            eprintln!("Could not write {}: {}", path, error);
+            process::exit(1);
+        }
+    }
+}
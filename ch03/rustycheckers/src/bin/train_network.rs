@@ -0,0 +1,146 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::process;
+
+use rustycheckers::ai::Weights;
+use rustycheckers::mcts::Rng;
+use rustycheckers::network::{self, Network, SelfPlay, DEFAULT_HIDDEN, MAX_HIDDEN};
+use rustycheckers::tournament::Strategy;
+
+const USAGE: &str = "\
+Usage:
+    train_network --out FILE [options]
+
+Plays games against itself, then fits a network to predict each game's
+result from its positions. Games are played and adjudicated under the
+tournament rules. The first generation plays with the material evaluation;
+later ones play with the network trained so far.
+
+Compare the result with the material evaluation using, for example:
+    tournament --engine material=depth=4 --engine net=depth=4,network=FILE
+
+Options:
+    --games N         self-play games per generation (default 200)
+    --depth N         search depth for self-play moves (default 2)
+    --generations N   rounds of self-play and training (default 1)
+    --epochs N        training passes per generation (default 20)
+    --rate X          learning rate (default 0.01)
+    --hidden N        hidden layer size, at most 1024 (default 32)
+    --init FILE       continue training the network in FILE
+    --seed N          seed for self-play and training (default 1)";
+
+struct Options {
+    out: String,
+    init: Option<String>,
+    self_play: SelfPlay,
+    depth: u8,
+    generations: usize,
+    epochs: usize,
+    rate: f32,
+    hidden: usize,
+    seed: u64,
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: Option<&String>) -> Result<T, String> {
+    value
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| format!("{} needs a number", flag))
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut out = None;
+    let mut options = Options {
+        out: String::new(),
+        init: None,
+        self_play: SelfPlay::default(),
+        depth: 2,
+        generations: 1,
+        epochs: 20,
+        rate: 0.01,
+        hidden: DEFAULT_HIDDEN,
+        seed: 1,
+    };
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--out" => out = Some(args.next().ok_or("--out needs a file")?.clone()),
+            "--init" => options.init = Some(args.next().ok_or("--init needs a file")?.clone()),
+            "--games" => options.self_play.games = parse_number(arg, args.next())?,
+            "--depth" => options.depth = parse_number(arg, args.next())?,
+            "--generations" => options.generations = parse_number(arg, args.next())?,
+            "--epochs" => options.epochs = parse_number(arg, args.next())?,
+            "--rate" => options.rate = parse_number(arg, args.next())?,
+            "--hidden" => options.hidden = parse_number(arg, args.next())?,
+            "--seed" => options.seed = parse_number(arg, args.next())?,
+            other => return Err(format!("unexpected argument {}", other)),
+        }
+    }
+
+    options.out = out.ok_or("--out is needed")?;
+    if options.hidden == 0 || options.hidden > MAX_HIDDEN {
+        return Err(format!("--hidden must be from 1 to {}", MAX_HIDDEN));
+    }
+
+    Ok(options)
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+
+    let mut rng = Rng::new(options.seed);
+    let (mut network, mut trained) = match options.init {
+        Some(ref path) => {
+            let network = File::open(path)
+                .and_then(|file| Network::load(&mut BufReader::new(file)))
+                .unwrap_or_else(|error| {
+                    eprintln!("Could not read {}: {}", path, error);
+                    process::exit(1);
+                });
+            (network, true)
+        }
+        None => (Network::new(options.hidden, &mut rng), false),
+    };
+
+    for generation in 1..=options.generations {
+        let strategy = if trained {
+            Strategy::Network {
+                depth: options.depth,
+                network: network.clone(),
+            }
+        } else {
+            Strategy::AlphaBeta {
+                depth: options.depth,
+                weights: Weights::default(),
+            }
+        };
+        let samples = network::self_play(&options.self_play, &strategy, &mut rng);
+        println!(
+            "Generation {}: {} positions from {} games",
+            generation,
+            samples.len(),
+            options.self_play.games
+        );
+
+        for epoch in 1..=options.epochs {
+            let error = network.train_epoch(&samples, options.rate, &mut rng);
+            println!("    epoch {:>3}: mean squared error {:.4}", epoch, error);
+        }
+        trained = true;
+    }
+
+    let written =
+        File::create(&options.out).and_then(|file| network.save(&mut BufWriter::new(file)));
+    if let Err(error) = written {
+        eprintln!("Could not write {}: {}", options.out, error);
+        process::exit(1);
+    }
+}
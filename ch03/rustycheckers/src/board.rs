@@ -1,14 +1,47 @@
-use std::cmp::Ordering;
-
 pub const START_INDEX: usize = 0;
 pub const END_INDEX: usize = 7;
 
+/// The number of playable (dark) squares on the board.
+pub const SQUARE_COUNT: u8 = 32;
+
 #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
 pub enum PieceColor {
     Black,
     White,
 }
 
+impl PieceColor {
+    /// Returns the opposing color.
+    pub fn opponent(self) -> PieceColor {
+        match self {
+            PieceColor::Black => PieceColor::White,
+            PieceColor::White => PieceColor::Black,
+        }
+    }
+
+    /// The row this color's men start from. Black's is y = 7, so its men
+    /// move toward y = 0; White's is y = 0.
+    pub fn home_row(self) -> usize {
+        match self {
+            PieceColor::Black => END_INDEX,
+            PieceColor::White => START_INDEX,
+        }
+    }
+
+    /// The row where this color's men are crowned: the opponent's home row.
+    pub fn crowning_row(self) -> usize {
+        self.opponent().home_row()
+    }
+
+    /// The sign of the change in y when this color's men move.
+    pub fn forward(self) -> i8 {
+        match self {
+            PieceColor::Black => -1,
+            PieceColor::White => 1,
+        }
+    }
+}
+
 pub struct Delta {
     pub x: i8,
     pub y: i8,
@@ -47,6 +80,100 @@ impl GamePiece {
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct Coordinate(pub usize, pub usize);
 
+/// Why a value could not be converted to a coordinate, square or move.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CoordinateError {
+    /// The coordinate is not on the board.
+    OffBoard,
+    /// Only the dark squares are numbered or played on.
+    LightSquare,
+    /// A square number outside 1-32.
+    BadSquareNumber,
+    /// Text that is not a square in algebraic notation.
+    BadNotation,
+    /// Bits set outside a packed move's encoding.
+    BadEncoding,
+}
+
+/// Converts untrusted coordinates, such as those passed over the FFI.
+/// Negative values and values past the edge are rejected rather than
+/// wrapped.
+impl TryFrom<(i32, i32)> for Coordinate {
+    type Error = CoordinateError;
+
+    fn try_from((x, y): (i32, i32)) -> Result<Coordinate, CoordinateError> {
+        let x = usize::try_from(x).map_err(|_| CoordinateError::OffBoard)?;
+        let y = usize::try_from(y).map_err(|_| CoordinateError::OffBoard)?;
+        let location = Coordinate(x, y);
+
+        if location.valid() {
+            Ok(location)
+        } else {
+            Err(CoordinateError::OffBoard)
+        }
+    }
+}
+
+impl TryFrom<&str> for Coordinate {
+    type Error = CoordinateError;
+
+    fn try_from(notation: &str) -> Result<Coordinate, CoordinateError> {
+        Coordinate::from_algebraic(notation)
+    }
+}
+
+/// A standard checkers square number, 1-32. See `Coordinate::to_square`.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Square(u8);
+
+impl Square {
+    pub fn number(self) -> u8 {
+        self.0
+    }
+}
+
+impl TryFrom<u8> for Square {
+    type Error = CoordinateError;
+
+    fn try_from(number: u8) -> Result<Square, CoordinateError> {
+        if number == 0 || number > SQUARE_COUNT {
+            Err(CoordinateError::BadSquareNumber)
+        } else {
+            Ok(Square(number))
+        }
+    }
+}
+
+impl TryFrom<Coordinate> for Square {
+    type Error = CoordinateError;
+
+    fn try_from(location: Coordinate) -> Result<Square, CoordinateError> {
+        let Coordinate(x, y) = location;
+
+        if !location.valid() {
+            return Err(CoordinateError::OffBoard);
+        }
+        if (x + y) % 2 == 0 {
+            return Err(CoordinateError::LightSquare);
+        }
+
+        let row = END_INDEX - y;
+        let column = END_INDEX - x;
+
+        Ok(Square((row * 4 + column / 2 + 1) as u8))
+    }
+}
+
+impl From<Square> for Coordinate {
+    fn from(square: Square) -> Coordinate {
+        let index = (square.0 - 1) as usize;
+        let row = index / 4;
+        let column = (index % 4) * 2 + if row.is_multiple_of(2) { 1 } else { 0 };
+
+        Coordinate(END_INDEX - column, END_INDEX - row)
+    }
+}
+
 impl Coordinate {
     pub fn valid(self) -> bool {
         let Coordinate(x, y) = self;
@@ -86,29 +213,62 @@ impl Coordinate {
         jumps.into_iter()
     }
 
-    pub fn hash(&self) -> String {
-        format!("{},{}", self.0, self.1)
+    /// Returns the standard checkers square number (1-32) for a coordinate.
+    ///
+    /// Squares are numbered from Black's back row (1-4) to White's back row
+    /// (29-32), reading each row from Black's right-hand side. Returns `None`
+    /// for light squares and coordinates off the board.
+    pub fn to_square(self) -> Option<u8> {
+        Square::try_from(self).ok().map(Square::number)
     }
 
-    pub fn cmp(&self, other: &Coordinate) -> Ordering {
-        let Coordinate(a_x, a_y) = self;
-        let Coordinate(b_x, b_y) = other;
+    /// Returns the coordinate for a standard checkers square number (1-32).
+    pub fn from_square(square: u8) -> Option<Coordinate> {
+        Square::try_from(square).ok().map(Coordinate::from)
+    }
 
-        if a_x > b_x {
-            Ordering::Greater
-        } else if a_x < b_x {
-            Ordering::Less
-        } else if a_y == b_y {
-            Ordering::Equal
-        } else if a_y > b_y {
-            Ordering::Greater
-        } else {
-            Ordering::Less
+    /// Returns the coordinate in algebraic notation, from `a1` in White's
+    /// left-hand corner (square 29) to `h8` in Black's (square 4).
+    pub fn to_algebraic(self) -> Option<String> {
+        let Coordinate(x, y) = self;
+
+        if !self.valid() {
+            return None;
+        }
+
+        let file = (b'a' + (END_INDEX - x) as u8) as char;
+        let rank = (b'1' + y as u8) as char;
+        Some(format!("{}{}", file, rank))
+    }
+
+    /// Parses a coordinate in algebraic notation, such as `b8`.
+    pub fn from_algebraic(notation: &str) -> Result<Coordinate, CoordinateError> {
+        match notation.as_bytes() {
+            [file @ b'a'..=b'h', rank @ b'1'..=b'8'] => Ok(Coordinate(
+                END_INDEX - (file - b'a') as usize,
+                (rank - b'1') as usize,
+            )),
+            _ => Err(CoordinateError::BadNotation),
+        }
+    }
+
+    /// Converts between the board's coordinates and the coordinates a
+    /// player sees from their own side, with their home row at y = 7.
+    ///
+    /// That is Black's view of the board already; White's view is the board
+    /// turned half way round. Converting twice gives back the original, and
+    /// coordinates off the board stay off it.
+    pub fn seen_by(self, player: PieceColor) -> Coordinate {
+        let Coordinate(x, y) = self;
+
+        match player {
+            PieceColor::Black => self,
+            PieceColor::White => Coordinate(END_INDEX.wrapping_sub(x), END_INDEX.wrapping_sub(y)),
         }
     }
 }
 
-#[derive(Clone, Copy, Eq, Debug, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Copy, Eq, Debug, Hash, Ord, PartialEq, PartialOrd)]
 pub struct Move {
     pub from: Coordinate,
     pub to: Coordinate,
@@ -122,17 +282,162 @@ impl Move {
         }
     }
 
-    pub fn cmp(&self, other: &Move) -> Ordering {
-        if self.from > other.from {
-            Ordering::Greater
-        } else if self.from < other.from {
-            Ordering::Less
-        } else if self.to > other.to {
-            Ordering::Greater
-        } else if self.to < other.to {
-            Ordering::Less
-        } else {
-            Ordering::Equal
+    /// Converts a move like `Coordinate::seen_by`.
+    pub fn seen_by(self, player: PieceColor) -> Move {
+        Move {
+            from: self.from.seen_by(player),
+            to: self.to.seen_by(player),
+        }
+    }
+
+    /// Returns the coordinate passed over by a jump, or `None` if the move
+    /// is not a jump.
+    pub fn jumped(&self) -> Option<Coordinate> {
+        let Coordinate(from_x, from_y) = self.from;
+        let Coordinate(to_x, to_y) = self.to;
+
+        if from_x.abs_diff(to_x) != 2 || from_y.abs_diff(to_y) != 2 {
+            return None;
         }
+
+        Some(Coordinate((from_x + to_x) / 2, (from_y + to_y) / 2))
+    }
+}
+
+/// Mask of the bits used by a packed move.
+const PACKED_MOVE_BITS: u16 = 0x0FFF;
+
+/// Packs a move into 12 bits: six for the square each end is on, counted
+/// `y * 8 + x`, with the origin in the high bits. Both ends must be dark
+/// squares on the board.
+impl TryFrom<Move> for u16 {
+    type Error = CoordinateError;
+
+    fn try_from(movement: Move) -> Result<u16, CoordinateError> {
+        let index = |location: Coordinate| -> Result<u16, CoordinateError> {
+            let Coordinate(x, y) = location;
+            Square::try_from(location)?;
+            Ok((y * 8 + x) as u16)
+        };
+
+        Ok(index(movement.from)? << 6 | index(movement.to)?)
+    }
+}
+
+impl TryFrom<u16> for Move {
+    type Error = CoordinateError;
+
+    fn try_from(packed: u16) -> Result<Move, CoordinateError> {
+        if packed & !PACKED_MOVE_BITS != 0 {
+            return Err(CoordinateError::BadEncoding);
+        }
+
+        let location = |index: u16| -> Result<Coordinate, CoordinateError> {
+            let location = Coordinate((index % 8) as usize, (index / 8) as usize);
+            Square::try_from(location)?;
+            Ok(location)
+        };
+
+        Ok(Move {
+            from: location(packed >> 6)?,
+            to: location(packed & 0x3F)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Coordinate, CoordinateError, Move, PieceColor, Square};
+
+    #[test]
+    fn converts_squares_and_algebraic() {
+        for number in 1..=32 {
+            let square = Square::try_from(number).unwrap();
+            let location = Coordinate::from(square);
+
+            assert_eq!(Square::try_from(location), Ok(square));
+            let algebraic = location.to_algebraic().unwrap();
+            assert_eq!(Coordinate::try_from(algebraic.as_str()), Ok(location));
+        }
+
+        assert_eq!(
+            Coordinate::from_square(1).unwrap().to_algebraic().unwrap(),
+            "b8"
+        );
+        assert_eq!(
+            Coordinate::from_square(29).unwrap().to_algebraic().unwrap(),
+            "a1"
+        );
+        assert_eq!(Square::try_from(0), Err(CoordinateError::BadSquareNumber));
+        assert_eq!(Square::try_from(33), Err(CoordinateError::BadSquareNumber));
+        assert_eq!(
+            Square::try_from(Coordinate(0, 0)),
+            Err(CoordinateError::LightSquare)
+        );
+        assert_eq!(
+            Coordinate::from_algebraic("i1"),
+            Err(CoordinateError::BadNotation)
+        );
+        assert_eq!(
+            Coordinate::from_algebraic("a10"),
+            Err(CoordinateError::BadNotation)
+        );
+    }
+
+    #[test]
+    fn rejects_untrusted_coordinates() {
+        assert_eq!(Coordinate::try_from((7, 0)), Ok(Coordinate(7, 0)));
+        assert_eq!(
+            Coordinate::try_from((-1, 0)),
+            Err(CoordinateError::OffBoard)
+        );
+        assert_eq!(Coordinate::try_from((0, 8)), Err(CoordinateError::OffBoard));
+        assert_eq!(
+            Coordinate::try_from((i32::MIN, i32::MAX)),
+            Err(CoordinateError::OffBoard)
+        );
+    }
+
+    #[test]
+    fn flips_perspective() {
+        let location = Coordinate(1, 2);
+
+        assert_eq!(location.seen_by(PieceColor::Black), location);
+        assert_eq!(location.seen_by(PieceColor::White), Coordinate(6, 5));
+        assert_eq!(
+            location
+                .seen_by(PieceColor::White)
+                .seen_by(PieceColor::White),
+            location
+        );
+        assert!(!Coordinate(8, 0).seen_by(PieceColor::White).valid());
+
+        // Each side's men head for the row its opponent starts from, which
+        // is the far row in that side's own view.
+        for color in [PieceColor::Black, PieceColor::White] {
+            let home = Coordinate(0, color.home_row()).seen_by(color);
+            let crowning = Coordinate(0, color.crowning_row()).seen_by(color);
+            assert_eq!((home.1, crowning.1), (7, 0));
+            assert_eq!(color.forward(), -color.opponent().forward());
+        }
+    }
+
+    #[test]
+    fn packs_moves() {
+        let movement = Move::new((2, 5), (3, 4));
+        let packed = u16::try_from(movement).unwrap();
+
+        assert_eq!(packed, (5 * 8 + 2) << 6 | (4 * 8 + 3));
+        assert_eq!(Move::try_from(packed), Ok(movement));
+        assert_eq!(
+            u16::try_from(Move::new((0, 0), (1, 1))),
+            Err(CoordinateError::LightSquare)
+        );
+        assert_eq!(
+            u16::try_from(Move::new((9, 0), (1, 1))),
+            Err(CoordinateError::OffBoard)
+        );
+        assert_eq!(Move::try_from(0x1000), Err(CoordinateError::BadEncoding));
+        assert_eq!(Move::try_from(0), Err(CoordinateError::LightSquare));
     }
 }
@@ -0,0 +1,465 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Read, Write};
+
+use super::board::Move;
+use super::game::GameEngine;
+use super::game_tree::GameTree;
+use super::pdn::{split_games, tokenize, GameRecord, GameResult};
+
+/// Identifies the file format; bump `FORMAT_VERSION` on any layout change.
+const MAGIC: &[u8; 4] = b"RCDB";
+const FORMAT_VERSION: u8 = 2;
+
+/// Index of a game in the database.
+pub type GameId = usize;
+
+/// A game passing through a position: the game and the number of moves
+/// played before it got there.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Occurrence {
+    pub game: GameId,
+    pub ply: usize,
+}
+
+/// Game results tallied from Black's side, as PDN writes them.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Tally {
+    pub black_wins: usize,
+    pub white_wins: usize,
+    pub draws: usize,
+    pub unfinished: usize,
+}
+
+impl Tally {
+    fn add(&mut self, result: GameResult) {
+        match result {
+            GameResult::BlackWins => self.black_wins += 1,
+            GameResult::WhiteWins => self.white_wins += 1,
+            GameResult::Draw => self.draws += 1,
+            GameResult::Unfinished => self.unfinished += 1,
+        }
+    }
+
+    pub fn games(&self) -> usize {
+        self.black_wins + self.white_wins + self.draws + self.unfinished
+    }
+}
+
+/// A move played from a position, with how the games that played it ended.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Continuation {
+    pub movement: Move,
+    pub results: Tally,
+}
+
+/// What the database knows about a position.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Explorer {
+    /// Results of every game that reached the position.
+    pub results: Tally,
+    /// Moves played next, most popular first.
+    pub continuations: Vec<Continuation>,
+}
+
+/// The outcome of importing a PDN collection.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ImportSummary {
+    pub imported: usize,
+    /// Games whose moves were already in the database.
+    pub duplicates: usize,
+    /// Games that could not be read, by position in the file (counting
+    /// from zero), with the reason.
+    pub rejected: Vec<(usize, String)>,
+}
+
+/// A collection of games indexed by the Zobrist key of every position they
+/// pass through.
+///
+/// Games are replayed on `GameEngine` as they are added, so only games that
+/// are legal under this engine's rules are stored. The index is not saved;
+/// `load` rebuilds it by replaying the games.
+#[derive(Default)]
+pub struct Database {
+    games: Vec<GameRecord>,
+    index: HashMap<u64, Vec<Occurrence>>,
+    move_lists: HashSet<Vec<Move>>,
+}
+
+impl Database {
+    pub fn new() -> Database {
+        Database::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.games.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.games.is_empty()
+    }
+
+    pub fn game(&self, id: GameId) -> Option<&GameRecord> {
+        self.games.get(id)
+    }
+
+    /// Number of distinct positions indexed.
+    pub fn positions(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Adds a game, returning its id, or `None` if the same moves are
+    /// already stored. Fails if a move is illegal.
+    pub fn add_game(&mut self, record: GameRecord) -> Result<Option<GameId>, String> {
+        if self.move_lists.contains(&record.moves) {
+            return Ok(None);
+        }
+
+        let id = self.games.len();
+        let mut engine = GameEngine::new();
+        let mut hashes = vec![engine.position_hash()];
+
+        for (ply, movement) in record.moves.iter().enumerate() {
+            engine
+                .move_piece(movement)
+                .map_err(|_| format!("illegal move at ply {}", ply + 1))?;
+            hashes.push(engine.position_hash());
+        }
+
+        for (ply, hash) in hashes.into_iter().enumerate() {
+            self.index
+                .entry(hash)
+                .or_default()
+                .push(Occurrence { game: id, ply });
+        }
+
+        self.move_lists.insert(record.moves.clone());
+        self.games.push(record);
+        Ok(Some(id))
+    }
+
+    /// Imports every game in a PDN collection, keeping each game's main
+    /// line. Games that fail to parse or replay are skipped and reported.
+    pub fn import_pdn(&mut self, text: &str) -> Result<ImportSummary, String> {
+        let mut summary = ImportSummary::default();
+
+        for (index, tokens) in split_games(tokenize(text)?).into_iter().enumerate() {
+            let record = match GameTree::from_tokens(tokens) {
+                Ok(tree) => GameRecord {
+                    moves: tree.main_line(),
+                    tags: tree.tags,
+                    result: tree.result,
+                },
+                Err(message) => {
+                    summary.rejected.push((index, message));
+                    continue;
+                }
+            };
+
+            match self.add_game(record) {
+                Ok(Some(_id)) => summary.imported += 1,
+                Ok(None) => summary.duplicates += 1,
+                Err(message) => summary.rejected.push((index, message)),
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// Every time a game reached the position with this key.
+    pub fn occurrences(&self, hash: u64) -> &[Occurrence] {
+        self.index.get(&hash).map_or(&[], |occurrences| occurrences)
+    }
+
+    /// The games that reached the position, each listed once, in the order
+    /// they were added.
+    pub fn games_reaching(&self, hash: u64) -> Vec<GameId> {
+        let mut games: Vec<GameId> = self
+            .occurrences(hash)
+            .iter()
+            .map(|occurrence| occurrence.game)
+            .collect();
+
+        games.dedup();
+        games
+    }
+
+    /// Summarizes the games through a position and the moves played next.
+    pub fn explore(&self, hash: u64) -> Explorer {
+        let mut explorer = Explorer::default();
+        let mut seen = HashSet::new();
+
+        for game in self.games_reaching(hash) {
+            explorer.results.add(self.games[game].result);
+        }
+
+        for occurrence in self.occurrences(hash) {
+            let record = &self.games[occurrence.game];
+            let movement = match record.moves.get(occurrence.ply) {
+                Some(movement) => *movement,
+                None => continue,
+            };
+
+            // A game that comes back to the position and plays the same
+            // move again still counts once.
+            if !seen.insert((occurrence.game, movement)) {
+                continue;
+            }
+
+            let index = match explorer
+                .continuations
+                .iter()
+                .position(|continuation| continuation.movement == movement)
+            {
+                Some(index) => index,
+                None => {
+                    explorer.continuations.push(Continuation {
+                        movement,
+                        results: Tally::default(),
+                    });
+                    explorer.continuations.len() - 1
+                }
+            };
+            explorer.continuations[index].results.add(record.result);
+        }
+
+        explorer.continuations.sort_by(|a, b| {
+            b.results
+                .games()
+                .cmp(&a.results.games())
+                .then(a.movement.cmp(&b.movement))
+        });
+
+        explorer
+    }
+
+    /// Writes the games in the database's binary format.
+    pub fn save<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[FORMAT_VERSION])?;
+        let count = u32::try_from(self.games.len()).map_err(|_| invalid_data("too many games"))?;
+        writer.write_all(&count.to_le_bytes())?;
+
+        for record in &self.games {
+            writer.write_all(&[result_code(record.result)])?;
+
+            write_length(writer, record.tags.len(), "too many tags")?;
+            for (name, value) in &record.tags {
+                write_string(writer, name)?;
+                write_string(writer, value)?;
+            }
+
+            write_length(writer, record.moves.len(), "game too long")?;
+            for movement in &record.moves {
+                let packed = u16::try_from(*movement)
+                    .map_err(|_| invalid_data("move off the dark squares"))?;
+                writer.write_all(&packed.to_le_bytes())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads games written by `save`, replaying them to rebuild the index.
+    pub fn load<R: Read>(reader: &mut R) -> io::Result<Database> {
+        let mut header = [0; 5];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != MAGIC {
+            return Err(invalid_data("not a game database"));
+        }
+        if header[4] != FORMAT_VERSION {
+            return Err(invalid_data("unsupported database version"));
+        }
+
+        let mut database = Database::new();
+        let count = read_u32(reader)?;
+
+        for _game in 0..count {
+            let result = match read_u8(reader)? {
+                0 => GameResult::BlackWins,
+                1 => GameResult::WhiteWins,
+                2 => GameResult::Draw,
+                3 => GameResult::Unfinished,
+                _ => return Err(invalid_data("unknown result")),
+            };
+
+            let mut tags = Vec::new();
+            for _tag in 0..read_u16(reader)? {
+                tags.push((read_string(reader)?, read_string(reader)?));
+            }
+
+            let mut moves = Vec::new();
+            for _move in 0..read_u16(reader)? {
+                let movement = Move::try_from(read_u16(reader)?)
+                    .map_err(|_| invalid_data("bad move encoding"))?;
+                moves.push(movement);
+            }
+
+            database
+                .add_game(GameRecord {
+                    tags,
+                    moves,
+                    result,
+                })
+                .map_err(|message| invalid_data(&message))?;
+        }
+
+        Ok(database)
+    }
+}
+
+fn result_code(result: GameResult) -> u8 {
+    match result {
+        GameResult::BlackWins => 0,
+        GameResult::WhiteWins => 1,
+        GameResult::Draw => 2,
+        GameResult::Unfinished => 3,
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_owned())
+}
+
+/// Writes a count or length as a u16, failing with `message` if it does not
+/// fit.
+fn write_length<W: Write>(writer: &mut W, length: usize, message: &str) -> io::Result<()> {
+    let length = u16::try_from(length).map_err(|_| invalid_data(message))?;
+    writer.write_all(&length.to_le_bytes())
+}
+
+fn write_string<W: Write>(writer: &mut W, text: &str) -> io::Result<()> {
+    let bytes = text.as_bytes();
+
+    write_length(writer, bytes.len(), "tag too long")?;
+    writer.write_all(bytes)
+}
+
+fn read_u8<R: Read>(reader: &mut R) -> io::Result<u8> {
+    let mut bytes = [0; 1];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes[0])
+}
+
+fn read_u16<R: Read>(reader: &mut R) -> io::Result<u16> {
+    let mut bytes = [0; 2];
+    reader.read_exact(&mut bytes)?;
+    Ok(u16::from_le_bytes(bytes))
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_string<R: Read>(reader: &mut R) -> io::Result<String> {
+    let mut bytes = vec![0; read_u16(reader)? as usize];
+    reader.read_exact(&mut bytes)?;
+    String::from_utf8(bytes).map_err(|_| invalid_data("tag is not UTF-8"))
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+
+    use super::super::board::Move;
+    use super::super::game::GameEngine;
+    use super::super::pdn::{GameRecord, GameResult};
+    use super::Database;
+
+    const COLLECTION: &str = "\
+[Event \"a\"]\n1. 11-15 22-18 2. 15x22 25x18 1-0\n\n\
+[Event \"b\"]\n1. 11-15 22-18 2. 15x22 26x17 0-1\n\n\
+[Event \"c\"]\n1. 11-15 23-19 1/2-1/2\n\n\
+[Event \"duplicate\"]\n1. 11-15 23-19 1-0\n\n\
+[Event \"illegal\"]\n1. 11-15 11-15 *\n";
+
+    fn after(moves: &[Move]) -> u64 {
+        let mut engine = GameEngine::new();
+        for movement in moves {
+            engine.move_piece(movement).unwrap();
+        }
+        engine.position_hash()
+    }
+
+    #[test]
+    fn imports_collections() {
+        let mut database = Database::new();
+        let summary = database.import_pdn(COLLECTION).unwrap();
+
+        assert_eq!(summary.imported, 3);
+        assert_eq!(summary.duplicates, 1);
+        assert_eq!(summary.rejected.len(), 1);
+        assert_eq!(summary.rejected[0].0, 4);
+        assert_eq!(database.len(), 3);
+        assert_eq!(database.game(1).unwrap().tag("Event"), Some("b"));
+    }
+
+    #[test]
+    fn explores_positions() {
+        let mut database = Database::new();
+        database.import_pdn(COLLECTION).unwrap();
+
+        let start = database.explore(GameEngine::new().position_hash());
+        assert_eq!(start.results.games(), 3);
+        assert_eq!(start.continuations.len(), 1);
+        assert_eq!(start.continuations[0].movement, Move::new((2, 5), (3, 4)));
+
+        let explorer = database.explore(after(&[Move::new((2, 5), (3, 4))]));
+        assert_eq!(explorer.continuations.len(), 2);
+        // 22-18 was played twice, so it comes first.
+        let main = &explorer.continuations[0];
+        assert_eq!(main.movement, Move::new((5, 2), (4, 3)));
+        assert_eq!(main.results.black_wins, 1);
+        assert_eq!(main.results.white_wins, 1);
+        assert_eq!(explorer.continuations[1].results.draws, 1);
+
+        assert_eq!(
+            database.games_reaching(after(&[Move::new((0, 5), (1, 4))])),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn saves_and_loads() {
+        let mut database = Database::new();
+        database.import_pdn(COLLECTION).unwrap();
+
+        let mut bytes = Vec::new();
+        database.save(&mut bytes).unwrap();
+        let loaded = Database::load(&mut bytes.as_slice()).unwrap();
+
+        assert_eq!(loaded.len(), database.len());
+        assert_eq!(loaded.positions(), database.positions());
+        assert_eq!(loaded.game(2), database.game(2));
+        assert_eq!(loaded.game(0).unwrap().result, GameResult::BlackWins);
+
+        assert!(Database::load(&mut &b"PDN!\x01"[..]).is_err());
+    }
+
+    #[test]
+    fn refuses_to_save_oversized_games() {
+        let tag = ("Note".to_owned(), String::new());
+        let mut database = Database::new();
+        database
+            .add_game(GameRecord {
+                tags: vec![tag; u16::MAX as usize + 1],
+                moves: Vec::new(),
+                result: GameResult::Unfinished,
+            })
+            .unwrap();
+
+        let error = database.save(&mut Vec::new()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let mut database = Database::new();
+        database
+            .add_game(GameRecord {
+                tags: vec![("Note".to_owned(), "x".repeat(u16::MAX as usize + 1))],
+                moves: Vec::new(),
+                result: GameResult::Unfinished,
+            })
+            .unwrap();
+        assert!(database.save(&mut Vec::new()).is_err());
+    }
+}
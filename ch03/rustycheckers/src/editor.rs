@@ -0,0 +1,285 @@
+use super::board::{Coordinate, GamePiece, PieceColor, END_INDEX, START_INDEX};
+use super::game::GameEngine;
+
+/// Most pieces a side can have: the twelve it starts with.
+pub const MAX_PIECES: usize = 12;
+
+/// A problem with a position being set up.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SetupError {
+    /// The coordinate is not on the board.
+    OffBoard(Coordinate),
+    /// Pieces only stand on the dark squares.
+    LightSquare(Coordinate),
+    /// A side has more than `MAX_PIECES` pieces.
+    TooManyPieces(PieceColor),
+    /// A man stands on the row where it would already have been crowned.
+    ManOnCrowningRow(Coordinate),
+    /// A side has no pieces, so the game would already be over.
+    NoPieces(PieceColor),
+}
+
+impl SetupError {
+    /// The code for `OffBoard`, for coordinates that cannot even be
+    /// represented as a `Coordinate`.
+    pub const OFF_BOARD_CODE: i32 = 1;
+
+    /// A number identifying the kind of error, for the FFI. Never 0.
+    pub fn code(self) -> i32 {
+        match self {
+            SetupError::OffBoard(_) => SetupError::OFF_BOARD_CODE,
+            SetupError::LightSquare(_) => 2,
+            SetupError::TooManyPieces(_) => 3,
+            SetupError::ManOnCrowningRow(_) => 4,
+            SetupError::NoPieces(_) => 5,
+        }
+    }
+}
+
+/// A board being set up by hand, before play starts from it.
+///
+/// Pieces may only be placed on dark squares; everything else about the
+/// position is checked by `validate` so that a setup can pass through
+/// invalid states while it is being edited.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BoardEditor {
+    board: [[Option<GamePiece>; 8]; 8],
+    turn: PieceColor,
+}
+
+impl Default for BoardEditor {
+    fn default() -> BoardEditor {
+        BoardEditor::new()
+    }
+}
+
+impl BoardEditor {
+    /// Starts with an empty board and Black to move.
+    pub fn new() -> BoardEditor {
+        BoardEditor {
+            board: [[None; 8]; 8],
+            turn: PieceColor::Black,
+        }
+    }
+
+    /// Starts from the position in a game.
+    pub fn from_engine(engine: &GameEngine) -> BoardEditor {
+        let mut editor = BoardEditor::new();
+
+        for (Coordinate(x, y), piece) in engine.pieces() {
+            editor.board[x][y] = Some(piece);
+        }
+        editor.turn = engine.current_turn();
+
+        editor
+    }
+
+    pub fn piece(&self, location: Coordinate) -> Option<GamePiece> {
+        let Coordinate(x, y) = location;
+
+        if location.valid() {
+            self.board[x][y]
+        } else {
+            None
+        }
+    }
+
+    pub fn turn(&self) -> PieceColor {
+        self.turn
+    }
+
+    pub fn set_turn(&mut self, turn: PieceColor) {
+        self.turn = turn;
+    }
+
+    /// Removes every piece.
+    pub fn clear(&mut self) {
+        self.board = [[None; 8]; 8];
+    }
+
+    /// Puts a piece on a dark square, replacing anything already there.
+    pub fn place(&mut self, location: Coordinate, piece: GamePiece) -> Result<(), SetupError> {
+        let Coordinate(x, y) = check_square(location)?;
+
+        self.board[x][y] = Some(piece);
+        Ok(())
+    }
+
+    /// Takes a piece off the board, returning it.
+    pub fn remove(&mut self, location: Coordinate) -> Option<GamePiece> {
+        let Coordinate(x, y) = check_square(location).ok()?;
+
+        self.board[x][y].take()
+    }
+
+    /// Crowns a man or uncrowns a king, returning the piece as it now is.
+    pub fn toggle_king(&mut self, location: Coordinate) -> Option<GamePiece> {
+        let Coordinate(x, y) = check_square(location).ok()?;
+        let piece = self.board[x][y]?;
+
+        let toggled = GamePiece {
+            crowned: !piece.crowned,
+            ..piece
+        };
+        self.board[x][y] = Some(toggled);
+
+        Some(toggled)
+    }
+
+    /// Every piece on the board with its location.
+    pub fn pieces(&self) -> Vec<(Coordinate, GamePiece)> {
+        let mut pieces = Vec::new();
+
+        for y in START_INDEX..=END_INDEX {
+            for x in START_INDEX..=END_INDEX {
+                if let Some(piece) = self.board[x][y] {
+                    pieces.push((Coordinate(x, y), piece));
+                }
+            }
+        }
+
+        pieces
+    }
+
+    /// Checks that play could start from the position, returning the first
+    /// problem found.
+    pub fn validate(&self) -> Result<(), SetupError> {
+        let pieces = self.pieces();
+
+        for color in [PieceColor::Black, PieceColor::White] {
+            let count = pieces
+                .iter()
+                .filter(|(_location, piece)| piece.color == color)
+                .count();
+
+            if count == 0 {
+                return Err(SetupError::NoPieces(color));
+            }
+            if count > MAX_PIECES {
+                return Err(SetupError::TooManyPieces(color));
+            }
+        }
+
+        for (location, piece) in pieces {
+            let Coordinate(_x, y) = location;
+
+            if !piece.crowned && y == piece.color.crowning_row() {
+                return Err(SetupError::ManOnCrowningRow(location));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Validates the position and creates a game from it.
+    pub fn start(&self) -> Result<GameEngine, SetupError> {
+        self.validate()?;
+
+        Ok(GameEngine::from_position(&self.pieces(), self.turn))
+    }
+}
+
+fn check_square(location: Coordinate) -> Result<Coordinate, SetupError> {
+    let Coordinate(x, y) = location;
+
+    if !location.valid() {
+        Err(SetupError::OffBoard(location))
+    } else if (x + y) % 2 == 0 {
+        Err(SetupError::LightSquare(location))
+    } else {
+        Ok(location)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::board::{Coordinate, GamePiece, PieceColor};
+    use super::super::game::GameEngine;
+    use super::{BoardEditor, SetupError};
+
+    fn black() -> GamePiece {
+        GamePiece::new(PieceColor::Black)
+    }
+
+    fn white() -> GamePiece {
+        GamePiece::new(PieceColor::White)
+    }
+
+    #[test]
+    fn places_only_on_dark_squares() {
+        let mut editor = BoardEditor::new();
+
+        assert_eq!(
+            editor.place(Coordinate(0, 0), black()),
+            Err(SetupError::LightSquare(Coordinate(0, 0)))
+        );
+        assert_eq!(
+            editor.place(Coordinate(8, 1), black()),
+            Err(SetupError::OffBoard(Coordinate(8, 1)))
+        );
+        assert_eq!(editor.place(Coordinate(1, 4), black()), Ok(()));
+        assert_eq!(editor.piece(Coordinate(1, 4)), Some(black()));
+
+        assert_eq!(
+            editor.toggle_king(Coordinate(1, 4)),
+            Some(GamePiece::crown(black()))
+        );
+        assert_eq!(editor.toggle_king(Coordinate(1, 4)), Some(black()));
+        assert_eq!(editor.remove(Coordinate(1, 4)), Some(black()));
+        assert_eq!(editor.remove(Coordinate(1, 4)), None);
+        assert_eq!(editor.toggle_king(Coordinate(1, 4)), None);
+    }
+
+    #[test]
+    fn validates_setups() {
+        let mut editor = BoardEditor::new();
+        assert_eq!(
+            editor.validate(),
+            Err(SetupError::NoPieces(PieceColor::Black))
+        );
+
+        editor.place(Coordinate(1, 4), black()).unwrap();
+        editor.place(Coordinate(2, 1), white()).unwrap();
+        assert_eq!(editor.validate(), Ok(()));
+
+        // A white man on Black's back row should have been crowned.
+        editor.place(Coordinate(0, 7), white()).unwrap();
+        assert_eq!(
+            editor.validate(),
+            Err(SetupError::ManOnCrowningRow(Coordinate(0, 7)))
+        );
+        editor.toggle_king(Coordinate(0, 7));
+        assert_eq!(editor.validate(), Ok(()));
+
+        for (x, y) in [(1, 0), (3, 0), (5, 0), (7, 0), (0, 1), (4, 1)] {
+            editor
+                .place(Coordinate(x, y), GamePiece::crown(black()))
+                .unwrap();
+        }
+        for (x, y) in [(6, 1), (1, 2), (3, 2), (5, 2), (7, 2), (0, 3)] {
+            editor.place(Coordinate(x, y), black()).unwrap();
+        }
+        assert_eq!(
+            editor.validate(),
+            Err(SetupError::TooManyPieces(PieceColor::Black))
+        );
+    }
+
+    #[test]
+    fn starts_play_from_setup() {
+        let mut editor = BoardEditor::new();
+        editor.place(Coordinate(3, 4), black()).unwrap();
+        editor.place(Coordinate(4, 3), white()).unwrap();
+        editor.set_turn(PieceColor::White);
+
+        let engine = editor.start().unwrap();
+        assert_eq!(engine.current_turn(), PieceColor::White);
+        assert_eq!(engine.pieces(), editor.pieces());
+        assert!(engine.valid_moves().iter().any(|m| engine.is_capture(m)));
+
+        // Editing a running game round-trips its position.
+        let game = GameEngine::new();
+        let copy = BoardEditor::from_engine(&game).start().unwrap();
+        assert_eq!(copy.position_hash(), game.position_hash());
+    }
+}
@@ -1,12 +1,39 @@
 use super::board::{Coordinate, GamePiece, Move, PieceColor, END_INDEX, START_INDEX};
+use super::zobrist;
 use std::collections::HashMap;
 
+/// The capture rules a game is played by.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Rules {
+    /// The rules this engine was written for: capturing is optional, and a
+    /// jump always ends the turn.
+    #[default]
+    Casual,
+    /// Standard checkers, as other engines play it: a side that can capture
+    /// must, and a piece that has captured keeps jumping while it can. Being
+    /// crowned ends the turn.
+    Standard,
+}
+
 /// The GameEngine, which tracks our state and interactions
+#[derive(Clone)]
 pub struct GameEngine {
     board: [[Option<GamePiece>; 8]; 8],
     current_turn: PieceColor,
     move_count: u32,
     valid_moves: HashMap<PieceColor, Vec<Move>>,
+    /// Zobrist key of the current position, kept up to date as pieces move.
+    hash: u64,
+    rules: Rules,
+    /// Under standard rules, the piece partway through a multi-jump. Only it
+    /// may move until it has no jumps left.
+    jumping: Option<Coordinate>,
+}
+
+impl Default for GameEngine {
+    fn default() -> GameEngine {
+        GameEngine::new()
+    }
 }
 
 /// A Result object for our game
@@ -16,23 +43,69 @@ pub struct MoveResult {
 }
 
 impl GameEngine {
-    /// ******************
-    /// * Public Methods *
-    /// ******************
+    /* ****************
+     * Public Methods *
+     ******************/
 
     /// Constructor
     pub fn new() -> GameEngine {
+        GameEngine::starting_with(PieceColor::Black)
+    }
+
+    /// Creates a game from the standard setup with the given color to move
+    /// first.
+    pub fn starting_with(turn: PieceColor) -> GameEngine {
         let mut engine = GameEngine {
             board: [[None; 8]; 8],
-            current_turn: PieceColor::Black,
+            current_turn: turn,
             move_count: 0,
             valid_moves: HashMap::new(),
+            hash: 0,
+            rules: Rules::Casual,
+            jumping: None,
         };
 
         engine.initialize();
         engine
     }
 
+    /// Creates a game from an arbitrary position.
+    ///
+    /// Places the given pieces on an otherwise empty board, with `turn` to
+    /// move. No checks are made that the position could arise in a game.
+    pub fn from_position(pieces: &[(Coordinate, GamePiece)], turn: PieceColor) -> GameEngine {
+        let mut engine = GameEngine {
+            board: [[None; 8]; 8],
+            current_turn: turn,
+            move_count: 0,
+            valid_moves: HashMap::new(),
+            hash: 0,
+            rules: Rules::Casual,
+            jumping: None,
+        };
+
+        for (Coordinate(x, y), piece) in pieces {
+            if *x <= END_INDEX && *y <= END_INDEX {
+                engine.board[*x][*y] = Some(*piece);
+            }
+        }
+
+        engine.valid_moves = engine.init_valid_moves();
+        engine.hash = zobrist::position_key(&engine.pieces(), turn);
+        engine
+    }
+
+    /// Returns the game set to play by the given rules.
+    pub fn with_rules(mut self, rules: Rules) -> GameEngine {
+        self.rules = rules;
+        self
+    }
+
+    /// Returns the rules the game is played by.
+    pub fn rules(&self) -> Rules {
+        self.rules
+    }
+
     /// Initializes the game.
     ///
     /// Sets the pieces on the board, and generates the initial set of valid
@@ -73,7 +146,8 @@ impl GameEngine {
             self.board[*x][*y] = Some(GamePiece::new(PieceColor::Black));
         });
 
-        self.valid_moves = self.init_valid_moves()
+        self.valid_moves = self.init_valid_moves();
+        self.hash = zobrist::position_key(&self.pieces(), self.current_turn);
     }
 
     /// Move a piece.
@@ -83,25 +157,27 @@ impl GameEngine {
     /// empty, marking the 'to' space with the correct piece, and emptying any
     /// jumped spaces.
     /// Regenerates the valid moves for the mutated spaces on the board.
-    /// Finally, toggles the active player and increments the move counter.
+    /// Finally, toggles the active player and increments the move counter,
+    /// unless standard rules have the same piece jump again.
+    #[allow(clippy::result_unit_err)]
     pub fn move_piece(&mut self, movement: &Move) -> Result<MoveResult, ()> {
-        let valid_move_list = match self.valid_moves.get(&self.current_turn) {
-            None => return Err(()),
-            Some(list) => list,
-        };
-
-        if !valid_move_list.contains(movement) {
+        if !self.valid_moves().contains(movement) {
             return Err(());
         }
 
-        let Coordinate(from_x, from_y) = movement.from;
-        let piece = self.board[from_x][from_y].unwrap();
+        // The move lists should never name an empty square, but a stale entry
+        // is rejected rather than trusted.
+        let piece = match self.get_piece(movement.from) {
+            Ok(Some(piece)) => piece,
+            _ => return Err(()),
+        };
 
         let jumped_piece_coords = self.jumped_piece_coords(movement.from, movement.to);
 
-        if let Some(Coordinate(x, y)) = jumped_piece_coords {
-            let jumped_piece = self.board[x][y].unwrap();
-            self.remove_piece(Coordinate(x, y), jumped_piece);
+        if let Some(location) = jumped_piece_coords {
+            if let Ok(Some(jumped_piece)) = self.get_piece(location) {
+                self.remove_piece(location, jumped_piece);
+            }
         }
 
         // Move the piece at the "from" coordinates to the "to" coordinates:
@@ -115,7 +191,25 @@ impl GameEngine {
             false
         };
 
-        self.advance_turn();
+        let jumps_again = self.rules == Rules::Standard
+            && jumped_piece_coords.is_some()
+            && !crowned
+            && self
+                .valid_moves
+                .get(&self.current_turn)
+                .is_some_and(|moves| {
+                    moves
+                        .iter()
+                        .any(|next| next.from == movement.to && self.is_jump(*next))
+                });
+
+        if jumps_again {
+            self.jumping = Some(movement.to);
+            self.move_count += 1;
+        } else {
+            self.jumping = None;
+            self.advance_turn();
+        }
 
         Ok(MoveResult {
             movement: *movement,
@@ -124,6 +218,7 @@ impl GameEngine {
     }
 
     /// Return the piece at a given coordinate.
+    #[allow(clippy::result_unit_err)]
     pub fn get_piece(&self, coord: Coordinate) -> Result<Option<GamePiece>, ()> {
         let Coordinate(x, y) = coord;
         if x <= 7 && y <= 7 {
@@ -133,6 +228,32 @@ impl GameEngine {
         }
     }
 
+    /// Returns whether a piece at the given location has been crowned.
+    pub fn is_crowned(&self, location: Coordinate) -> bool {
+        let Coordinate(x, y) = location;
+
+        if let Some(piece) = self.board[x][y] {
+            piece.crowned
+        } else {
+            false
+        }
+    }
+
+    /// Returns every piece on the board along with its location.
+    pub fn pieces(&self) -> Vec<(Coordinate, GamePiece)> {
+        let mut pieces = Vec::new();
+
+        for y in START_INDEX..=END_INDEX {
+            for x in START_INDEX..=END_INDEX {
+                if let Some(piece) = self.board[x][y] {
+                    pieces.push((Coordinate(x, y), piece));
+                }
+            }
+        }
+
+        pieces
+    }
+
     /// Returns the color playing the current turn
     pub fn current_turn(&self) -> PieceColor {
         self.current_turn
@@ -143,6 +264,57 @@ impl GameEngine {
         self.move_count
     }
 
+    /// Returns a 64-bit key identifying the position: the pieces on the board
+    /// and the side to move. Equal positions always have equal keys, however
+    /// they were reached; the move count is not part of the position.
+    pub fn position_hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Returns the legal moves for the player whose turn it is, in a stable
+    /// (sorted) order.
+    ///
+    /// Under standard rules only captures are listed when there are any, and
+    /// only the jumping piece's captures partway through a multi-jump.
+    pub fn valid_moves(&self) -> Vec<Move> {
+        let mut moves = self
+            .valid_moves
+            .get(&self.current_turn)
+            .cloned()
+            .unwrap_or_default();
+
+        if self.rules == Rules::Standard {
+            if let Some(jumping) = self.jumping {
+                moves.retain(|movement| movement.from == jumping && self.is_jump(*movement));
+            } else if moves.iter().any(|movement| self.is_jump(*movement)) {
+                moves.retain(|movement| self.is_jump(*movement));
+            }
+        }
+
+        moves.sort();
+        moves
+    }
+
+    /// Returns the winner of the game, if there is one.
+    ///
+    /// A player who has no legal moves on their turn (including having no
+    /// pieces left) loses.
+    pub fn winner(&self) -> Option<PieceColor> {
+        if !self.valid_moves().is_empty() {
+            return None;
+        }
+
+        match self.current_turn {
+            PieceColor::Black => Some(PieceColor::White),
+            PieceColor::White => Some(PieceColor::Black),
+        }
+    }
+
+    /// Returns whether the given move captures a piece.
+    pub fn is_capture(&self, movement: &Move) -> bool {
+        self.is_jump(*movement)
+    }
+
     /* *****************
      * Private Methods *
      *******************/
@@ -183,7 +355,7 @@ impl GameEngine {
                 if let Some(piece) = self.board[x][y] {
                     let location = Coordinate(x, y);
 
-                    let mut location_moves = self.valid_moves_for(location);
+                    let location_moves = self.valid_moves_for(location);
                     let mut legal_moves = self.legal_moves(piece, location_moves);
 
                     map.entry(piece.color)
@@ -200,7 +372,7 @@ impl GameEngine {
     /// Evaluates move direction rules (e.g. color+crown rules), destination
     /// rules (to avoid collisions), and jump rules (jumped piece colors)
     fn legal_moves(&mut self, piece: GamePiece, moves: Vec<Move>) -> Vec<Move> {
-        return moves
+        moves
             .iter()
             .copied()
             // Filter out crowned-only moves unless crowned:
@@ -209,23 +381,22 @@ impl GameEngine {
             .filter(|mve| self.valid_destination(*mve))
             // Filter out jumps that don't pass over another piece:
             .filter(|mve| !self.is_jump(*mve) || self.valid_jump(piece, *mve))
-            .collect::<Vec<Move>>();
+            .collect::<Vec<Move>>()
     }
 
     /// Returns whether a given move direction is valid.
     ///
     /// Evaluates the piece color and crown state.
     fn valid_direction(&self, piece: GamePiece, movement: Move) -> bool {
-        if piece.crowned { return true }
+        if piece.crowned {
+            return true;
+        }
 
         let Coordinate(_from_x, from_y) = movement.from;
         let Coordinate(_to_x, to_y) = movement.to;
         let y_delta: i8 = to_y as i8 - from_y as i8;
 
-        match piece.color {
-            PieceColor::Black => y_delta < 0,
-            PieceColor::White => y_delta > 0,
-        }
+        y_delta.signum() == piece.color.forward()
     }
 
     /// Returns whether a given move is a jump or not.
@@ -241,9 +412,6 @@ impl GameEngine {
     ///
     /// Evaluates the piece color, and the color of the jumped piece.
     fn valid_jump(&self, piece: GamePiece, movement: Move) -> bool {
-        let Coordinate(_from_x, from_y) = movement.from;
-        let Coordinate(_to_x, to_y) = movement.to;
-        
         match self.jumped_piece_coords(movement.from, movement.to) {
             None => false,
             Some(location) => {
@@ -251,7 +419,7 @@ impl GameEngine {
 
                 match self.board[jumped_x][jumped_y] {
                     None => false,
-                    Some(jumped_piece) => jumped_piece.color != piece.color
+                    Some(jumped_piece) => jumped_piece.color != piece.color,
                 }
             }
         }
@@ -268,7 +436,7 @@ impl GameEngine {
     /// the global list for the piece color.
     fn update_valid_moves_for(&mut self, location: Coordinate, piece: GamePiece) {
         let color = piece.color;
-        let mut new_moves = self.valid_moves_for(location);
+        let new_moves = self.valid_moves_for(location);
         let mut legal_new_moves = self.legal_moves(piece, new_moves);
 
         self.valid_moves
@@ -288,7 +456,7 @@ impl GameEngine {
         let new_moves: Vec<Move> = color_list
             .iter()
             .cloned()
-            .filter(|movement| movement.from.eq(&location))
+            .filter(|movement| movement.from.ne(&location))
             .collect();
 
         self.valid_moves.insert(piece.color, new_moves);
@@ -296,8 +464,32 @@ impl GameEngine {
 
     /// Removes old moves for a location, and adds the updated moves.
     ///
-    /// If none exist, simply inserts the new moves.
+    /// If none exist, simply inserts the new moves. Pieces within jumping
+    /// distance of the location are refreshed as well, since a change here
+    /// can open or block their moves and jumps.
     fn update_valid_moves(&mut self, location: Coordinate, piece: GamePiece) {
+        self.refresh_valid_moves(location, piece);
+
+        let Coordinate(x, y) = location;
+        let x_range = x.saturating_sub(2)..=(x + 2).min(END_INDEX);
+
+        for neighbor_x in x_range {
+            for neighbor_y in y.saturating_sub(2)..=(y + 2).min(END_INDEX) {
+                let neighbor = Coordinate(neighbor_x, neighbor_y);
+
+                if neighbor == location {
+                    continue;
+                }
+
+                if let Some(neighbor_piece) = self.board[neighbor_x][neighbor_y] {
+                    self.refresh_valid_moves(neighbor, neighbor_piece);
+                }
+            }
+        }
+    }
+
+    /// Replaces the moves for a single location with its current legal moves.
+    fn refresh_valid_moves(&mut self, location: Coordinate, piece: GamePiece) {
         let Coordinate(x, y) = location;
 
         match self.board[x][y] {
@@ -312,14 +504,19 @@ impl GameEngine {
     /// Removes a piece from a given location on the board.
     fn remove_piece(&mut self, location: Coordinate, piece: GamePiece) {
         let Coordinate(x, y) = location;
-        self.board[x][y] = None;
+        if let Some(removed) = self.board[x][y].take() {
+            self.hash ^= zobrist::piece_key(location, removed);
+        }
         self.update_valid_moves(location, piece)
     }
 
     /// Sets a piece on a given location on the board.
     fn set_piece(&mut self, location: Coordinate, piece: GamePiece) {
         let Coordinate(x, y) = location;
-        self.board[x][y] = Some(piece);
+        if let Some(replaced) = self.board[x][y].replace(piece) {
+            self.hash ^= zobrist::piece_key(location, replaced);
+        }
+        self.hash ^= zobrist::piece_key(location, piece);
         self.update_valid_moves(location, piece)
     }
 
@@ -332,7 +529,7 @@ impl GameEngine {
         let x_delta: i8 = to_x as i8 - from_x as i8;
         let y_delta: i8 = to_y as i8 - from_y as i8;
 
-        if x_delta < 2 && y_delta < 2 {
+        if x_delta.abs() != 2 || y_delta.abs() != 2 {
             None
         } else {
             let piece_x = (from_x as i8 + x_delta / 2) as usize;
@@ -344,14 +541,9 @@ impl GameEngine {
 
     /// Returns whether or not a given piece should be crowned.
     fn should_crown(&self, piece: GamePiece, location: Coordinate) -> bool {
-        const WHITE_ROW: usize = START_INDEX;
-        const BLACK_ROW: usize = END_INDEX;
         let Coordinate(_x, y) = location;
 
-        match piece.color {
-            PieceColor::Black => y == WHITE_ROW,
-            PieceColor::White => y == BLACK_ROW,
-        }
+        y == piece.color.crowning_row()
     }
 
     /// Mutates crowned state of the piece at a given location to be true.
@@ -359,30 +551,24 @@ impl GameEngine {
         let Coordinate(x, y) = location;
 
         if let Some(piece) = self.board[x][y] {
-            self.board[x][y] = Some(GamePiece::crown(piece));
+            let crowned = GamePiece::crown(piece);
+            self.board[x][y] = Some(crowned);
+            self.hash ^=
+                zobrist::piece_key(location, piece) ^ zobrist::piece_key(location, crowned);
+            self.update_valid_moves(location, crowned);
             true
         } else {
             false
         }
     }
 
-    /// Returns whether a piece at the given location has been crowned.
-    fn is_crowned(&mut self, location: Coordinate) -> bool {
-        let Coordinate(x, y) = location;
-
-        if let Some(piece) = self.board[x][y] {
-            piece.crowned
-        } else {
-            false
-        }
-    }
-
     /// Advances the turn to the next player.
     fn advance_turn(&mut self) {
         match self.current_turn {
             PieceColor::Black => self.current_turn = PieceColor::White,
             PieceColor::White => self.current_turn = PieceColor::Black,
         }
+        self.hash ^= zobrist::WHITE_TO_MOVE;
 
         self.move_count += 1
     }
@@ -391,7 +577,12 @@ impl GameEngine {
 #[cfg(test)]
 mod test {
     use super::super::board::{Coordinate, GamePiece, Move, PieceColor};
-    use super::GameEngine;
+    use super::{GameEngine, Rules};
+
+    fn sorted<T: Ord>(mut items: Vec<T>) -> Vec<T> {
+        items.sort();
+        items
+    }
 
     #[test]
     fn should_crown() {
@@ -400,7 +591,7 @@ mod test {
         let res = engine.should_crown(black, Coordinate(3, 0));
         assert!(res);
         let res_nocrown = engine.should_crown(black, Coordinate(5, 2));
-        assert_eq!(res_nocrown, false);
+        assert!(!res_nocrown);
     }
 
     #[test]
@@ -422,34 +613,49 @@ mod test {
         assert_eq!(engine.move_count(), 2);
     }
 
+    #[test]
+    fn starts_with_either_color() {
+        let black_first = GameEngine::new();
+        let mut white_first = GameEngine::starting_with(PieceColor::White);
+
+        assert_eq!(white_first.current_turn(), PieceColor::White);
+        assert_eq!(white_first.pieces(), black_first.pieces());
+        assert_ne!(white_first.position_hash(), black_first.position_hash());
+        assert!(white_first.move_piece(&Move::new((2, 5), (3, 4))).is_err());
+        assert!(white_first.move_piece(&Move::new((1, 2), (2, 3))).is_ok());
+    }
+
     #[test]
     fn move_targets() {
         let c1 = Coordinate(0, 5);
-        let targets = c1.valid_moves().collect::<Vec<Coordinate>>().sort();
-        assert_eq!(targets, [Coordinate(1, 6), Coordinate(1, 4)].sort());
+        let targets = sorted(c1.valid_moves().collect::<Vec<Coordinate>>());
+        assert_eq!(
+            targets,
+            sorted(Vec::from([Coordinate(1, 6), Coordinate(1, 4)]))
+        );
 
         let c2 = Coordinate(1, 6);
-        let targets2 = c2.valid_moves().collect::<Vec<Coordinate>>().sort();
+        let targets2 = sorted(c2.valid_moves().collect::<Vec<Coordinate>>());
         assert_eq!(
             targets2,
-            [
+            sorted(Vec::from([
                 Coordinate(0, 7),
                 Coordinate(2, 7),
                 Coordinate(2, 5),
                 Coordinate(0, 5)
-            ].sort()
+            ]))
         );
 
         let c3 = Coordinate(2, 5);
-        let targets3 = c3.valid_moves().collect::<Vec<Coordinate>>().sort();
+        let targets3 = sorted(c3.valid_moves().collect::<Vec<Coordinate>>());
         assert_eq!(
             targets3,
-            [
+            sorted(Vec::from([
                 Coordinate(1, 6),
                 Coordinate(3, 6),
                 Coordinate(3, 4),
                 Coordinate(1, 4)
-            ].sort()
+            ]))
         );
     }
 
@@ -488,8 +694,8 @@ mod test {
     fn legal_moves_black() {
         let mut engine = GameEngine::new();
         engine.initialize();
-        let moves = engine.valid_moves.get_mut(&PieceColor::Black).unwrap().sort();
-        let expected_moves = Vec::from([
+        let moves = sorted(engine.valid_moves[&PieceColor::Black].clone());
+        let expected_moves = sorted(Vec::from([
             Move {
                 from: Coordinate(0, 5),
                 to: Coordinate(1, 4),
@@ -517,8 +723,8 @@ mod test {
             Move {
                 from: Coordinate(6, 5),
                 to: Coordinate(5, 4),
-            }
-        ]).sort();
+            },
+        ]));
 
         assert_eq!(moves, expected_moves);
     }
@@ -528,8 +734,8 @@ mod test {
         let mut engine = GameEngine::new();
         engine.initialize();
         engine.advance_turn();
-        let moves = engine.valid_moves.get_mut(&PieceColor::White).unwrap().sort();
-        let expected_moves = Vec::from([
+        let moves = sorted(engine.valid_moves[&PieceColor::White].clone());
+        let expected_moves = sorted(Vec::from([
             Move {
                 from: Coordinate(1, 2),
                 to: Coordinate(0, 3),
@@ -557,8 +763,8 @@ mod test {
             Move {
                 from: Coordinate(7, 2),
                 to: Coordinate(6, 3),
-            }
-        ]).sort();
+            },
+        ]));
         assert_eq!(moves, expected_moves);
     }
 
@@ -566,53 +772,53 @@ mod test {
     fn jumps_validation() {
         let mut engine = GameEngine::new();
         engine.initialize();
-        engine.board[1][4] = Some(GamePiece::new(PieceColor::White)); // this should be jumpable from 0,5 to 2,3
-        let moves = engine.valid_moves.get_mut(&PieceColor::Black).unwrap().sort();
-        let expected_moves = Vec::from([
+        engine.set_piece(Coordinate(1, 4), GamePiece::new(PieceColor::White)); // this should be jumpable from 0,5 to 2,3
+        let moves = sorted(engine.valid_moves[&PieceColor::Black].clone());
+        let expected_moves = sorted(Vec::from([
             Move {
                 from: Coordinate(0, 5),
                 to: Coordinate(2, 3),
             },
             Move {
                 from: Coordinate(2, 5),
-                to: Coordinate(0, 3)
+                to: Coordinate(0, 3),
             },
             Move {
                 from: Coordinate(2, 5),
-                to: Coordinate(3, 4)
+                to: Coordinate(3, 4),
             },
             Move {
                 from: Coordinate(4, 5),
-                to: Coordinate(5, 4)
+                to: Coordinate(5, 4),
             },
             Move {
                 from: Coordinate(4, 5),
-                to: Coordinate(3, 4)
+                to: Coordinate(3, 4),
             },
             Move {
                 from: Coordinate(6, 5),
-                to: Coordinate(7, 4)
+                to: Coordinate(7, 4),
             },
             Move {
                 from: Coordinate(6, 5),
-                to: Coordinate(5, 4)
-            }
-        ]).sort();
+                to: Coordinate(5, 4),
+            },
+        ]));
         assert_eq!(moves, expected_moves);
     }
 
     #[test]
     fn valid_jumps() {
         let c1 = Coordinate(3, 3);
-        let targets = c1.valid_jumps().collect::<Vec<Coordinate>>().sort();
+        let targets = sorted(c1.valid_jumps().collect::<Vec<Coordinate>>());
         assert_eq!(
             targets,
-            [
+            sorted(Vec::from([
                 Coordinate(5, 1),
                 Coordinate(5, 5),
                 Coordinate(1, 1),
                 Coordinate(1, 5)
-            ].sort()
+            ]))
         );
     }
 
@@ -636,7 +842,95 @@ mod test {
 
         // fail to perform illegal move
         let res = engine.move_piece(&Move::new((1, 4), (2, 4))); // can't move horiz
-        assert!(!res.is_ok());
+        assert!(res.is_err());
         assert_eq!(engine.board[2][4], None);
     }
+
+    #[test]
+    fn position_hash_is_kept_incrementally() {
+        let mut engine = GameEngine::new();
+        let start = engine.position_hash();
+
+        // 11-15 22-18 15x22, then check against a fresh computation.
+        for movement in [
+            Move::new((2, 5), (3, 4)),
+            Move::new((5, 2), (4, 3)),
+            Move::new((3, 4), (5, 2)),
+        ] {
+            engine.move_piece(&movement).unwrap();
+            let rebuilt = GameEngine::from_position(&engine.pieces(), engine.current_turn());
+            assert_eq!(engine.position_hash(), rebuilt.position_hash());
+        }
+
+        assert_ne!(engine.position_hash(), start);
+        assert_eq!(GameEngine::new().position_hash(), start);
+    }
+
+    #[test]
+    fn position_hash_identifies_positions() {
+        // 9-13 21-17 10-14 and 10-14 21-17 9-13 transpose.
+        let mut first = GameEngine::new();
+        let mut second = GameEngine::new();
+        for movement in [
+            Move::new((0, 5), (1, 4)),
+            Move::new((7, 2), (6, 3)),
+            Move::new((2, 5), (3, 4)),
+        ] {
+            first.move_piece(&movement).unwrap();
+        }
+        for movement in [
+            Move::new((2, 5), (3, 4)),
+            Move::new((7, 2), (6, 3)),
+            Move::new((0, 5), (1, 4)),
+        ] {
+            second.move_piece(&movement).unwrap();
+        }
+        assert_eq!(first.position_hash(), second.position_hash());
+
+        // The side to move is part of the position.
+        let pieces = GameEngine::new().pieces();
+        assert_ne!(
+            GameEngine::from_position(&pieces, PieceColor::Black).position_hash(),
+            GameEngine::from_position(&pieces, PieceColor::White).position_hash()
+        );
+
+        // So is crowning.
+        let mut crowned = GameEngine::new();
+        crowned.crown(Coordinate(0, 5));
+        assert_ne!(crowned.position_hash(), GameEngine::new().position_hash());
+    }
+
+    #[test]
+    fn standard_rules_force_captures_and_multi_jumps() {
+        let black = GamePiece::new(PieceColor::Black);
+        let white = GamePiece::new(PieceColor::White);
+        let pieces = [
+            (Coordinate(1, 6), black),
+            (Coordinate(6, 7), black),
+            (Coordinate(2, 5), white),
+            (Coordinate(4, 3), white),
+            (Coordinate(7, 0), white),
+        ];
+        let first_jump = Move::new((1, 6), (3, 4));
+        let second_jump = Move::new((3, 4), (5, 2));
+
+        let mut casual = GameEngine::from_position(&pieces, PieceColor::Black);
+        assert!(casual.valid_moves().contains(&Move::new((6, 7), (5, 6))));
+        casual.move_piece(&first_jump).unwrap();
+        assert_eq!(casual.current_turn(), PieceColor::White);
+
+        let mut standard =
+            GameEngine::from_position(&pieces, PieceColor::Black).with_rules(Rules::Standard);
+        assert_eq!(standard.valid_moves(), vec![first_jump]);
+        assert!(standard.move_piece(&Move::new((6, 7), (5, 6))).is_err());
+
+        standard.move_piece(&first_jump).unwrap();
+        assert_eq!(standard.current_turn(), PieceColor::Black);
+        assert_eq!(standard.valid_moves(), vec![second_jump]);
+
+        standard.move_piece(&second_jump).unwrap();
+        assert_eq!(standard.current_turn(), PieceColor::White);
+        assert_eq!(standard.move_count(), 2);
+        assert_eq!(standard.get_piece(Coordinate(4, 3)), Ok(None));
+    }
 }
@@ -0,0 +1,595 @@
+use super::board::Move;
+use super::game::GameEngine;
+use super::pdn::{self, GameResult, Token, SUFFIX_NAGS};
+use super::protocol::{format_move, parse_move};
+
+/// Identifies a position in a `GameTree`. The root (the starting position)
+/// is always 0.
+pub type NodeId = usize;
+
+pub const ROOT: NodeId = 0;
+
+/// A move annotation, in the Numeric Annotation Glyph numbering.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Annotation {
+    /// `!` ($1)
+    Good,
+    /// `?` ($2)
+    Mistake,
+    /// `!!` ($3)
+    Brilliant,
+    /// `??` ($4)
+    Blunder,
+    /// `!?` ($5)
+    Interesting,
+    /// `?!` ($6)
+    Dubious,
+    /// Any other glyph, written as `$n`.
+    Other(u8),
+}
+
+impl Annotation {
+    pub fn from_nag(nag: u8) -> Annotation {
+        match nag {
+            1 => Annotation::Good,
+            2 => Annotation::Mistake,
+            3 => Annotation::Brilliant,
+            4 => Annotation::Blunder,
+            5 => Annotation::Interesting,
+            6 => Annotation::Dubious,
+            other => Annotation::Other(other),
+        }
+    }
+
+    pub fn nag(self) -> u8 {
+        match self {
+            Annotation::Good => 1,
+            Annotation::Mistake => 2,
+            Annotation::Brilliant => 3,
+            Annotation::Blunder => 4,
+            Annotation::Interesting => 5,
+            Annotation::Dubious => 6,
+            Annotation::Other(nag) => nag,
+        }
+    }
+
+    /// Returns the suffix form (`!`, `?!`, ...) if the glyph has one.
+    pub fn suffix(self) -> Option<&'static str> {
+        SUFFIX_NAGS
+            .iter()
+            .find(|(_glyph, nag)| *nag == self.nag())
+            .map(|(glyph, _nag)| *glyph)
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Node {
+    movement: Option<Move>,
+    parent: Option<NodeId>,
+    /// The first child continues the main line; the rest are variations.
+    children: Vec<NodeId>,
+    ply: u32,
+    comment: Option<String>,
+    annotations: Vec<Annotation>,
+}
+
+/// A game with variations, comments and annotations.
+///
+/// Every node is a position reached by a move from its parent. A cursor
+/// tracks the position being viewed; moves played from it either follow an
+/// existing branch or add a new one.
+#[derive(Clone)]
+pub struct GameTree {
+    nodes: Vec<Node>,
+    current: NodeId,
+    engine: GameEngine,
+    pub tags: Vec<(String, String)>,
+    pub result: GameResult,
+}
+
+impl Default for GameTree {
+    fn default() -> GameTree {
+        GameTree::new()
+    }
+}
+
+impl GameTree {
+    /// Creates a tree holding just the starting position.
+    pub fn new() -> GameTree {
+        GameTree {
+            nodes: vec![Node {
+                movement: None,
+                parent: None,
+                children: Vec::new(),
+                ply: 0,
+                comment: None,
+                annotations: Vec::new(),
+            }],
+            current: ROOT,
+            engine: GameEngine::new(),
+            tags: Vec::new(),
+            result: GameResult::Unfinished,
+        }
+    }
+
+    /// Returns the node at the cursor.
+    pub fn current(&self) -> NodeId {
+        self.current
+    }
+
+    /// Returns the game at the cursor.
+    pub fn engine(&self) -> &GameEngine {
+        &self.engine
+    }
+
+    /// Returns the move that led to a node (`None` for the root).
+    pub fn movement(&self, node: NodeId) -> Option<Move> {
+        self.nodes.get(node).and_then(|node| node.movement)
+    }
+
+    pub fn parent(&self, node: NodeId) -> Option<NodeId> {
+        self.nodes.get(node).and_then(|node| node.parent)
+    }
+
+    /// Returns the continuations from a node, main line first.
+    pub fn children(&self, node: NodeId) -> &[NodeId] {
+        self.nodes
+            .get(node)
+            .map_or(&[], |node| node.children.as_slice())
+    }
+
+    pub fn comment(&self, node: NodeId) -> Option<&str> {
+        self.nodes
+            .get(node)
+            .and_then(|node| node.comment.as_deref())
+    }
+
+    pub fn annotations(&self, node: NodeId) -> &[Annotation] {
+        self.nodes
+            .get(node)
+            .map_or(&[], |node| node.annotations.as_slice())
+    }
+
+    /// Plays a move from the cursor and moves the cursor to the result.
+    ///
+    /// Follows an existing branch if there is one for this move; otherwise
+    /// adds the move as the main line (if the position has no continuation
+    /// yet) or as a new variation.
+    #[allow(clippy::result_unit_err)]
+    pub fn play(&mut self, movement: &Move) -> Result<NodeId, ()> {
+        if let Some(existing) = self.child_with_move(self.current, movement) {
+            self.engine.move_piece(movement)?;
+            self.current = existing;
+            return Ok(existing);
+        }
+
+        self.engine.move_piece(movement)?;
+
+        let node = self.nodes.len();
+        self.nodes.push(Node {
+            movement: Some(*movement),
+            parent: Some(self.current),
+            children: Vec::new(),
+            ply: self.nodes[self.current].ply + 1,
+            comment: None,
+            annotations: Vec::new(),
+        });
+        self.nodes[self.current].children.push(node);
+        self.current = node;
+
+        Ok(node)
+    }
+
+    fn child_with_move(&self, node: NodeId, movement: &Move) -> Option<NodeId> {
+        self.nodes[node]
+            .children
+            .iter()
+            .copied()
+            .find(|child| self.nodes[*child].movement.as_ref() == Some(movement))
+    }
+
+    /// Moves the cursor one move along the main line. Returns false at the
+    /// end of the line.
+    pub fn forward(&mut self) -> bool {
+        let child = match self.nodes[self.current].children.first() {
+            Some(child) => *child,
+            None => return false,
+        };
+
+        match self.nodes[child].movement {
+            Some(movement) if self.engine.move_piece(&movement).is_ok() => {
+                self.current = child;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Moves the cursor back one move. Returns false at the root.
+    pub fn back(&mut self) -> bool {
+        match self.nodes[self.current].parent {
+            Some(parent) => self.jump(parent),
+            None => false,
+        }
+    }
+
+    /// Moves the cursor to any node, replaying the game up to it.
+    pub fn jump(&mut self, node: NodeId) -> bool {
+        if node >= self.nodes.len() {
+            return false;
+        }
+
+        let mut engine = GameEngine::new();
+        for movement in self.path_to(node) {
+            if engine.move_piece(&movement).is_err() {
+                return false;
+            }
+        }
+
+        self.engine = engine;
+        self.current = node;
+        true
+    }
+
+    /// Returns the moves leading from the root to a node.
+    pub fn path_to(&self, node: NodeId) -> Vec<Move> {
+        let mut moves = Vec::new();
+        let mut current = Some(node);
+
+        while let Some(id) = current {
+            if let Some(movement) = self.nodes[id].movement {
+                moves.push(movement);
+            }
+            current = self.nodes[id].parent;
+        }
+
+        moves.reverse();
+        moves
+    }
+
+    /// Returns the main line from the root.
+    pub fn main_line(&self) -> Vec<Move> {
+        let mut moves = Vec::new();
+        let mut node = ROOT;
+
+        while let Some(child) = self.nodes[node].children.first() {
+            moves.extend(self.nodes[*child].movement);
+            node = *child;
+        }
+
+        moves
+    }
+
+    /// Moves a variation one step up among its siblings; promoting the first
+    /// variation makes it the main line. Returns false if it is already the
+    /// main line.
+    pub fn promote_variation(&mut self, node: NodeId) -> bool {
+        let parent = match self.parent(node) {
+            Some(parent) => parent,
+            None => return false,
+        };
+
+        let siblings = &mut self.nodes[parent].children;
+        match siblings.iter().position(|child| *child == node) {
+            Some(index) if index > 0 => {
+                siblings.swap(index, index - 1);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Removes a node and everything after it. The cursor moves to the
+    /// parent if it was inside the removed branch.
+    pub fn delete_variation(&mut self, node: NodeId) -> bool {
+        let parent = match self.parent(node) {
+            Some(parent) => parent,
+            None => return false,
+        };
+
+        let mut cursor = Some(self.current);
+        while let Some(id) = cursor {
+            if id == node {
+                self.jump(parent);
+                break;
+            }
+            cursor = self.nodes[id].parent;
+        }
+
+        // Detached nodes stay in storage so existing ids remain valid.
+        self.nodes[parent].children.retain(|child| *child != node);
+        true
+    }
+
+    pub fn set_comment(&mut self, node: NodeId, comment: Option<&str>) {
+        if let Some(node) = self.nodes.get_mut(node) {
+            node.comment = comment.map(str::to_owned);
+        }
+    }
+
+    pub fn add_annotation(&mut self, node: NodeId, annotation: Annotation) {
+        if let Some(node) = self.nodes.get_mut(node) {
+            if !node.annotations.contains(&annotation) {
+                node.annotations.push(annotation);
+            }
+        }
+    }
+
+    pub fn remove_annotation(&mut self, node: NodeId, annotation: Annotation) {
+        if let Some(node) = self.nodes.get_mut(node) {
+            node.annotations.retain(|existing| *existing != annotation);
+        }
+    }
+
+    /// Writes the tree as PDN with nested variations.
+    pub fn to_pdn(&self) -> String {
+        let mut pdn = pdn::write_tags(&self.tags, self.result);
+        let mut tokens = Vec::new();
+
+        if let Some(comment) = self.comment(ROOT) {
+            tokens.push(format_comment(comment));
+        }
+
+        self.write_continuations(ROOT, &mut tokens, true);
+        tokens.push(self.result.as_str().to_owned());
+
+        pdn.push_str(&pdn::wrap(&tokens));
+        pdn.push_str("\n\n");
+        pdn
+    }
+
+    fn write_continuations(&self, node: NodeId, tokens: &mut Vec<String>, force_number: bool) {
+        let children = &self.nodes[node].children;
+        let main = match children.first() {
+            Some(main) => *main,
+            None => return,
+        };
+
+        self.write_move(main, tokens, force_number);
+
+        for variation in &children[1..] {
+            tokens.push("(".to_owned());
+            self.write_move(*variation, tokens, true);
+            self.write_continuations(*variation, tokens, false);
+            tokens.push(")".to_owned());
+        }
+
+        let interrupted = children.len() > 1 || self.nodes[main].comment.is_some();
+        self.write_continuations(main, tokens, interrupted);
+    }
+
+    fn write_move(&self, node: NodeId, tokens: &mut Vec<String>, force_number: bool) {
+        let node = &self.nodes[node];
+        let movement = match node.movement {
+            Some(movement) => movement,
+            None => return,
+        };
+
+        // Black moves on odd plies and starts each numbered move.
+        let number = node.ply.div_ceil(2);
+        if node.ply % 2 == 1 {
+            tokens.push(format!("{}.", number));
+        } else if force_number {
+            tokens.push(format!("{}...", number));
+        }
+
+        let mut text = format_move(&movement);
+        let mut glyphs = Vec::new();
+
+        for annotation in &node.annotations {
+            match annotation.suffix() {
+                Some(suffix) if glyphs.is_empty() && !text.ends_with(['!', '?']) => {
+                    text.push_str(suffix)
+                }
+                _ => glyphs.push(format!("${}", annotation.nag())),
+            }
+        }
+
+        tokens.push(text);
+        tokens.extend(glyphs);
+
+        if let Some(comment) = &node.comment {
+            tokens.push(format_comment(comment));
+        }
+    }
+
+    /// Reads a single game from PDN text.
+    pub fn from_pdn(text: &str) -> Result<GameTree, String> {
+        GameTree::from_tokens(pdn::tokenize(text)?)
+    }
+
+    /// Builds a tree from the tokens of a single game.
+    pub fn from_tokens(tokens: Vec<Token>) -> Result<GameTree, String> {
+        struct Level {
+            cursor: NodeId,
+            last: Option<NodeId>,
+        }
+
+        let mut tree = GameTree::new();
+        let mut level = Level {
+            cursor: ROOT,
+            last: None,
+        };
+        let mut outer: Vec<Level> = Vec::new();
+        // A comment opening a variation belongs to its first move.
+        let mut pending_comment: Option<String> = None;
+
+        for token in tokens {
+            match token {
+                Token::Tag(name, value) => {
+                    if name == "Result" {
+                        tree.result = GameResult::parse(&value).unwrap_or(tree.result);
+                    }
+                    tree.tags.push((name, value));
+                }
+                Token::Move(notation) => {
+                    if tree.current != level.cursor {
+                        tree.jump(level.cursor);
+                    }
+
+                    let movement = parse_move(&tree.engine, &notation)
+                        .ok_or_else(|| format!("illegal move {}", notation))?;
+                    let node = tree
+                        .play(&movement)
+                        .map_err(|_| format!("illegal move {}", notation))?;
+
+                    if let Some(comment) = pending_comment.take() {
+                        tree.set_comment(node, Some(&comment));
+                    }
+
+                    level.cursor = node;
+                    level.last = Some(node);
+                }
+                Token::Nag(nag) => {
+                    let node = level.last.ok_or("annotation before any move")?;
+                    tree.add_annotation(node, Annotation::from_nag(nag));
+                }
+                Token::Comment(comment) => match level.last {
+                    Some(node) => tree.set_comment(node, Some(&comment)),
+                    None if !outer.is_empty() => pending_comment = Some(comment),
+                    None => tree.set_comment(level.cursor, Some(&comment)),
+                },
+                Token::VariationStart => {
+                    let last = level.last.ok_or("variation before any move")?;
+                    let parent = tree.parent(last).unwrap_or(ROOT);
+
+                    outer.push(level);
+                    level = Level {
+                        cursor: parent,
+                        last: None,
+                    };
+                }
+                Token::VariationEnd => {
+                    pending_comment = None;
+                    level = outer.pop().ok_or("unbalanced ')'")?;
+                }
+                Token::Result(result) => tree.result = result,
+            }
+        }
+
+        if !outer.is_empty() {
+            return Err("unterminated variation".to_owned());
+        }
+
+        tree.tags.retain(|(name, _value)| name != "Result");
+        tree.jump(ROOT);
+        Ok(tree)
+    }
+}
+
+fn format_comment(comment: &str) -> String {
+    format!("{{{}}}", comment.replace('}', ")"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::board::Move;
+    use super::super::pdn::GameResult;
+    use super::{Annotation, GameTree, ROOT};
+
+    #[test]
+    fn plays_and_navigates() {
+        let mut tree = GameTree::new();
+        let first = tree.play(&Move::new((2, 5), (3, 4))).unwrap();
+        let second = tree.play(&Move::new((5, 2), (4, 3))).unwrap();
+
+        assert_eq!(tree.current(), second);
+        assert_eq!(tree.engine().move_count(), 2);
+
+        assert!(tree.back());
+        assert_eq!(tree.current(), first);
+        assert_eq!(tree.engine().move_count(), 1);
+
+        assert!(tree.forward());
+        assert_eq!(tree.current(), second);
+        assert!(!tree.forward());
+
+        assert!(tree.jump(ROOT));
+        assert!(!tree.back());
+        assert!(tree.play(&Move::new((1, 2), (0, 3))).is_err());
+    }
+
+    #[test]
+    fn adds_and_promotes_variations() {
+        let mut tree = GameTree::new();
+        let main = tree.play(&Move::new((2, 5), (3, 4))).unwrap();
+        tree.back();
+        let variation = tree.play(&Move::new((0, 5), (1, 4))).unwrap();
+        tree.back();
+
+        // Replaying an existing move follows its branch.
+        assert_eq!(tree.play(&Move::new((2, 5), (3, 4))), Ok(main));
+        assert_eq!(tree.children(ROOT), &[main, variation]);
+
+        assert!(tree.promote_variation(variation));
+        assert_eq!(tree.children(ROOT), &[variation, main]);
+        assert_eq!(tree.main_line(), vec![Move::new((0, 5), (1, 4))]);
+        assert!(!tree.promote_variation(variation));
+
+        assert!(tree.delete_variation(variation));
+        assert_eq!(tree.children(ROOT), &[main]);
+    }
+
+    #[test]
+    fn round_trips_pdn_with_nested_variations() {
+        let text = "[Event \"Lesson\"]\n\
+                    [Result \"1-0\"]\n\
+                    {Start} 1. 11-15 22-18 ( 1... 23-19 $2 ( 1... 22-17 {also} ) 2. 8-11 ) \
+                    2. 15x22! $14 25x18 1-0";
+        let tree = GameTree::from_pdn(text).unwrap();
+
+        assert_eq!(tree.result, GameResult::BlackWins);
+        assert_eq!(tree.main_line().len(), 4);
+        assert_eq!(tree.comment(ROOT), Some("Start"));
+
+        let first = tree.children(ROOT)[0];
+        assert_eq!(tree.children(first).len(), 3);
+
+        let capture = tree.children(tree.children(first)[0])[0];
+        assert_eq!(
+            tree.annotations(capture),
+            &[Annotation::Good, Annotation::Other(14)]
+        );
+
+        let written = tree.to_pdn();
+        assert_eq!(
+            written,
+            "[Event \"Lesson\"]\n\
+             [Result \"1-0\"]\n\
+             {Start} 1. 11-15 22-18 ( 1... 23-19? 2. 8-11 ) ( 1... 22-17 {also} ) 2. 15x22!\n\
+             $14 25x18 1-0\n\n"
+        );
+
+        let reparsed = GameTree::from_pdn(&written).unwrap();
+        assert_eq!(reparsed.to_pdn(), written);
+    }
+
+    #[test]
+    fn round_trips_comments_opening_variations() {
+        let text = "1. 11-15 {main} ( {instead} 1. 9-13 ) 1... 22-18 *";
+        let tree = GameTree::from_pdn(text).unwrap();
+
+        let main = tree.children(ROOT)[0];
+        let variation = tree.children(ROOT)[1];
+        assert_eq!(tree.comment(ROOT), None);
+        assert_eq!(tree.comment(main), Some("main"));
+        assert_eq!(tree.comment(variation), Some("instead"));
+
+        let written = tree.to_pdn();
+        assert_eq!(
+            written,
+            "[Result \"*\"]\n1. 11-15 {main} ( 1. 9-13 {instead} ) 1... 22-18 *\n\n"
+        );
+
+        let reparsed = GameTree::from_pdn(&written).unwrap();
+        assert_eq!(reparsed.comment(reparsed.children(ROOT)[1]), Some("instead"));
+        assert_eq!(reparsed.to_pdn(), written);
+    }
+
+    #[test]
+    fn rejects_illegal_pdn() {
+        assert!(GameTree::from_pdn("1. 11-15 11-15").is_err());
+        assert!(GameTree::from_pdn("1. 11-15 ( 1. 9-13").is_err());
+        assert!(GameTree::from_pdn("( 11-15 )").is_err());
+    }
+}
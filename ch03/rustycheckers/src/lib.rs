@@ -1,16 +1,64 @@
-mod board;
-mod game;
+pub mod ai;
+pub mod analysis;
+pub mod board;
+pub mod database;
+pub mod editor;
+pub mod game;
+pub mod game_tree;
+pub mod mcts;
+pub mod network;
+pub mod pdn;
+pub mod protocol;
+pub mod puzzle;
+pub mod report;
+pub mod tournament;
+pub mod zobrist;
 
 #[macro_use]
 extern crate lazy_static;
 
-use board::{Coordinate, GamePiece, Move, PieceColor};
+use ai::{Evaluator, Weights};
+use analysis::{Analysis, Blunder};
+use board::{Coordinate, CoordinateError, GamePiece, Move, PieceColor};
+use editor::{BoardEditor, SetupError};
 use game::GameEngine;
 use mut_static::MutStatic;
+use network::Network;
+use puzzle::{Attempt, Puzzle, PuzzleSession};
+
+/// Size of the buffer the host writes puzzle text into.
+const PUZZLE_BUFFER_SIZE: usize = 1024;
+
+static mut PUZZLE_BUFFER: [u8; PUZZLE_BUFFER_SIZE] = [0; PUZZLE_BUFFER_SIZE];
 
 lazy_static! {
     pub static ref GAME_ENGINE: MutStatic<GameEngine> = 
         MutStatic::from(GameEngine::new());
+    pub static ref PUZZLE: MutStatic<Option<PuzzleSession>> =
+        MutStatic::from(None);
+    /// The position the current game started from, and the moves played.
+    pub static ref GAME_HISTORY: MutStatic<(GameEngine, Vec<Move>)> =
+        MutStatic::from((GameEngine::new(), Vec::new()));
+    static ref LAST_ANALYSIS: MutStatic<Option<Analysis>> =
+        MutStatic::from(None);
+    static ref LAST_BLUNDERS: MutStatic<Vec<Blunder>> =
+        MutStatic::from(Vec::new());
+    pub static ref EDITOR: MutStatic<BoardEditor> =
+        MutStatic::from(BoardEditor::new());
+    /// The evaluation `get_hint` and `analyze_position` search with: `None`
+    /// for material.
+    static ref NETWORK: MutStatic<Option<Network>> =
+        MutStatic::from(None);
+    /// Milliseconds spent on each move of the current game, as far as the
+    /// host has timed them.
+    static ref MOVE_TIMES: MutStatic<Vec<u32>> =
+        MutStatic::from(Vec::new());
+    /// The JSON written by the last `game_report`.
+    static ref LAST_REPORT: MutStatic<String> =
+        MutStatic::from(String::new());
+    /// The player whose side of the board the host's coordinates are from.
+    static ref PERSPECTIVE: MutStatic<PieceColor> =
+        MutStatic::from(PieceColor::Black);
 }
 
 /// Exported method for getting the piece at a given location.
@@ -18,11 +66,13 @@ lazy_static! {
 pub extern "C" fn get_piece(x: i32, y: i32) -> i32 {
     let engine = GAME_ENGINE.read().unwrap();
 
-    let piece = engine.get_piece(Coordinate(x as usize, y as usize));
+    let piece = host_coordinate(x, y)
+        .ok()
+        .and_then(|location| engine.get_piece(location).ok());
     match piece {
-        Ok(Some(result)) => result.into(),
-        Ok(None) => -1,
-        Err(_) => -1,
+        Some(Some(result)) => result.into(),
+        Some(None) => -1,
+        None => -1,
     }
 }
 
@@ -34,18 +84,61 @@ pub extern "C" fn get_current_turn() -> i32 {
     GamePiece::new(engine.current_turn()).into()
 }
 
+/// Exported method for starting a new game from the standard setup with
+/// the given color flag to move first. Returns 1, or 0 for a bad color.
+#[no_mangle]
+pub extern "C" fn new_game(first: i32) -> i32 {
+    match GamePiece::try_from(first) {
+        Ok(piece) if !piece.crowned => {
+            *PUZZLE.write().unwrap() = None;
+            start_game(&GameEngine::starting_with(piece.color));
+            1
+        },
+        _ => 0,
+    }
+}
+
+/// Exported method for choosing whose side of the board coordinates are
+/// seen from, given as a color flag. That player's home row is y = 7 in
+/// every coordinate passed to and from the host, including notifications
+/// and encoded moves. Returns 1, or 0 for a bad color.
+#[no_mangle]
+pub extern "C" fn set_perspective(color: i32) -> i32 {
+    match GamePiece::try_from(color) {
+        Ok(piece) if !piece.crowned => {
+            *PERSPECTIVE.write().unwrap() = piece.color;
+            1
+        },
+        _ => 0,
+    }
+}
+
+/// Exported method for getting the color flag set by `set_perspective`.
+#[no_mangle]
+pub extern "C" fn get_perspective() -> i32 {
+    GamePiece::new(perspective()).into()
+}
+
+/// Exported method for getting the Zobrist key of the current position.
+/// Arrives in JavaScript as a `BigInt`.
+#[no_mangle]
+pub extern "C" fn position_hash() -> u64 {
+    GAME_ENGINE.read().unwrap().position_hash()
+}
+
 /// Exported method for moving a piece.
 #[no_mangle]
 pub extern "C" fn move_piece(from_x: i32, from_y: i32, to_x: i32, to_y: i32) -> i32 {
+    let movement = match host_move(from_x, from_y, to_x, to_y) {
+        Some(movement) => movement,
+        None => return 0,
+    };
     let mut engine = GAME_ENGINE.write().unwrap();
-    let movement = Move::new(
-        (from_x as usize, from_y as usize),
-        (to_x as usize, to_y as usize)
-    );
     let result = engine.move_piece(&movement);
 
     match result {
         Ok(move_result) => {
+            GAME_HISTORY.write().unwrap().1.push(movement);
             unsafe {
                 notify_piece_moved(from_x, from_y, to_x, to_y);
             }
@@ -60,10 +153,449 @@ pub extern "C" fn move_piece(from_x: i32, from_y: i32, to_x: i32, to_y: i32) ->
     }
 }
 
+/// Exported method returning where the host should write puzzle text
+/// before calling `load_puzzle`.
+#[no_mangle]
+pub extern "C" fn puzzle_buffer() -> *mut u8 {
+    std::ptr::addr_of_mut!(PUZZLE_BUFFER) as *mut u8
+}
+
+/// Exported method for starting the puzzle written to the puzzle buffer.
+/// Returns 1 if the puzzle was loaded, 0 if it could not be parsed.
+#[no_mangle]
+pub extern "C" fn load_puzzle(length: i32) -> i32 {
+    if length < 0 || length as usize > PUZZLE_BUFFER_SIZE {
+        return 0;
+    }
+
+    let bytes = unsafe {
+        std::slice::from_raw_parts(std::ptr::addr_of!(PUZZLE_BUFFER) as *const u8, length as usize)
+    };
+    let puzzle = match std::str::from_utf8(bytes).map(Puzzle::parse) {
+        Ok(Ok(puzzle)) => puzzle,
+        _ => return 0,
+    };
+
+    let session = PuzzleSession::new(puzzle);
+    start_game(session.engine());
+    *PUZZLE.write().unwrap() = Some(session);
+    1
+}
+
+/// Exported method for playing a move in the current puzzle.
+/// Returns 0 for an illegal move, 1 for a correct move (the reply has
+/// been played), 2 when the puzzle is solved and 3 for a wrong move.
+#[no_mangle]
+pub extern "C" fn puzzle_move(from_x: i32, from_y: i32, to_x: i32, to_y: i32) -> i32 {
+    let mut puzzle = PUZZLE.write().unwrap();
+    let session = match puzzle.as_mut() {
+        Some(session) => session,
+        None => return 0,
+    };
+    let movement = match host_move(from_x, from_y, to_x, to_y) {
+        Some(movement) => movement,
+        None => return 0,
+    };
+
+    let before = session.engine().clone();
+    let attempt = session.attempt(&movement);
+    *GAME_ENGINE.write().unwrap() = session.engine().clone();
+
+    match attempt {
+        Attempt::Illegal => 0,
+        Attempt::Wrong => {
+            unsafe {
+                notify_puzzle_failed();
+            }
+            3
+        },
+        Attempt::Correct { reply } => {
+            GAME_HISTORY.write().unwrap().1.extend([movement, reply]);
+            notify_move(&before, &movement);
+            let mut after_move = before.clone();
+            let _ = after_move.move_piece(&movement);
+            notify_move(&after_move, &reply);
+            1
+        },
+        Attempt::Solved => {
+            GAME_HISTORY.write().unwrap().1.push(movement);
+            notify_move(&before, &movement);
+            unsafe {
+                notify_puzzle_solved();
+            }
+            2
+        },
+    }
+}
+
+/// Exported method for starting the current puzzle over.
+#[no_mangle]
+pub extern "C" fn reset_puzzle() -> i32 {
+    let mut puzzle = PUZZLE.write().unwrap();
+
+    match puzzle.as_mut() {
+        Some(session) => {
+            session.reset();
+            start_game(session.engine());
+            1
+        },
+        None => 0,
+    }
+}
+
+/// Replaces the current game with one starting from the given position.
+fn start_game(engine: &GameEngine) {
+    *GAME_ENGINE.write().unwrap() = engine.clone();
+    *GAME_HISTORY.write().unwrap() = (engine.clone(), Vec::new());
+    MOVE_TIMES.write().unwrap().clear();
+}
+
+/// Exported method for recording how long the next untimed move of the
+/// current game took, in milliseconds. Returns 1, or 0 if every move played
+/// so far is already timed or the time is negative.
+#[no_mangle]
+pub extern "C" fn record_move_time(millis: i32) -> i32 {
+    let played = GAME_HISTORY.read().unwrap().1.len();
+    let mut times = MOVE_TIMES.write().unwrap();
+
+    match u32::try_from(millis) {
+        Ok(millis) if times.len() < played => {
+            times.push(millis);
+            1
+        },
+        _ => 0,
+    }
+}
+
+/// Exported method for writing a JSON report on the current game, with
+/// each position evaluated to `depth` plies (`analysis::DEFAULT_DEPTH` if
+/// not positive). Returns the length of the JSON, which can be read from
+/// `report_buffer` until the next report.
+#[no_mangle]
+pub extern "C" fn game_report(depth: i32) -> i32 {
+    let history = GAME_HISTORY.read().unwrap();
+    let times = MOVE_TIMES.read().unwrap();
+    let depth = search_depth(depth);
+    let json = with_evaluator(|evaluator| {
+        report::report_with(&history.0, &history.1, &times, depth, evaluator).to_json()
+    });
+    let length = json.len() as i32;
+
+    *LAST_REPORT.write().unwrap() = json;
+    length
+}
+
+/// Exported method returning where the JSON written by `game_report`
+/// starts.
+#[no_mangle]
+pub extern "C" fn report_buffer() -> *const u8 {
+    LAST_REPORT.read().unwrap().as_ptr()
+}
+
+/// Exported method for analyzing the current position. Searches `depth`
+/// plies, or `analysis::DEFAULT_DEPTH` if `depth` is not positive, and
+/// returns the number of legal moves scored.
+#[no_mangle]
+pub extern "C" fn analyze_position(depth: i32) -> i32 {
+    let engine = GAME_ENGINE.read().unwrap();
+    let depth = search_depth(depth);
+    let analysis = with_evaluator(|evaluator| analysis::analyze_with(&engine, depth, evaluator));
+    let count = analysis.moves.len() as i32;
+
+    *LAST_ANALYSIS.write().unwrap() = Some(analysis);
+    count
+}
+
+/// Exported method for the score of the last analyzed position, from the
+/// perspective of the side to move. Positive scores favor that side; a man
+/// is worth 100.
+#[no_mangle]
+pub extern "C" fn get_evaluation() -> i32 {
+    match *LAST_ANALYSIS.read().unwrap() {
+        Some(ref analysis) => analysis.score,
+        None => 0,
+    }
+}
+
+/// Exported method for the move ranked at `index` by the last analysis,
+/// best first, as the host sees it packed by `u16::try_from(Move)`.
+/// Returns -1 past the last move.
+#[no_mangle]
+pub extern "C" fn get_analysis_move(index: i32) -> i32 {
+    match *LAST_ANALYSIS.read().unwrap() {
+        Some(ref analysis) => analysis
+            .moves
+            .get(index as usize)
+            .and_then(|scored| u16::try_from(scored.movement.seen_by(perspective())).ok())
+            .map_or(-1, i32::from),
+        None => -1,
+    }
+}
+
+/// Exported method for the score of the move ranked at `index` by the last
+/// analysis.
+#[no_mangle]
+pub extern "C" fn get_analysis_score(index: i32) -> i32 {
+    match *LAST_ANALYSIS.read().unwrap() {
+        Some(ref analysis) => analysis
+            .moves
+            .get(index as usize)
+            .map_or(0, |scored| scored.score),
+        None => 0,
+    }
+}
+
+/// Exported method for suggesting a move in the current position, as the
+/// host sees it packed by `u16::try_from(Move)`. Returns -1 if the side to
+/// move has no moves.
+#[no_mangle]
+pub extern "C" fn get_hint(depth: i32) -> i32 {
+    let engine = GAME_ENGINE.read().unwrap();
+    let depth = search_depth(depth);
+    let result = with_evaluator(|evaluator| ai::search_with(&engine, depth, evaluator));
+
+    result
+        .best_move
+        .and_then(|movement| u16::try_from(movement.seen_by(perspective())).ok())
+        .map_or(-1, i32::from)
+}
+
+/// Exported method for choosing the evaluation `get_hint`,
+/// `analyze_position`, `detect_blunders` and `game_report` search with: 0
+/// for material, 1 for the built-in neural network. Returns 0, or -1 for
+/// an unknown evaluation.
+#[no_mangle]
+pub extern "C" fn set_evaluation(kind: i32) -> i32 {
+    let network = match kind {
+        0 => None,
+        1 => Some(Network::embedded()),
+        _ => return -1,
+    };
+
+    *NETWORK.write().unwrap() = network;
+    0
+}
+
+/// Exported method for finding the moves of the current game that dropped
+/// the evaluation by at least `threshold` (`analysis::BLUNDER_THRESHOLD`
+/// if not positive). Returns the number of blunders found.
+#[no_mangle]
+pub extern "C" fn detect_blunders(depth: i32, threshold: i32) -> i32 {
+    let history = GAME_HISTORY.read().unwrap();
+    let threshold = if threshold > 0 {
+        threshold
+    } else {
+        analysis::BLUNDER_THRESHOLD
+    };
+    let depth = search_depth(depth);
+    let blunders = with_evaluator(|evaluator| {
+        analysis::find_blunders_with(&history.0, &history.1, depth, threshold, evaluator)
+    });
+    let count = blunders.len() as i32;
+
+    *LAST_BLUNDERS.write().unwrap() = blunders;
+    count
+}
+
+/// Exported method for the ply, counting from zero, of a blunder found by
+/// `detect_blunders`. Returns -1 past the last blunder.
+#[no_mangle]
+pub extern "C" fn get_blunder_ply(index: i32) -> i32 {
+    LAST_BLUNDERS
+        .read()
+        .unwrap()
+        .get(index as usize)
+        .map_or(-1, |blunder| blunder.ply as i32)
+}
+
+/// Exported method for the move that should have been played instead of a
+/// blunder, packed like `get_hint`.
+#[no_mangle]
+pub extern "C" fn get_blunder_best(index: i32) -> i32 {
+    LAST_BLUNDERS
+        .read()
+        .unwrap()
+        .get(index as usize)
+        .and_then(|blunder| u16::try_from(blunder.best.seen_by(perspective())).ok())
+        .map_or(-1, i32::from)
+}
+
+/// Exported method for how much a blunder dropped the evaluation.
+#[no_mangle]
+pub extern "C" fn get_blunder_loss(index: i32) -> i32 {
+    LAST_BLUNDERS
+        .read()
+        .unwrap()
+        .get(index as usize)
+        .map_or(0, |blunder| blunder.loss)
+}
+
+/// Runs `f` with the evaluation chosen by `set_evaluation`.
+fn with_evaluator<T>(f: impl FnOnce(&dyn Evaluator) -> T) -> T {
+    match *NETWORK.read().unwrap() {
+        Some(ref network) => f(network),
+        None => f(&Weights::default()),
+    }
+}
+
+fn search_depth(depth: i32) -> u8 {
+    if depth > 0 {
+        depth.min(analysis::MAX_DEPTH as i32) as u8
+    } else {
+        analysis::DEFAULT_DEPTH
+    }
+}
+
+fn perspective() -> PieceColor {
+    *PERSPECTIVE.read().unwrap()
+}
+
+/// Converts coordinates from the host, seen from the current perspective,
+/// to the board's.
+fn host_coordinate(x: i32, y: i32) -> Result<Coordinate, CoordinateError> {
+    Coordinate::try_from((x, y)).map(|location| location.seen_by(perspective()))
+}
+
+/// Converts a move from the host, or `None` if either end is off the board.
+fn host_move(from_x: i32, from_y: i32, to_x: i32, to_y: i32) -> Option<Move> {
+    Some(Move {
+        from: host_coordinate(from_x, from_y).ok()?,
+        to: host_coordinate(to_x, to_y).ok()?,
+    })
+}
+
+/// Exported method for starting to set up a position. Starts from the
+/// current game when `from_game` is non-zero, otherwise from an empty board.
+#[no_mangle]
+pub extern "C" fn begin_editing(from_game: i32) -> i32 {
+    let editor = if from_game != 0 {
+        BoardEditor::from_engine(&GAME_ENGINE.read().unwrap())
+    } else {
+        BoardEditor::new()
+    };
+
+    *EDITOR.write().unwrap() = editor;
+    1
+}
+
+/// Exported method for getting a piece on the board being edited, encoded
+/// like `get_piece`.
+#[no_mangle]
+pub extern "C" fn editor_get_piece(x: i32, y: i32) -> i32 {
+    let editor = EDITOR.read().unwrap();
+
+    match host_coordinate(x, y).map(|location| editor.piece(location)) {
+        Ok(Some(piece)) => piece.into(),
+        _ => -1,
+    }
+}
+
+/// Exported method for placing a piece, encoded like `get_piece`. Returns 0
+/// on success or a `SetupError` code.
+#[no_mangle]
+pub extern "C" fn editor_place(x: i32, y: i32, piece: i32) -> i32 {
+    let piece = match GamePiece::try_from(piece) {
+        Ok(piece) => piece,
+        Err(_) => return -1,
+    };
+    let location = match host_coordinate(x, y) {
+        Ok(location) => location,
+        Err(_) => return SetupError::OFF_BOARD_CODE,
+    };
+    let mut editor = EDITOR.write().unwrap();
+
+    match editor.place(location, piece) {
+        Ok(()) => 0,
+        Err(error) => error.code(),
+    }
+}
+
+/// Exported method for removing a piece. Returns the removed piece, or -1
+/// if the square was empty.
+#[no_mangle]
+pub extern "C" fn editor_remove(x: i32, y: i32) -> i32 {
+    let mut editor = EDITOR.write().unwrap();
+
+    match host_coordinate(x, y).map(|location| editor.remove(location)) {
+        Ok(Some(piece)) => piece.into(),
+        _ => -1,
+    }
+}
+
+/// Exported method for crowning or uncrowning a piece. Returns the piece as
+/// it now is, or -1 if the square was empty.
+#[no_mangle]
+pub extern "C" fn editor_toggle_king(x: i32, y: i32) -> i32 {
+    let mut editor = EDITOR.write().unwrap();
+
+    match host_coordinate(x, y).map(|location| editor.toggle_king(location)) {
+        Ok(Some(piece)) => piece.into(),
+        _ => -1,
+    }
+}
+
+/// Exported method for choosing the side to move, given as a color flag.
+#[no_mangle]
+pub extern "C" fn editor_set_turn(color: i32) -> i32 {
+    let turn = match GamePiece::try_from(color) {
+        Ok(piece) if !piece.crowned => piece.color,
+        _ => return 0,
+    };
+
+    EDITOR.write().unwrap().set_turn(turn);
+    1
+}
+
+/// Exported method for emptying the board being edited.
+#[no_mangle]
+pub extern "C" fn editor_clear() {
+    EDITOR.write().unwrap().clear();
+}
+
+/// Exported method for checking the setup. Returns 0 if play can start
+/// from it, or a `SetupError` code.
+#[no_mangle]
+pub extern "C" fn editor_validate() -> i32 {
+    match EDITOR.read().unwrap().validate() {
+        Ok(()) => 0,
+        Err(error) => error.code(),
+    }
+}
+
+/// Exported method for starting a new game from the setup. Returns 0 on
+/// success or a `SetupError` code, leaving the current game untouched.
+#[no_mangle]
+pub extern "C" fn editor_start() -> i32 {
+    match EDITOR.read().unwrap().start() {
+        Ok(engine) => {
+            *PUZZLE.write().unwrap() = None;
+            start_game(&engine);
+            0
+        },
+        Err(error) => error.code(),
+    }
+}
+
+/// Sends the notifications for a move played from the given position.
+fn notify_move(engine: &GameEngine, movement: &Move) {
+    let mut engine = engine.clone();
+    let crowned = engine.move_piece(movement).is_ok_and(|result| result.crowned);
+    let Move { from, to } = movement.seen_by(perspective());
+
+    unsafe {
+        notify_piece_moved(from.0 as i32, from.1 as i32, to.0 as i32, to.1 as i32);
+        if crowned {
+            notify_piece_crowned(to.0 as i32, to.1 as i32);
+        }
+    }
+}
+
 // Imported Notification methods.
 extern "C" {
     fn notify_piece_moved(fromX: i32, fromY: i32, toX: i32, toY: i32);
     fn notify_piece_crowned(x: i32, y: i32);
+    fn notify_puzzle_solved();
+    fn notify_puzzle_failed();
 }
 
 const BLACK_FLAG: u8 = 1;
@@ -71,16 +603,16 @@ const WHITE_FLAG: u8 = 2;
 const CROWN_FLAG: u8 = 4;
 
 /// Converts a GamePiece into a bitmapped integer value.
-impl Into<i32> for GamePiece {
-    fn into(self) -> i32 {
+impl From<GamePiece> for i32 {
+    fn from(piece: GamePiece) -> i32 {
         let mut val: u8 = 0;
 
-        match self.color {
+        match piece.color {
             PieceColor::Black => val += BLACK_FLAG,
             PieceColor::White => val += WHITE_FLAG,
         }
 
-        if self.crowned {
+        if piece.crowned {
             val += CROWN_FLAG;
         }
 
@@ -88,4 +620,27 @@ impl Into<i32> for GamePiece {
     }
 }
 
+/// Converts a bitmapped integer value back into a GamePiece. Exactly one
+/// color flag must be set, and no bits other than the flags.
+impl TryFrom<i32> for GamePiece {
+    type Error = ();
+
+    fn try_from(value: i32) -> Result<GamePiece, ()> {
+        let flags = u8::try_from(value).map_err(|_| ())?;
+
+        let color = match flags & !CROWN_FLAG {
+            BLACK_FLAG => PieceColor::Black,
+            WHITE_FLAG => PieceColor::White,
+            _ => return Err(()),
+        };
+        let piece = GamePiece::new(color);
+
+        if flags & CROWN_FLAG != 0 {
+            Ok(GamePiece::crown(piece))
+        } else {
+            Ok(piece)
+        }
+    }
+}
+
 
@@ -0,0 +1,203 @@
+use super::ai;
+use super::board::{Move, PieceColor};
+use super::game::GameEngine;
+
+/// Exploration constant for the UCT selection formula.
+const EXPLORATION: f64 = 1.41;
+/// Playouts longer than this are scored by material instead of played out.
+const PLAYOUT_PLIES: u32 = 80;
+
+/// A small, seedable xorshift generator, so searches can be reproduced.
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        const MIX: u64 = 0x9E37_79B9_7F4A_7C15;
+
+        // A zero state would only ever produce zeros.
+        let state = seed ^ MIX;
+        Rng {
+            state: if state == 0 { MIX } else { state },
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state = x;
+        x
+    }
+
+    /// Returns a value in `0..bound`. `bound` must be non-zero.
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+}
+
+struct Node {
+    movement: Option<Move>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    untried: Vec<Move>,
+    /// The color that made `movement`, whose wins this node counts.
+    mover: PieceColor,
+    visits: u32,
+    wins: f64,
+}
+
+/// Chooses a move with Monte Carlo tree search, running the given number of
+/// random playouts. Returns `None` if there are no legal moves.
+pub fn search(engine: &GameEngine, iterations: u32, rng: &mut Rng) -> Option<Move> {
+    let mut nodes = vec![Node {
+        movement: None,
+        parent: None,
+        children: Vec::new(),
+        untried: engine.valid_moves(),
+        mover: engine.current_turn().opponent(),
+        visits: 0,
+        wins: 0.0,
+    }];
+
+    if nodes[0].untried.is_empty() {
+        return None;
+    }
+
+    for _ in 0..iterations {
+        let mut index = 0;
+        let mut state = engine.clone();
+
+        // Selection: descend through fully expanded nodes.
+        while nodes[index].untried.is_empty() && !nodes[index].children.is_empty() {
+            index = select_child(&nodes, index);
+            if let Some(movement) = nodes[index].movement {
+                let _ = state.move_piece(&movement);
+            }
+        }
+
+        // Expansion: add one untried move.
+        if !nodes[index].untried.is_empty() {
+            let choice = rng.below(nodes[index].untried.len());
+            let movement = nodes[index].untried.swap_remove(choice);
+            let mover = state.current_turn();
+            let _ = state.move_piece(&movement);
+
+            nodes.push(Node {
+                movement: Some(movement),
+                parent: Some(index),
+                children: Vec::new(),
+                untried: state.valid_moves(),
+                mover,
+                visits: 0,
+                wins: 0.0,
+            });
+
+            let child = nodes.len() - 1;
+            nodes[index].children.push(child);
+            index = child;
+        }
+
+        // Simulation and backpropagation.
+        let winner = playout(state, rng);
+        let mut current = Some(index);
+
+        while let Some(node_index) = current {
+            let node = &mut nodes[node_index];
+            node.visits += 1;
+            node.wins += match winner {
+                Some(color) if color == node.mover => 1.0,
+                Some(_) => 0.0,
+                None => 0.5,
+            };
+            current = node.parent;
+        }
+    }
+
+    nodes[0]
+        .children
+        .iter()
+        .max_by_key(|child| nodes[**child].visits)
+        .and_then(|child| nodes[*child].movement)
+}
+
+fn select_child(nodes: &[Node], parent: usize) -> usize {
+    let parent_visits = nodes[parent].visits.max(1) as f64;
+
+    *nodes[parent]
+        .children
+        .iter()
+        .max_by(|a, b| uct(&nodes[**a], parent_visits).total_cmp(&uct(&nodes[**b], parent_visits)))
+        .unwrap_or(&parent)
+}
+
+fn uct(node: &Node, parent_visits: f64) -> f64 {
+    if node.visits == 0 {
+        return f64::INFINITY;
+    }
+
+    let visits = node.visits as f64;
+    node.wins / visits + EXPLORATION * (parent_visits.ln() / visits).sqrt()
+}
+
+/// Plays random moves until the game ends or the playout limit is reached,
+/// then returns the winner, judging unfinished games by material.
+fn playout(mut state: GameEngine, rng: &mut Rng) -> Option<PieceColor> {
+    for _ in 0..PLAYOUT_PLIES {
+        let moves = state.valid_moves();
+
+        if moves.is_empty() {
+            return state.winner();
+        }
+
+        let movement = moves[rng.below(moves.len())];
+        let _ = state.move_piece(&movement);
+    }
+
+    let score = ai::evaluate(&state);
+
+    match score {
+        0 => None,
+        s if s > 0 => Some(state.current_turn()),
+        _ => Some(state.current_turn().opponent()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::board::Move;
+    use super::super::game::GameEngine;
+    use super::{search, Rng};
+
+    #[test]
+    fn rng_is_reproducible() {
+        let mut a = Rng::new(7);
+        let mut b = Rng::new(7);
+
+        for _ in 0..10 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+        assert_ne!(Rng::new(0).next_u64(), 0);
+    }
+
+    #[test]
+    fn search_returns_legal_move() {
+        let engine = GameEngine::new();
+        let movement = search(&engine, 200, &mut Rng::new(1)).unwrap();
+
+        assert!(engine.valid_moves().contains(&movement));
+    }
+
+    #[test]
+    fn search_is_reproducible() {
+        let mut engine = GameEngine::new();
+        engine.move_piece(&Move::new((2, 5), (3, 4))).unwrap();
+
+        let first = search(&engine, 300, &mut Rng::new(3));
+        let second = search(&engine, 300, &mut Rng::new(3));
+        assert_eq!(first, second);
+    }
+}
@@ -0,0 +1,430 @@
+//! A small neural network for evaluating positions, and the self-play
+//! training that produces its weights.
+//!
+//! The network has one hidden layer of rectified linear units over 128
+//! inputs: one per dark square for each of the side to move's men and kings
+//! and the opponent's men and kings. The board is turned around when White
+//! is to move, so the network always sees the position from the mover's
+//! side. Its output is squashed with `tanh` to an expected result between
+//! -1 (lost) and 1 (won).
+
+use std::io::{self, Read, Write};
+
+use super::ai::Evaluator;
+use super::board::{PieceColor, SQUARE_COUNT};
+use super::game::GameEngine;
+use super::mcts::Rng;
+use super::pdn::GameResult;
+use super::tournament::{self, Contestant, Settings, Strategy};
+
+/// Number of network inputs: four piece kinds on each dark square.
+pub const INPUTS: usize = 4 * SQUARE_COUNT as usize;
+/// Hidden layer size used unless training asks for another.
+pub const DEFAULT_HIDDEN: usize = 32;
+/// Largest hidden layer `save` writes and `load` accepts.
+pub const MAX_HIDDEN: usize = 1024;
+/// Evaluation of a certain win, in the same units as `ai::MAN_VALUE`.
+const OUTPUT_SCALE: f32 = 1000.0;
+
+/// Identifies the weights format; bump `FORMAT_VERSION` on any change.
+const MAGIC: &[u8; 4] = b"RCNN";
+const FORMAT_VERSION: u8 = 1;
+
+/// Weights built into the library, trained with
+/// `train_network --games 2000 --epochs 30 --generations 2`.
+const EMBEDDED: &[u8] = include_bytes!("../weights/network.bin");
+
+/// The indices of the inputs that are on in a position; all others are off.
+pub fn features(engine: &GameEngine) -> Vec<usize> {
+    let turn = engine.current_turn();
+
+    engine
+        .pieces()
+        .into_iter()
+        .filter_map(|(location, piece)| {
+            let square = location.to_square()? as usize - 1;
+            let (square, own) = match turn {
+                PieceColor::Black => (square, piece.color == PieceColor::Black),
+                // Turning the board around reverses the square numbers.
+                PieceColor::White => (
+                    SQUARE_COUNT as usize - 1 - square,
+                    piece.color == PieceColor::White,
+                ),
+            };
+            let kind = match (own, piece.crowned) {
+                (true, false) => 0,
+                (true, true) => 1,
+                (false, false) => 2,
+                (false, true) => 3,
+            };
+
+            Some(kind * SQUARE_COUNT as usize + square)
+        })
+        .collect()
+}
+
+/// A position from a self-play game and how the game ended for the side to
+/// move: 1 for a win, -1 for a loss and 0 for a draw.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sample {
+    pub features: Vec<usize>,
+    pub target: f32,
+}
+
+/// A fully-connected network with one hidden layer.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Network {
+    hidden: usize,
+    /// `hidden` weights for each input in turn.
+    input_weights: Vec<f32>,
+    hidden_biases: Vec<f32>,
+    output_weights: Vec<f32>,
+    output_bias: f32,
+}
+
+impl Network {
+    /// Creates a network with small random weights, ready for training.
+    pub fn new(hidden: usize, rng: &mut Rng) -> Network {
+        let input_range = 0.2;
+        let output_range = 1.0 / (hidden as f32).sqrt();
+
+        Network {
+            hidden,
+            input_weights: (0..INPUTS * hidden)
+                .map(|_| uniform(rng, input_range))
+                .collect(),
+            hidden_biases: vec![0.0; hidden],
+            output_weights: (0..hidden).map(|_| uniform(rng, output_range)).collect(),
+            output_bias: 0.0,
+        }
+    }
+
+    /// The network built into the library.
+    pub fn embedded() -> Network {
+        Network::load(&mut &EMBEDDED[..]).expect("the embedded network is valid")
+    }
+
+    pub fn hidden(&self) -> usize {
+        self.hidden
+    }
+
+    /// Computes the hidden layer's inputs for the given features.
+    fn hidden_sums(&self, features: &[usize]) -> Vec<f32> {
+        let mut sums = self.hidden_biases.clone();
+
+        for &feature in features {
+            let weights = &self.input_weights[feature * self.hidden..][..self.hidden];
+            for (sum, weight) in sums.iter_mut().zip(weights) {
+                *sum += weight;
+            }
+        }
+
+        sums
+    }
+
+    fn output(&self, sums: &[f32]) -> f32 {
+        let total = sums
+            .iter()
+            .zip(&self.output_weights)
+            .map(|(sum, weight)| sum.max(0.0) * weight)
+            .sum::<f32>();
+
+        (total + self.output_bias).tanh()
+    }
+
+    /// The expected result for the side to move, between -1 and 1.
+    pub fn predict(&self, features: &[usize]) -> f32 {
+        self.output(&self.hidden_sums(features))
+    }
+
+    /// Runs one pass of stochastic gradient descent over the samples in a
+    /// random order, returning the mean squared error seen along the way.
+    pub fn train_epoch(&mut self, samples: &[Sample], rate: f32, rng: &mut Rng) -> f32 {
+        let mut order: Vec<usize> = (0..samples.len()).collect();
+        for i in (1..order.len()).rev() {
+            order.swap(i, rng.below(i + 1));
+        }
+
+        let mut total_error = 0.0;
+
+        for index in order {
+            let sample = &samples[index];
+            let sums = self.hidden_sums(&sample.features);
+            let predicted = self.output(&sums);
+            let error = predicted - sample.target;
+            total_error += error * error;
+
+            // Gradient of the squared error before the tanh.
+            let gradient = 2.0 * error * (1.0 - predicted * predicted) * rate;
+
+            for (j, &sum) in sums.iter().enumerate() {
+                if sum <= 0.0 {
+                    continue;
+                }
+
+                let hidden_gradient = gradient * self.output_weights[j];
+                self.output_weights[j] -= gradient * sum;
+                self.hidden_biases[j] -= hidden_gradient;
+                for &feature in &sample.features {
+                    self.input_weights[feature * self.hidden + j] -= hidden_gradient;
+                }
+            }
+            self.output_bias -= gradient;
+        }
+
+        total_error / samples.len().max(1) as f32
+    }
+
+    /// Writes the weights in the network's binary format. Nothing is written
+    /// if the hidden layer is too large for the format.
+    pub fn save<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let hidden = match u16::try_from(self.hidden) {
+            Ok(hidden) if self.hidden <= MAX_HIDDEN => hidden,
+            _ => return Err(invalid_data("hidden layer too large")),
+        };
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[FORMAT_VERSION])?;
+        writer.write_all(&hidden.to_le_bytes())?;
+
+        let values = self
+            .input_weights
+            .iter()
+            .chain(&self.hidden_biases)
+            .chain(&self.output_weights)
+            .chain([&self.output_bias]);
+        for value in values {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads weights written by `save`.
+    pub fn load<R: Read>(reader: &mut R) -> io::Result<Network> {
+        let mut header = [0; 7];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != MAGIC {
+            return Err(invalid_data("not a network"));
+        }
+        if header[4] != FORMAT_VERSION {
+            return Err(invalid_data("unsupported network version"));
+        }
+
+        let hidden = u16::from_le_bytes([header[5], header[6]]) as usize;
+        if hidden == 0 || hidden > MAX_HIDDEN {
+            return Err(invalid_data("bad hidden layer size"));
+        }
+
+        Ok(Network {
+            hidden,
+            input_weights: read_values(reader, INPUTS * hidden)?,
+            hidden_biases: read_values(reader, hidden)?,
+            output_weights: read_values(reader, hidden)?,
+            output_bias: read_values(reader, 1)?[0],
+        })
+    }
+}
+
+impl Evaluator for Network {
+    fn evaluate(&self, engine: &GameEngine) -> i32 {
+        (self.predict(&features(engine)) * OUTPUT_SCALE).round() as i32
+    }
+}
+
+/// How self-play games for training are played.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SelfPlay {
+    pub games: usize,
+    /// Moves played at random at the start of each game, so that games
+    /// differ. Positions before they are done are not sampled.
+    pub random_plies: usize,
+    /// Games still going after this many plies are drawn.
+    pub max_plies: u32,
+}
+
+impl Default for SelfPlay {
+    fn default() -> SelfPlay {
+        SelfPlay {
+            games: 200,
+            random_plies: 6,
+            max_plies: 200,
+        }
+    }
+}
+
+/// Plays games of a strategy against itself under the tournament rules,
+/// labelling every position after the random opening with how the game
+/// ended for the side to move.
+pub fn self_play(settings: &SelfPlay, strategy: &Strategy, rng: &mut Rng) -> Vec<Sample> {
+    let player = Contestant {
+        name: "self-play".to_owned(),
+        strategy: strategy.clone(),
+    };
+    let rules = Settings {
+        max_plies: settings.max_plies,
+        ..Settings::default()
+    };
+    let mut samples = Vec::new();
+
+    for _game in 0..settings.games {
+        let mut engine = GameEngine::new();
+        let mut opening = Vec::new();
+        while opening.len() < settings.random_plies {
+            let moves = engine.valid_moves();
+            if moves.is_empty() {
+                break;
+            }
+            let movement = moves[rng.below(moves.len())];
+            if engine.move_piece(&movement).is_err() {
+                break;
+            }
+            opening.push(movement);
+        }
+
+        let (record, _termination) = tournament::play_game(&player, &player, &opening, &rules, rng);
+        let winner = match record.result {
+            GameResult::BlackWins => Some(PieceColor::Black),
+            GameResult::WhiteWins => Some(PieceColor::White),
+            GameResult::Draw | GameResult::Unfinished => None,
+        };
+
+        let mut engine = GameEngine::new();
+        for (ply, movement) in record.moves.iter().enumerate() {
+            if ply >= opening.len() {
+                samples.push(Sample {
+                    features: features(&engine),
+                    target: match winner {
+                        Some(color) if color == engine.current_turn() => 1.0,
+                        Some(_) => -1.0,
+                        None => 0.0,
+                    },
+                });
+            }
+            if engine.move_piece(movement).is_err() {
+                break;
+            }
+        }
+    }
+
+    samples
+}
+
+/// A value drawn evenly from `-range..range`.
+fn uniform(rng: &mut Rng, range: f32) -> f32 {
+    let unit = (rng.next_u64() >> 40) as f32 / (1u64 << 24) as f32;
+    (unit * 2.0 - 1.0) * range
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_owned())
+}
+
+fn read_values<R: Read>(reader: &mut R, count: usize) -> io::Result<Vec<f32>> {
+    let mut bytes = vec![0; count * 4];
+    reader.read_exact(&mut bytes)?;
+
+    Ok(bytes
+        .chunks_exact(4)
+        .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::ai::{self, Evaluator, Weights};
+    use super::super::board::{Move, PieceColor};
+    use super::super::game::GameEngine;
+    use super::super::mcts::Rng;
+    use super::super::tournament::Strategy;
+    use super::{features, Network, Sample, SelfPlay, MAX_HIDDEN};
+
+    #[test]
+    fn features_are_from_the_movers_side() {
+        let engine = GameEngine::new();
+        let mut black = features(&engine);
+        black.sort();
+
+        assert_eq!(black.len(), 24);
+        // Black's own men are on squares 1-12.
+        assert_eq!(black[..12], (0..12).collect::<Vec<usize>>()[..]);
+
+        // The starting setup is symmetric, so White sees the same inputs.
+        let white_to_move = GameEngine::from_position(&engine.pieces(), PieceColor::White);
+        let mut white = features(&white_to_move);
+        white.sort();
+        assert_eq!(white, black);
+    }
+
+    #[test]
+    fn training_fits_samples() {
+        let mut rng = Rng::new(7);
+        let mut network = Network::new(8, &mut rng);
+        let engine = GameEngine::new();
+        let mut ahead = engine.clone();
+        ahead.move_piece(&Move::new((2, 5), (3, 4))).unwrap();
+
+        let samples = vec![
+            Sample {
+                features: features(&engine),
+                target: 0.5,
+            },
+            Sample {
+                features: features(&ahead),
+                target: -0.5,
+            },
+        ];
+
+        let first = network.train_epoch(&samples, 0.05, &mut rng);
+        let mut last = first;
+        for _epoch in 0..200 {
+            last = network.train_epoch(&samples, 0.05, &mut rng);
+        }
+
+        assert!(last < first / 10.0);
+        assert!(network.evaluate(&engine) > 0);
+        assert!(network.evaluate(&ahead) < 0);
+    }
+
+    #[test]
+    fn saves_and_loads() {
+        let network = Network::new(4, &mut Rng::new(1));
+        let mut bytes = Vec::new();
+        network.save(&mut bytes).unwrap();
+
+        assert_eq!(Network::load(&mut bytes.as_slice()).unwrap(), network);
+        assert!(Network::load(&mut &bytes[..20]).is_err());
+
+        let oversized = Network::new(MAX_HIDDEN + 1, &mut Rng::new(1));
+        let mut written = Vec::new();
+        assert!(oversized.save(&mut written).is_err());
+        assert!(written.is_empty());
+    }
+
+    #[test]
+    fn embedded_network_plays() {
+        let network = Network::embedded();
+        let engine = GameEngine::new();
+        let best = ai::search_with(&engine, 2, &network).best_move.unwrap();
+
+        assert!(engine.valid_moves().contains(&best));
+    }
+
+    #[test]
+    fn self_play_labels_positions() {
+        let settings = SelfPlay {
+            games: 2,
+            ..SelfPlay::default()
+        };
+        let strategy = Strategy::AlphaBeta {
+            depth: 1,
+            weights: Weights::default(),
+        };
+        let samples = super::self_play(&settings, &strategy, &mut Rng::new(3));
+
+        assert!(!samples.is_empty());
+        assert!(samples
+            .iter()
+            .all(|sample| [-1.0, 0.0, 1.0].contains(&sample.target)));
+    }
+}
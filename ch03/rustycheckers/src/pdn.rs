@@ -0,0 +1,383 @@
+use super::board::Move;
+use super::protocol::format_move;
+
+/// Longest line written in a PDN move section.
+const LINE_WIDTH: usize = 79;
+/// Tags written first, in this order, ahead of any others.
+const ROSTER_TAGS: [&str; 6] = ["Event", "Site", "Date", "Round", "Black", "White"];
+
+/// The result of a game. PDN results are written from Black's (the first
+/// player's) side, so `1-0` is a Black win.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum GameResult {
+    BlackWins,
+    WhiteWins,
+    Draw,
+    Unfinished,
+}
+
+impl GameResult {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            GameResult::BlackWins => "1-0",
+            GameResult::WhiteWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+            GameResult::Unfinished => "*",
+        }
+    }
+
+    pub fn parse(text: &str) -> Option<GameResult> {
+        match text {
+            "1-0" | "2-0" => Some(GameResult::BlackWins),
+            "0-1" | "0-2" => Some(GameResult::WhiteWins),
+            "1/2-1/2" | "1-1" => Some(GameResult::Draw),
+            "*" => Some(GameResult::Unfinished),
+            _ => None,
+        }
+    }
+}
+
+/// A game from the standard starting position, with its PDN tags.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GameRecord {
+    pub tags: Vec<(String, String)>,
+    pub moves: Vec<Move>,
+    pub result: GameResult,
+}
+
+impl GameRecord {
+    pub fn new(moves: Vec<Move>, result: GameResult) -> GameRecord {
+        GameRecord {
+            tags: Vec::new(),
+            moves,
+            result,
+        }
+    }
+
+    /// Adds or replaces a tag.
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(key, _value)| key == name) {
+            Some(tag) => tag.1 = value.to_owned(),
+            None => self.tags.push((name.to_owned(), value.to_owned())),
+        }
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(key, _value)| key == name)
+            .map(|(_key, value)| value.as_str())
+    }
+
+    /// Writes the game as PDN, ending with a blank line.
+    pub fn to_pdn(&self) -> String {
+        let mut pdn = write_tags(&self.tags, self.result);
+
+        let mut tokens = Vec::new();
+        for (index, movement) in self.moves.iter().enumerate() {
+            if index % 2 == 0 {
+                tokens.push(format!("{}.", index / 2 + 1));
+            }
+            tokens.push(format_move(movement));
+        }
+        tokens.push(self.result.as_str().to_owned());
+
+        pdn.push_str(&wrap(&tokens));
+        pdn.push_str("\n\n");
+        pdn
+    }
+}
+
+/// Writes a tag section: the roster tags first, then the rest, then the
+/// result.
+pub fn write_tags(tags: &[(String, String)], result: GameResult) -> String {
+    let mut pdn = String::new();
+
+    let roster = ROSTER_TAGS
+        .iter()
+        .filter_map(|name| tags.iter().find(|(key, _value)| key == name));
+    let others = tags
+        .iter()
+        .filter(|(key, _value)| key != "Result" && !ROSTER_TAGS.contains(&key.as_str()));
+
+    for (name, value) in roster.chain(others) {
+        pdn.push_str(&format!("[{} \"{}\"]\n", name, escape(value)));
+    }
+    pdn.push_str(&format!("[Result \"{}\"]\n", result.as_str()));
+    pdn
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Joins tokens with spaces, breaking lines before they grow too long.
+pub fn wrap(tokens: &[String]) -> String {
+    let mut text = String::new();
+    let mut line_length = 0;
+
+    for token in tokens {
+        if line_length > 0 && line_length + 1 + token.len() > LINE_WIDTH {
+            text.push('\n');
+            line_length = 0;
+        } else if line_length > 0 {
+            text.push(' ');
+            line_length += 1;
+        }
+
+        text.push_str(token);
+        line_length += token.len();
+    }
+
+    text
+}
+
+/// A lexical element of PDN text.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Token {
+    Tag(String, String),
+    /// A move in standard notation, such as `11-15` or `15x22`.
+    Move(String),
+    /// A numeric annotation glyph; `!`, `?` and friends map to `$1`-`$6`.
+    Nag(u8),
+    Comment(String),
+    VariationStart,
+    VariationEnd,
+    Result(GameResult),
+}
+
+/// Suffix annotations and the glyph numbers they stand for.
+pub const SUFFIX_NAGS: [(&str, u8); 6] = [
+    ("!!", 3),
+    ("??", 4),
+    ("!?", 5),
+    ("?!", 6),
+    ("!", 1),
+    ("?", 2),
+];
+
+/// Splits PDN text into tokens. Move numbers are skipped.
+pub fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((start, character)) = chars.next() {
+        match character {
+            c if c.is_whitespace() => {}
+            '[' => {
+                let end = find_closing(text, start, ']')?;
+                tokens.push(parse_tag(&text[start + 1..end])?);
+                skip_to(&mut chars, end);
+            }
+            '{' => {
+                let end = find_closing(text, start, '}')?;
+                tokens.push(Token::Comment(text[start + 1..end].trim().to_owned()));
+                skip_to(&mut chars, end);
+            }
+            ';' => {
+                let end = text[start..]
+                    .find('\n')
+                    .map_or(text.len(), |end| start + end);
+                tokens.push(Token::Comment(text[start + 1..end].trim().to_owned()));
+                skip_to(&mut chars, end);
+            }
+            '(' => tokens.push(Token::VariationStart),
+            ')' => tokens.push(Token::VariationEnd),
+            _ => {
+                let end = text[start..]
+                    .find(|c: char| c.is_whitespace() || "[]{}();".contains(c))
+                    .map_or(text.len(), |end| start + end);
+                tokens.extend(parse_word(&text[start..end])?);
+
+                if end > start + character.len_utf8() {
+                    skip_to(&mut chars, end - 1);
+                }
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Advances the iterator past the character at `index`.
+fn skip_to<I: Iterator<Item = (usize, char)>>(chars: &mut std::iter::Peekable<I>, index: usize) {
+    while let Some((position, _character)) = chars.peek() {
+        if *position > index {
+            break;
+        }
+        chars.next();
+    }
+}
+
+fn find_closing(text: &str, start: usize, closing: char) -> Result<usize, String> {
+    text[start..]
+        .find(closing)
+        .map(|end| start + end)
+        .ok_or_else(|| format!("missing '{}'", closing))
+}
+
+fn parse_tag(body: &str) -> Result<Token, String> {
+    let body = body.trim();
+    let (name, value) = body
+        .split_once(char::is_whitespace)
+        .ok_or_else(|| format!("malformed tag [{}]", body))?;
+    let value = value
+        .trim()
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .ok_or_else(|| format!("unquoted tag value in [{}]", body))?;
+
+    Ok(Token::Tag(
+        name.to_owned(),
+        value.replace("\\\"", "\"").replace("\\\\", "\\"),
+    ))
+}
+
+/// Parses a word: a move number, move, result or annotation glyph.
+fn parse_word(word: &str) -> Result<Vec<Token>, String> {
+    if let Some(result) = GameResult::parse(word) {
+        return Ok(vec![Token::Result(result)]);
+    }
+
+    if let Some(number) = word.strip_prefix('$') {
+        let nag = number
+            .parse()
+            .map_err(|_| format!("malformed annotation {}", word))?;
+        return Ok(vec![Token::Nag(nag)]);
+    }
+
+    // Move numbers such as `12.` or `12...`, possibly followed by a move.
+    let word = match word.find('.') {
+        Some(dot) if word[..dot].chars().all(|c| c.is_ascii_digit()) => {
+            word[dot..].trim_start_matches('.')
+        }
+        _ => word,
+    };
+
+    if word.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let body_end = word.find(['!', '?']).unwrap_or(word.len());
+    let (body, suffix) = word.split_at(body_end);
+
+    let mut tokens = Vec::new();
+    if !body.is_empty() {
+        if !body
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == '-' || c == 'x')
+        {
+            return Err(format!("unexpected text {}", word));
+        }
+        tokens.push(Token::Move(body.to_owned()));
+    }
+
+    if !suffix.is_empty() {
+        let nag = SUFFIX_NAGS
+            .iter()
+            .find(|(glyph, _nag)| *glyph == suffix)
+            .map(|(_glyph, nag)| *nag)
+            .ok_or_else(|| format!("unknown annotation {}", suffix))?;
+        tokens.push(Token::Nag(nag));
+    }
+
+    Ok(tokens)
+}
+
+/// Splits a token stream holding several games into one stream per game.
+///
+/// A new game starts at the first tag after a game's moves or result.
+pub fn split_games(tokens: Vec<Token>) -> Vec<Vec<Token>> {
+    let mut games = Vec::new();
+    let mut current: Vec<Token> = Vec::new();
+    let mut in_moves = false;
+
+    for token in tokens {
+        let is_tag = matches!(token, Token::Tag(..));
+
+        if is_tag && in_moves {
+            games.push(std::mem::take(&mut current));
+            in_moves = false;
+        }
+
+        in_moves |= !is_tag;
+        current.push(token);
+    }
+
+    if !current.is_empty() {
+        games.push(current);
+    }
+
+    games
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::board::Move;
+    use super::{split_games, tokenize, GameRecord, GameResult, Token};
+
+    #[test]
+    fn results_round_trip() {
+        for result in [
+            GameResult::BlackWins,
+            GameResult::WhiteWins,
+            GameResult::Draw,
+            GameResult::Unfinished,
+        ] {
+            assert_eq!(GameResult::parse(result.as_str()), Some(result));
+        }
+    }
+
+    #[test]
+    fn writes_game() {
+        let mut record = GameRecord::new(
+            vec![Move::new((2, 5), (3, 4)), Move::new((5, 2), (4, 3))],
+            GameResult::Draw,
+        );
+        record.set_tag("Black", "depth 4");
+        record.set_tag("Event", "Test \"match\"");
+
+        assert_eq!(
+            record.to_pdn(),
+            "[Event \"Test \\\"match\\\"\"]\n[Black \"depth 4\"]\n[Result \"1/2-1/2\"]\n1. 11-15 22-18 1/2-1/2\n\n"
+        );
+    }
+
+    #[test]
+    fn tokenizes_movetext() {
+        let tokens = tokenize(
+            "[Event \"Club \\\"night\\\"\"]\n1. 11-15 {good} 22-18! (22-17 $2) 2. 15x22 ; ok\n1-0",
+        )
+        .unwrap();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Tag("Event".to_owned(), "Club \"night\"".to_owned()),
+                Token::Move("11-15".to_owned()),
+                Token::Comment("good".to_owned()),
+                Token::Move("22-18".to_owned()),
+                Token::Nag(1),
+                Token::VariationStart,
+                Token::Move("22-17".to_owned()),
+                Token::Nag(2),
+                Token::VariationEnd,
+                Token::Move("15x22".to_owned()),
+                Token::Comment("ok".to_owned()),
+                Token::Result(GameResult::BlackWins),
+            ]
+        );
+
+        assert!(tokenize("1. 11-15 {unterminated").is_err());
+        assert!(tokenize("1. e4").is_err());
+    }
+
+    #[test]
+    fn splits_games() {
+        let tokens = tokenize("[Event \"a\"] 1. 11-15 * [Event \"b\"] 1. 9-13 *").unwrap();
+        let games = split_games(tokens);
+
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[1][0], Token::Tag("Event".to_owned(), "b".to_owned()));
+    }
+}
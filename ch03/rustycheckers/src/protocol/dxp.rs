@@ -0,0 +1,626 @@
+//! The DXP protocol for playing games between engines over TCP.
+//!
+//! Messages are ASCII strings terminated by a NUL byte. The first character
+//! names the message type and the rest are fixed-width fields. Squares use
+//! the 1-32 numbering, and colors are `W` for white and `Z` for black.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::time::Instant;
+
+use super::super::board::{Move, PieceColor};
+use super::super::game::GameEngine;
+use super::{captured_squares, find_turn, new_game, search_turn};
+
+pub const PROTOCOL_VERSION: u8 = 1;
+const NAME_WIDTH: usize = 32;
+
+/// Acceptance codes sent in a `GameAccept` or `BackAccept` message.
+pub const ACCEPTED: u8 = 0;
+pub const UNSUPPORTED_VERSION: u8 = 1;
+pub const UNSUPPORTED_POSITION: u8 = 2;
+pub const REFUSED: u8 = 9;
+
+/// Reasons sent in a `GameEnd` message, from the sender's point of view.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EndReason {
+    Unknown,
+    Loss,
+    Draw,
+    Win,
+}
+
+/// How a game over DXP ended, from our point of view.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Outcome {
+    Win,
+    Loss,
+    Draw,
+    Aborted,
+}
+
+/// The starting position requested in a `GameRequest`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum StartPosition {
+    Standard,
+    /// A side to move followed by one character per square.
+    Custom(PieceColor, String),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Message {
+    GameRequest {
+        initiator: String,
+        follower_color: PieceColor,
+        minutes: u16,
+        moves: u16,
+        start: StartPosition,
+    },
+    GameAccept {
+        follower: String,
+        code: u8,
+    },
+    Move {
+        seconds: u16,
+        from: u8,
+        to: u8,
+        captures: Vec<u8>,
+    },
+    GameEnd {
+        reason: EndReason,
+        stop: bool,
+    },
+    Chat(String),
+    BackRequest {
+        move_number: u16,
+        color: PieceColor,
+    },
+    BackAccept {
+        code: u8,
+    },
+}
+
+impl Message {
+    /// Encodes the message, without the terminating NUL.
+    pub fn encode(&self) -> String {
+        match self {
+            Message::GameRequest {
+                initiator,
+                follower_color,
+                minutes,
+                moves,
+                start,
+            } => {
+                let start = match start {
+                    StartPosition::Standard => "A".to_owned(),
+                    StartPosition::Custom(color, squares) => {
+                        format!("B{}{}", color_char(*color), squares)
+                    }
+                };
+
+                format!(
+                    "R{:02}{}{}{:03}{:03}{}",
+                    PROTOCOL_VERSION,
+                    pad_name(initiator),
+                    color_char(*follower_color),
+                    minutes,
+                    moves,
+                    start
+                )
+            }
+            Message::GameAccept { follower, code } => {
+                format!("A{}{}", pad_name(follower), code)
+            }
+            Message::Move {
+                seconds,
+                from,
+                to,
+                captures,
+            } => {
+                let captured: String = captures
+                    .iter()
+                    .map(|square| format!("{:02}", square))
+                    .collect();
+
+                format!(
+                    "M{:04}{:02}{:02}{:02}{}",
+                    seconds,
+                    from,
+                    to,
+                    captures.len(),
+                    captured
+                )
+            }
+            Message::GameEnd { reason, stop } => {
+                let reason = match reason {
+                    EndReason::Unknown => 0,
+                    EndReason::Loss => 1,
+                    EndReason::Draw => 2,
+                    EndReason::Win => 3,
+                };
+
+                format!("E{}{}", reason, u8::from(*stop))
+            }
+            Message::Chat(text) => format!("C{}", text),
+            Message::BackRequest { move_number, color } => {
+                format!("B{:03}{}", move_number, color_char(*color))
+            }
+            Message::BackAccept { code } => format!("K{}", code),
+        }
+    }
+
+    /// Decodes a message from its text, without the terminating NUL.
+    pub fn decode(text: &str) -> io::Result<Message> {
+        let mut fields = Fields::new(text);
+
+        let message = match fields.take(1)? {
+            "R" => {
+                let _version = fields.number(2)?;
+                let initiator = fields.take(NAME_WIDTH)?.trim_end().to_owned();
+                let follower_color = parse_color(fields.take(1)?)?;
+                let minutes = fields.number(3)?;
+                let moves = fields.number(3)?;
+                let start = match fields.take(1)? {
+                    "A" => StartPosition::Standard,
+                    "B" => {
+                        let color = parse_color(fields.take(1)?)?;
+                        StartPosition::Custom(color, fields.rest().to_owned())
+                    }
+                    _ => return Err(invalid("unknown starting position")),
+                };
+
+                Message::GameRequest {
+                    initiator,
+                    follower_color,
+                    minutes,
+                    moves,
+                    start,
+                }
+            }
+            "A" => Message::GameAccept {
+                follower: fields.take(NAME_WIDTH)?.trim_end().to_owned(),
+                code: fields.number(1)? as u8,
+            },
+            "M" => {
+                let seconds = fields.number(4)?;
+                let from = fields.number(2)? as u8;
+                let to = fields.number(2)? as u8;
+                let count = fields.number(2)?;
+                let captures = (0..count)
+                    .map(|_| fields.number(2).map(|square| square as u8))
+                    .collect::<io::Result<Vec<u8>>>()?;
+
+                Message::Move {
+                    seconds,
+                    from,
+                    to,
+                    captures,
+                }
+            }
+            "E" => {
+                let reason = match fields.number(1)? {
+                    1 => EndReason::Loss,
+                    2 => EndReason::Draw,
+                    3 => EndReason::Win,
+                    _ => EndReason::Unknown,
+                };
+
+                Message::GameEnd {
+                    reason,
+                    stop: fields.number(1)? == 1,
+                }
+            }
+            "C" => Message::Chat(fields.rest().to_owned()),
+            "B" => Message::BackRequest {
+                move_number: fields.number(3)?,
+                color: parse_color(fields.take(1)?)?,
+            },
+            "K" => Message::BackAccept {
+                code: fields.number(1)? as u8,
+            },
+            _ => return Err(invalid("unknown message type")),
+        };
+
+        Ok(message)
+    }
+}
+
+/// Reads fixed-width fields from a message.
+struct Fields<'a> {
+    text: &'a str,
+}
+
+impl<'a> Fields<'a> {
+    fn new(text: &'a str) -> Fields<'a> {
+        Fields { text }
+    }
+
+    fn take(&mut self, width: usize) -> io::Result<&'a str> {
+        if self.text.len() < width || !self.text.is_char_boundary(width) {
+            return Err(invalid("message is too short"));
+        }
+
+        let (field, rest) = self.text.split_at(width);
+        self.text = rest;
+        Ok(field)
+    }
+
+    fn number(&mut self, width: usize) -> io::Result<u16> {
+        self.take(width)?
+            .trim()
+            .parse()
+            .map_err(|_| invalid("expected a number"))
+    }
+
+    fn rest(&mut self) -> &'a str {
+        std::mem::take(&mut self.text)
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_owned())
+}
+
+fn pad_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .filter(char::is_ascii)
+        .take(NAME_WIDTH)
+        .collect();
+    format!("{:<width$}", name, width = NAME_WIDTH)
+}
+
+fn color_char(color: PieceColor) -> char {
+    match color {
+        PieceColor::White => 'W',
+        PieceColor::Black => 'Z',
+    }
+}
+
+fn parse_color(field: &str) -> io::Result<PieceColor> {
+    match field {
+        "W" => Ok(PieceColor::White),
+        "Z" => Ok(PieceColor::Black),
+        _ => Err(invalid("unknown color")),
+    }
+}
+
+/// Reads one NUL-terminated message. Returns `None` when the peer hangs up.
+pub fn read_message<R: BufRead>(reader: &mut R) -> io::Result<Option<Message>> {
+    let mut buffer = Vec::new();
+
+    if reader.read_until(0, &mut buffer)? == 0 {
+        return Ok(None);
+    }
+
+    if buffer.last() == Some(&0) {
+        buffer.pop();
+    }
+
+    let text = String::from_utf8(buffer).map_err(|_| invalid("message is not ASCII"))?;
+    Message::decode(&text).map(Some)
+}
+
+/// Writes one message followed by its NUL terminator.
+pub fn write_message<W: Write>(writer: &mut W, message: &Message) -> io::Result<()> {
+    writer.write_all(message.encode().as_bytes())?;
+    writer.write_all(&[0])?;
+    writer.flush()
+}
+
+/// Settings for our side of a DXP game.
+#[derive(Clone, Debug)]
+pub struct Player {
+    pub name: String,
+    pub depth: u8,
+    /// The number of plies after which the side to move claims a draw.
+    pub max_plies: u32,
+}
+
+impl Player {
+    pub fn new(name: &str, depth: u8) -> Player {
+        Player {
+            name: name.to_owned(),
+            depth,
+            max_plies: 200,
+        }
+    }
+}
+
+/// A DXP connection to another engine.
+pub struct Connection<S: Read + Write> {
+    reader: BufReader<S>,
+    writer: S,
+}
+
+impl Connection<TcpStream> {
+    /// Connects to a DXP follower.
+    pub fn connect<A: ToSocketAddrs>(address: A) -> io::Result<Connection<TcpStream>> {
+        let stream = TcpStream::connect(address)?;
+        Connection::from_stream(stream)
+    }
+
+    /// Waits for a DXP initiator to connect.
+    pub fn accept(listener: &TcpListener) -> io::Result<Connection<TcpStream>> {
+        let (stream, _address) = listener.accept()?;
+        Connection::from_stream(stream)
+    }
+
+    fn from_stream(stream: TcpStream) -> io::Result<Connection<TcpStream>> {
+        stream.set_nodelay(true)?;
+        let reader = BufReader::new(stream.try_clone()?);
+
+        Ok(Connection {
+            reader,
+            writer: stream,
+        })
+    }
+}
+
+impl<S: Read + Write> Connection<S> {
+    pub fn send(&mut self, message: &Message) -> io::Result<()> {
+        write_message(&mut self.writer, message)
+    }
+
+    pub fn receive(&mut self) -> io::Result<Option<Message>> {
+        read_message(&mut self.reader)
+    }
+
+    /// Requests a game from the standard position and plays it.
+    pub fn play_as_initiator(
+        &mut self,
+        player: &Player,
+        our_color: PieceColor,
+    ) -> io::Result<Outcome> {
+        self.send(&Message::GameRequest {
+            initiator: player.name.clone(),
+            follower_color: our_color.opponent(),
+            minutes: 0,
+            moves: 0,
+            start: StartPosition::Standard,
+        })?;
+
+        loop {
+            match self.receive()? {
+                Some(Message::GameAccept { code: ACCEPTED, .. }) => break,
+                Some(Message::GameAccept { .. }) | None => return Ok(Outcome::Aborted),
+                Some(_) => continue,
+            }
+        }
+
+        self.play(player, our_color)
+    }
+
+    /// Waits for a game request, accepts it if we can play it, and plays it.
+    pub fn play_as_follower(&mut self, player: &Player) -> io::Result<Outcome> {
+        let (our_color, start) = loop {
+            match self.receive()? {
+                Some(Message::GameRequest {
+                    follower_color,
+                    start,
+                    ..
+                }) => break (follower_color, start),
+                Some(_) => continue,
+                None => return Ok(Outcome::Aborted),
+            }
+        };
+
+        if start != StartPosition::Standard {
+            self.send(&Message::GameAccept {
+                follower: player.name.clone(),
+                code: UNSUPPORTED_POSITION,
+            })?;
+            return Ok(Outcome::Aborted);
+        }
+
+        self.send(&Message::GameAccept {
+            follower: player.name.clone(),
+            code: ACCEPTED,
+        })?;
+
+        self.play(player, our_color)
+    }
+
+    /// Plays a game from the standard position until either side ends it.
+    fn play(&mut self, player: &Player, our_color: PieceColor) -> io::Result<Outcome> {
+        let mut engine = new_game();
+
+        loop {
+            if engine.current_turn() == our_color {
+                if let Some(reason) = self.game_over(&engine, player, our_color) {
+                    return self.end_game(reason);
+                }
+
+                let started = Instant::now();
+                let turn = match search_turn(&engine, player.depth) {
+                    Some(turn) => turn,
+                    None => return self.end_game(EndReason::Loss),
+                };
+
+                if !apply_turn(&mut engine, &turn) {
+                    return self.end_game(EndReason::Unknown);
+                }
+
+                self.send(&Message::Move {
+                    seconds: started.elapsed().as_secs().min(9999) as u16,
+                    from: turn[0].from.to_square().unwrap_or(0),
+                    to: turn[turn.len() - 1].to.to_square().unwrap_or(0),
+                    captures: captured_squares(&turn),
+                })?;
+                continue;
+            }
+
+            match self.receive()? {
+                Some(Message::Move {
+                    from, to, captures, ..
+                }) => {
+                    let applied = find_turn(&engine, from, to, &captures)
+                        .is_some_and(|turn| apply_turn(&mut engine, &turn));
+
+                    if !applied {
+                        return self.end_game(EndReason::Unknown);
+                    }
+                }
+                Some(Message::GameEnd { reason, .. }) => {
+                    self.send(&Message::GameEnd {
+                        reason: reason_for_peer(reason),
+                        stop: true,
+                    })?;
+                    return Ok(outcome_for(reason_for_peer(reason)));
+                }
+                Some(Message::BackRequest { .. }) => {
+                    self.send(&Message::BackAccept { code: REFUSED })?;
+                }
+                Some(_) => continue,
+                None => return Ok(Outcome::Aborted),
+            }
+        }
+    }
+
+    /// Returns why the game is over on our turn, if it is.
+    fn game_over(
+        &self,
+        engine: &GameEngine,
+        player: &Player,
+        our_color: PieceColor,
+    ) -> Option<EndReason> {
+        match engine.winner() {
+            Some(winner) if winner == our_color => Some(EndReason::Win),
+            Some(_) => Some(EndReason::Loss),
+            None if engine.move_count() >= player.max_plies => Some(EndReason::Draw),
+            None => None,
+        }
+    }
+
+    /// Sends a game end and waits for the peer's confirmation.
+    fn end_game(&mut self, reason: EndReason) -> io::Result<Outcome> {
+        self.send(&Message::GameEnd { reason, stop: true })?;
+
+        loop {
+            match self.receive()? {
+                Some(Message::GameEnd { .. }) | None => return Ok(outcome_for(reason)),
+                Some(_) => continue,
+            }
+        }
+    }
+}
+
+/// Plays every move of a turn, returning whether they were all legal.
+fn apply_turn(engine: &mut GameEngine, turn: &[Move]) -> bool {
+    turn.iter()
+        .all(|movement| engine.move_piece(movement).is_ok())
+}
+
+/// Translates the peer's end reason to our point of view.
+fn reason_for_peer(reason: EndReason) -> EndReason {
+    match reason {
+        EndReason::Loss => EndReason::Win,
+        EndReason::Win => EndReason::Loss,
+        other => other,
+    }
+}
+
+fn outcome_for(reason: EndReason) -> Outcome {
+    match reason {
+        EndReason::Win => Outcome::Win,
+        EndReason::Loss => Outcome::Loss,
+        EndReason::Draw => Outcome::Draw,
+        EndReason::Unknown => Outcome::Aborted,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::super::board::PieceColor;
+    use super::{Connection, EndReason, Message, Outcome, Player, StartPosition};
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn messages_round_trip() {
+        let messages = vec![
+            Message::GameRequest {
+                initiator: "rustycheckers".to_owned(),
+                follower_color: PieceColor::White,
+                minutes: 10,
+                moves: 40,
+                start: StartPosition::Standard,
+            },
+            Message::GameAccept {
+                follower: "opponent".to_owned(),
+                code: 0,
+            },
+            Message::Move {
+                seconds: 3,
+                from: 15,
+                to: 22,
+                captures: vec![18],
+            },
+            Message::GameEnd {
+                reason: EndReason::Draw,
+                stop: false,
+            },
+            Message::Chat("good game".to_owned()),
+            Message::BackRequest {
+                move_number: 12,
+                color: PieceColor::Black,
+            },
+            Message::BackAccept { code: 1 },
+        ];
+
+        for message in messages {
+            assert_eq!(Message::decode(&message.encode()).unwrap(), message);
+        }
+    }
+
+    #[test]
+    fn encodes_fixed_width_fields() {
+        let movement = Message::Move {
+            seconds: 12,
+            from: 9,
+            to: 14,
+            captures: vec![],
+        };
+
+        assert_eq!(movement.encode(), "M0012091400");
+        assert_eq!(
+            Message::GameEnd {
+                reason: EndReason::Win,
+                stop: true
+            }
+            .encode(),
+            "E31"
+        );
+        assert!(Message::decode("M00").is_err());
+        assert!(Message::decode("X").is_err());
+    }
+
+    #[test]
+    fn loopback_game() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let follower = thread::spawn(move || {
+            let mut connection = Connection::accept(&listener).unwrap();
+            let mut player = Player::new("follower", 2);
+            player.max_plies = 60;
+            connection.play_as_follower(&player).unwrap()
+        });
+
+        let mut connection = Connection::connect(address).unwrap();
+        let mut player = Player::new("initiator", 3);
+        player.max_plies = 60;
+        let initiator_outcome = connection
+            .play_as_initiator(&player, PieceColor::Black)
+            .unwrap();
+        let follower_outcome = follower.join().unwrap();
+
+        let expected = match initiator_outcome {
+            Outcome::Win => Outcome::Loss,
+            Outcome::Loss => Outcome::Win,
+            Outcome::Draw => Outcome::Draw,
+            Outcome::Aborted => panic!("game was aborted"),
+        };
+        assert_eq!(follower_outcome, expected);
+    }
+}
@@ -0,0 +1,265 @@
+//! A text protocol in the style of the Hub protocol used by draughts GUIs.
+//!
+//! Each line is a command followed by `name=value` arguments, with values
+//! optionally wrapped in double quotes. The engine answers `hub` with its
+//! identity and parameters, `init` with `ready` and `go` with `done move=..`.
+
+use std::io::{self, BufRead, Write};
+
+use super::super::game::{GameEngine, Rules};
+use super::{format_turn, new_game, parse_position, parse_turn, search_turn, START_POSITION};
+
+pub const ENGINE_NAME: &str = "rustycheckers";
+pub const DEFAULT_DEPTH: u8 = 6;
+pub const MAX_DEPTH: u8 = 20;
+
+/// A parsed command line: the command name and its arguments.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Command {
+    pub name: String,
+    pub args: Vec<(String, String)>,
+}
+
+impl Command {
+    /// Parses a command line, returning `None` for blank lines.
+    pub fn parse(line: &str) -> Option<Command> {
+        let mut tokens = tokenize(line).into_iter();
+        let name = tokens.next()?;
+
+        let args = tokens
+            .map(|token| match token.split_once('=') {
+                Some((key, value)) => (key.to_owned(), value.to_owned()),
+                None => (token, String::new()),
+            })
+            .collect();
+
+        Some(Command { name, args })
+    }
+
+    /// Returns the value of a named argument.
+    pub fn arg(&self, name: &str) -> Option<&str> {
+        self.args
+            .iter()
+            .find(|(key, _value)| key == name)
+            .map(|(_key, value)| value.as_str())
+    }
+}
+
+/// Splits a line on whitespace, keeping quoted values together and
+/// stripping the quotes.
+fn tokenize(line: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+
+    for character in line.chars() {
+        match character {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+
+    tokens
+}
+
+/// Engine state for a Hub session.
+pub struct HubSession {
+    engine: GameEngine,
+    depth: u8,
+}
+
+impl HubSession {
+    pub fn new(depth: u8) -> HubSession {
+        HubSession {
+            engine: new_game(),
+            depth,
+        }
+    }
+
+    /// Returns the current game.
+    pub fn engine(&self) -> &GameEngine {
+        &self.engine
+    }
+
+    /// Handles a single command, returning the reply lines and whether the
+    /// session should end.
+    pub fn handle(&mut self, command: &Command) -> (Vec<String>, bool) {
+        let replies = match command.name.as_str() {
+            "hub" => vec![
+                format!(
+                    "id name={} version={}",
+                    ENGINE_NAME,
+                    env!("CARGO_PKG_VERSION")
+                ),
+                format!(
+                    "param name=depth value={} type=int min=1 max={}",
+                    self.depth, MAX_DEPTH
+                ),
+                "wait".to_owned(),
+            ],
+            "init" => vec!["ready".to_owned()],
+            "ping" => vec!["pong".to_owned()],
+            "quit" => return (Vec::new(), true),
+            "new-game" => {
+                self.engine = new_game();
+                Vec::new()
+            }
+            "set-param" => self.set_param(command),
+            "level" => self.level(command),
+            "pos" => self.position(command),
+            "go" => self.go(),
+            // Searches are synchronous, so there is never anything to stop.
+            "stop" | "ponder-hit" => Vec::new(),
+            _ => vec![error(&format!("unknown command {}", command.name))],
+        };
+
+        (replies, false)
+    }
+
+    fn set_param(&mut self, command: &Command) -> Vec<String> {
+        match (command.arg("name"), command.arg("value")) {
+            (Some("depth"), Some(value)) => self.set_depth(value),
+            _ => vec![error("unknown parameter")],
+        }
+    }
+
+    fn level(&mut self, command: &Command) -> Vec<String> {
+        match command.arg("depth") {
+            Some(value) => self.set_depth(value),
+            None => Vec::new(),
+        }
+    }
+
+    fn set_depth(&mut self, value: &str) -> Vec<String> {
+        match value.parse::<u8>() {
+            Ok(depth) if (1..=MAX_DEPTH).contains(&depth) => {
+                self.depth = depth;
+                Vec::new()
+            }
+            _ => vec![error("invalid depth")],
+        }
+    }
+
+    /// Sets up a position from a base position (the start position if none
+    /// is given) and a list of moves.
+    fn position(&mut self, command: &Command) -> Vec<String> {
+        let base = command.arg("pos").unwrap_or(START_POSITION);
+        let mut engine = match parse_position(base) {
+            Some(engine) => engine.with_rules(Rules::Standard),
+            None => return vec![error("invalid position")],
+        };
+
+        for notation in command.arg("moves").unwrap_or("").split_whitespace() {
+            let turn = match parse_turn(&engine, notation) {
+                Some(turn) => turn,
+                None => return vec![error(&format!("illegal move {}", notation))],
+            };
+
+            for movement in turn {
+                if engine.move_piece(&movement).is_err() {
+                    return vec![error(&format!("illegal move {}", notation))];
+                }
+            }
+        }
+
+        self.engine = engine;
+        Vec::new()
+    }
+
+    fn go(&mut self) -> Vec<String> {
+        match search_turn(&self.engine, self.depth) {
+            Some(turn) => vec![format!("done move={}", format_turn(&turn))],
+            None => vec!["done".to_owned()],
+        }
+    }
+}
+
+fn error(message: &str) -> String {
+    format!("error message=\"{}\"", message)
+}
+
+/// Runs a Hub session until `quit` or the end of input.
+pub fn run<R: BufRead, W: Write>(input: R, mut output: W, depth: u8) -> io::Result<()> {
+    let mut session = HubSession::new(depth);
+
+    for line in input.lines() {
+        let command = match Command::parse(&line?) {
+            Some(command) => command,
+            None => continue,
+        };
+
+        let (replies, quit) = session.handle(&command);
+
+        for reply in replies {
+            writeln!(output, "{}", reply)?;
+        }
+        output.flush()?;
+
+        if quit {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::super::board::PieceColor;
+    use super::{run, Command, HubSession};
+    use std::io::Cursor;
+
+    #[test]
+    fn parses_quoted_arguments() {
+        let command = Command::parse("pos moves=\"11-15 22-18\" pos=B").unwrap();
+
+        assert_eq!(command.name, "pos");
+        assert_eq!(command.arg("moves"), Some("11-15 22-18"));
+        assert_eq!(command.arg("pos"), Some("B"));
+        assert_eq!(Command::parse("   "), None);
+    }
+
+    #[test]
+    fn applies_moves_to_position() {
+        let mut session = HubSession::new(2);
+        let command = Command::parse("pos moves=\"11-15 22-18\"").unwrap();
+        let (replies, quit) = session.handle(&command);
+
+        assert!(replies.is_empty());
+        assert!(!quit);
+        assert_eq!(session.engine().move_count(), 2);
+        assert_eq!(session.engine().current_turn(), PieceColor::Black);
+
+        let command = Command::parse("pos moves=\"11-15 11-15\"").unwrap();
+        let (replies, _quit) = session.handle(&command);
+        assert_eq!(replies, vec!["error message=\"illegal move 11-15\""]);
+    }
+
+    #[test]
+    fn loopback_session() {
+        let script = "hub\ninit\nlevel depth=2\npos moves=\"11-15\"\ngo think\nping\nquit\nping\n";
+        let mut output = Vec::new();
+
+        run(Cursor::new(script), &mut output, 4).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert!(lines[0].starts_with("id name=rustycheckers"));
+        assert_eq!(lines[1], "param name=depth value=4 type=int min=1 max=20");
+        assert_eq!(lines[2], "wait");
+        assert_eq!(lines[3], "ready");
+        assert!(lines[4].starts_with("done move="));
+        assert_eq!(lines[5], "pong");
+        assert_eq!(lines.len(), 6);
+    }
+}
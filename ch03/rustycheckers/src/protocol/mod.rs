@@ -0,0 +1,348 @@
+//! Adapters for talking to external checkers programs.
+//!
+//! Both protocols describe squares with the standard 1-32 numbering (see
+//! `Coordinate::to_square`), so the helpers here translate between that and
+//! the engine's moves.
+//!
+//! Other engines play standard rules, so games over either protocol use
+//! `Rules::Standard`. A turn there can be several of our moves (a
+//! multi-jump), which the `*_turn` helpers deal in.
+
+pub mod dxp;
+pub mod hub;
+
+use super::ai;
+use super::board::{Coordinate, GamePiece, Move, PieceColor, SQUARE_COUNT};
+use super::game::{GameEngine, Rules};
+
+/// Position string for the standard starting setup, in the Hub format.
+pub const START_POSITION: &str = "Bbbbbbbbbbbbbeeeeeeeewwwwwwwwwwww";
+
+/// Formats a move in standard notation, e.g. `11-15` or `15x22`.
+pub fn format_move(movement: &Move) -> String {
+    let from = movement.from.to_square().unwrap_or(0);
+    let to = movement.to.to_square().unwrap_or(0);
+    let separator = if movement.jumped().is_some() {
+        'x'
+    } else {
+        '-'
+    };
+
+    format!("{}{}{}", from, separator, to)
+}
+
+/// Parses a move in standard notation, returning it if it is legal in the
+/// given position.
+///
+/// Captures may list the captured squares after the destination
+/// (`15x22x18`); they are checked against the engine's capture.
+pub fn parse_move(engine: &GameEngine, notation: &str) -> Option<Move> {
+    let squares = notation
+        .split(['-', 'x'])
+        .map(|square| square.trim().parse::<u8>().ok())
+        .collect::<Option<Vec<u8>>>()?;
+
+    if squares.len() < 2 {
+        return None;
+    }
+
+    find_move(engine, squares[0], squares[1], &squares[2..])
+}
+
+/// Finds the legal move between two squares, checking any listed captures.
+pub fn find_move(engine: &GameEngine, from: u8, to: u8, captures: &[u8]) -> Option<Move> {
+    let movement = Move {
+        from: Coordinate::from_square(from)?,
+        to: Coordinate::from_square(to)?,
+    };
+
+    if !engine.valid_moves().contains(&movement) {
+        return None;
+    }
+
+    let jumped = movement
+        .jumped()
+        .and_then(|coordinate| coordinate.to_square());
+    let captures_match = match captures {
+        [] => true,
+        [captured] => jumped == Some(*captured),
+        _ => false,
+    };
+
+    if captures_match {
+        Some(movement)
+    } else {
+        None
+    }
+}
+
+/// Returns a standard-rules game from the standard position.
+pub fn new_game() -> GameEngine {
+    GameEngine::new().with_rules(Rules::Standard)
+}
+
+/// Formats a whole turn, e.g. `11-15`, `15x22` or `6x15x24x10x19` for a
+/// multi-jump from 6 to 15 over 10 and 19. Multi-jumps list their captures
+/// since different routes can join the same two squares.
+pub fn format_turn(turn: &[Move]) -> String {
+    let (first, last) = match (turn.first(), turn.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return String::new(),
+    };
+
+    if turn.len() == 1 {
+        return format_move(first);
+    }
+
+    let mut text = format!(
+        "{}x{}",
+        first.from.to_square().unwrap_or(0),
+        last.to.to_square().unwrap_or(0)
+    );
+    for square in captured_squares(turn) {
+        text.push_str(&format!("x{}", square));
+    }
+
+    text
+}
+
+/// Parses a whole turn in the notation written by `format_turn`, returning
+/// its moves if it is legal in the given position.
+pub fn parse_turn(engine: &GameEngine, notation: &str) -> Option<Vec<Move>> {
+    let squares = notation
+        .split(['-', 'x'])
+        .map(|square| square.trim().parse::<u8>().ok())
+        .collect::<Option<Vec<u8>>>()?;
+
+    if squares.len() < 2 {
+        return None;
+    }
+
+    find_turn(engine, squares[0], squares[1], &squares[2..])
+}
+
+/// Finds the legal turn between two squares. When captures are listed they
+/// must be exactly the pieces the turn takes, in any order; otherwise the
+/// first route found is used.
+pub fn find_turn(engine: &GameEngine, from: u8, to: u8, captures: &[u8]) -> Option<Vec<Move>> {
+    let from = Coordinate::from_square(from)?;
+    let to = Coordinate::from_square(to)?;
+    let mut wanted = captures.to_vec();
+    wanted.sort_unstable();
+
+    turns_from(engine, from).into_iter().find(|turn| {
+        let mut taken = captured_squares(turn);
+        taken.sort_unstable();
+
+        turn.last().map(|last| last.to) == Some(to) && (wanted.is_empty() || taken == wanted)
+    })
+}
+
+/// Every complete turn for the piece on `from`, following multi-jumps to
+/// the end.
+fn turns_from(engine: &GameEngine, from: Coordinate) -> Vec<Vec<Move>> {
+    let mut turns = Vec::new();
+
+    for movement in engine.valid_moves() {
+        if movement.from != from {
+            continue;
+        }
+
+        let mut child = engine.clone();
+        if child.move_piece(&movement).is_err() {
+            continue;
+        }
+
+        if child.current_turn() == engine.current_turn() {
+            for rest in turns_from(&child, movement.to) {
+                let mut turn = vec![movement];
+                turn.extend(rest);
+                turns.push(turn);
+            }
+        } else {
+            turns.push(vec![movement]);
+        }
+    }
+
+    turns
+}
+
+/// Searches for the side to move's whole turn, continuing a multi-jump
+/// until it ends. Returns `None` if there is no legal move.
+pub fn search_turn(engine: &GameEngine, depth: u8) -> Option<Vec<Move>> {
+    let mut position = engine.clone();
+    let mut turn = Vec::new();
+
+    while position.current_turn() == engine.current_turn() {
+        let movement = ai::search(&position, depth).best_move?;
+        position.move_piece(&movement).ok()?;
+        turn.push(movement);
+    }
+
+    Some(turn)
+}
+
+/// Returns the square numbers captured by the moves of a turn.
+pub fn captured_squares(turn: &[Move]) -> Vec<u8> {
+    turn.iter()
+        .filter_map(|movement| movement.jumped())
+        .filter_map(|coordinate| coordinate.to_square())
+        .collect()
+}
+
+/// Describes a position as the side to move (`B` or `W`) followed by one
+/// character per square: `e` for empty, `b`/`w` for men and `B`/`W` for kings.
+pub fn position_string(engine: &GameEngine) -> String {
+    let mut position = String::with_capacity(SQUARE_COUNT as usize + 1);
+
+    position.push(match engine.current_turn() {
+        PieceColor::Black => 'B',
+        PieceColor::White => 'W',
+    });
+
+    for square in 1..=SQUARE_COUNT {
+        let piece = Coordinate::from_square(square)
+            .and_then(|coordinate| engine.get_piece(coordinate).ok())
+            .flatten();
+
+        position.push(piece_char(piece));
+    }
+
+    position
+}
+
+/// Builds a game from a position string in the format written by
+/// `position_string`.
+pub fn parse_position(position: &str) -> Option<GameEngine> {
+    let mut characters = position.chars();
+
+    let turn = match characters.next()? {
+        'B' => PieceColor::Black,
+        'W' => PieceColor::White,
+        _ => return None,
+    };
+
+    let mut pieces = Vec::new();
+    let mut square = 0;
+
+    for character in characters {
+        square += 1;
+        let coordinate = Coordinate::from_square(square)?;
+
+        let piece = match character {
+            'e' => continue,
+            'b' => GamePiece::new(PieceColor::Black),
+            'w' => GamePiece::new(PieceColor::White),
+            'B' => GamePiece::crown(GamePiece::new(PieceColor::Black)),
+            'W' => GamePiece::crown(GamePiece::new(PieceColor::White)),
+            _ => return None,
+        };
+
+        pieces.push((coordinate, piece));
+    }
+
+    if square != SQUARE_COUNT {
+        return None;
+    }
+
+    Some(GameEngine::from_position(&pieces, turn))
+}
+
+fn piece_char(piece: Option<GamePiece>) -> char {
+    match piece {
+        None => 'e',
+        Some(GamePiece {
+            color: PieceColor::Black,
+            crowned: false,
+        }) => 'b',
+        Some(GamePiece {
+            color: PieceColor::White,
+            crowned: false,
+        }) => 'w',
+        Some(GamePiece {
+            color: PieceColor::Black,
+            crowned: true,
+        }) => 'B',
+        Some(GamePiece {
+            color: PieceColor::White,
+            crowned: true,
+        }) => 'W',
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::board::{Coordinate, GamePiece, Move, PieceColor};
+    use super::super::game::{GameEngine, Rules};
+    use super::{
+        format_move, format_turn, parse_move, parse_position, parse_turn, position_string,
+        search_turn, START_POSITION,
+    };
+
+    #[test]
+    fn square_numbers_round_trip() {
+        for square in 1..=32 {
+            let coordinate = Coordinate::from_square(square).unwrap();
+            assert_eq!(coordinate.to_square(), Some(square));
+        }
+
+        assert_eq!(Coordinate::from_square(0), None);
+        assert_eq!(Coordinate::from_square(33), None);
+        assert_eq!(Coordinate(0, 0).to_square(), None);
+    }
+
+    #[test]
+    fn formats_and_parses_moves() {
+        let engine = GameEngine::new();
+        let movement = Move::new((2, 5), (3, 4));
+
+        assert_eq!(format_move(&movement), "11-15");
+        assert_eq!(parse_move(&engine, "11-15"), Some(movement));
+        assert_eq!(parse_move(&engine, "11-19"), None);
+        assert_eq!(parse_move(&engine, "22-18"), None);
+    }
+
+    #[test]
+    fn start_position_string() {
+        let engine = GameEngine::new();
+        assert_eq!(position_string(&engine), START_POSITION);
+    }
+
+    #[test]
+    fn position_strings_round_trip() {
+        let engine = parse_position(START_POSITION).unwrap();
+        assert_eq!(engine.valid_moves(), GameEngine::new().valid_moves());
+
+        let custom = "WeeeeeBeeeeeeeeeeeeeeeeeeeeeeeeew";
+        assert_eq!(position_string(&parse_position(custom).unwrap()), custom);
+
+        assert!(parse_position("Beee").is_none());
+        assert!(parse_position("Xbbbbbbbbbbbbeeeeeeeewwwwwwwwwwww").is_none());
+    }
+
+    #[test]
+    fn multi_jumps_are_one_turn() {
+        let black = GamePiece::new(PieceColor::Black);
+        let white = GamePiece::new(PieceColor::White);
+        let pieces = [
+            (Coordinate(1, 6), black),
+            (Coordinate(6, 7), black),
+            (Coordinate(2, 5), white),
+            (Coordinate(4, 3), white),
+            (Coordinate(7, 0), white),
+        ];
+        let engine =
+            GameEngine::from_position(&pieces, PieceColor::Black).with_rules(Rules::Standard);
+        let turn = vec![Move::new((1, 6), (3, 4)), Move::new((3, 4), (5, 2))];
+
+        assert_eq!(format_turn(&turn), "8x22x11x18");
+        assert_eq!(parse_turn(&engine, "8x22x11x18"), Some(turn.clone()));
+        assert_eq!(parse_turn(&engine, "8x22"), Some(turn.clone()));
+        assert_eq!(search_turn(&engine, 2), Some(turn));
+
+        // The capture is forced, and has to be finished.
+        assert_eq!(parse_turn(&engine, "1-6"), None);
+        assert_eq!(parse_turn(&engine, "8x15"), None);
+        assert_eq!(parse_turn(&engine, "8x22x11"), None);
+    }
+}
@@ -0,0 +1,340 @@
+use super::ai;
+use super::analysis;
+use super::board::{Move, PieceColor};
+use super::game::GameEngine;
+use super::protocol::{format_move, parse_move, parse_position, position_string};
+
+/// A position with a winning line for the side to move.
+///
+/// The solution alternates the solver's moves with the defender's replies,
+/// starting and ending with a solver move.
+#[derive(Clone)]
+pub struct Puzzle {
+    pub start: GameEngine,
+    pub solution: Vec<Move>,
+}
+
+impl Puzzle {
+    /// Creates a puzzle, checking that the solution can be played out.
+    pub fn new(start: GameEngine, solution: Vec<Move>) -> Result<Puzzle, String> {
+        if solution.len().is_multiple_of(2) {
+            return Err("the solution must end with the solver's move".to_owned());
+        }
+
+        let mut engine = start.clone();
+        for movement in &solution {
+            engine
+                .move_piece(movement)
+                .map_err(|_| "the solution contains an illegal move".to_owned())?;
+        }
+
+        Ok(Puzzle { start, solution })
+    }
+
+    /// Parses a puzzle written as a position string (see
+    /// `protocol::position_string`) followed by the solution in standard
+    /// notation, e.g. `B...eew 14x23 27-18 23x14`.
+    pub fn parse(text: &str) -> Result<Puzzle, String> {
+        let mut words = text.split_whitespace();
+        let position = words.next().ok_or("the puzzle is empty")?;
+        let start = parse_position(position).ok_or("invalid position")?;
+
+        let mut engine = start.clone();
+        let mut solution = Vec::new();
+
+        for notation in words {
+            let movement =
+                parse_move(&engine, notation).ok_or_else(|| format!("illegal move {}", notation))?;
+            let _ = engine.move_piece(&movement);
+            solution.push(movement);
+        }
+
+        Puzzle::new(start, solution)
+    }
+
+    /// Writes the puzzle in the format read by `parse`.
+    pub fn to_text(&self) -> String {
+        let mut text = position_string(&self.start);
+
+        for movement in &self.solution {
+            text.push(' ');
+            text.push_str(&format_move(movement));
+        }
+
+        text
+    }
+
+    /// The color solving the puzzle.
+    pub fn solver(&self) -> PieceColor {
+        self.start.current_turn()
+    }
+
+    /// The number of moves the solver has to find.
+    pub fn solver_moves(&self) -> usize {
+        self.solution.len().div_ceil(2)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PuzzleStatus {
+    InProgress,
+    Solved,
+    Failed,
+}
+
+/// The result of trying a move in a puzzle.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Attempt {
+    /// The move is not legal here, or the puzzle is over; nothing changed.
+    Illegal,
+    /// The move is legal but does not win; the puzzle is failed.
+    Wrong,
+    /// The move was accepted and the defender replied.
+    Correct { reply: Move },
+    /// The move completed the puzzle.
+    Solved,
+}
+
+/// A puzzle being played, checking each move against the solution.
+///
+/// Moves that differ from the solution are still accepted if a search finds
+/// them at least as good as the expected move. Once play leaves the written
+/// line, the defender's replies and the reference moves come from search.
+pub struct PuzzleSession {
+    puzzle: Puzzle,
+    engine: GameEngine,
+    solver_moves_played: usize,
+    on_solution: bool,
+    status: PuzzleStatus,
+}
+
+impl PuzzleSession {
+    pub fn new(puzzle: Puzzle) -> PuzzleSession {
+        PuzzleSession {
+            engine: puzzle.start.clone(),
+            puzzle,
+            solver_moves_played: 0,
+            on_solution: true,
+            status: PuzzleStatus::InProgress,
+        }
+    }
+
+    pub fn puzzle(&self) -> &Puzzle {
+        &self.puzzle
+    }
+
+    /// Returns the current position.
+    pub fn engine(&self) -> &GameEngine {
+        &self.engine
+    }
+
+    pub fn status(&self) -> PuzzleStatus {
+        self.status
+    }
+
+    /// Starts the puzzle over.
+    pub fn reset(&mut self) {
+        *self = PuzzleSession::new(self.puzzle.clone());
+    }
+
+    /// Returns the move the solver is expected to play next.
+    pub fn hint(&self) -> Option<Move> {
+        if self.status != PuzzleStatus::InProgress {
+            return None;
+        }
+
+        self.reference_move()
+    }
+
+    /// Plays the solver's move, checks it, and answers with the defender's
+    /// reply.
+    pub fn attempt(&mut self, movement: &Move) -> Attempt {
+        if self.status != PuzzleStatus::InProgress || !self.engine.valid_moves().contains(movement)
+        {
+            return Attempt::Illegal;
+        }
+
+        let reference = match self.reference_move() {
+            Some(reference) => reference,
+            None => return Attempt::Illegal,
+        };
+
+        if *movement != reference {
+            let depth = self.remaining_plies();
+
+            if analysis::score_move(&self.engine, movement, depth)
+                < analysis::score_move(&self.engine, &reference, depth)
+            {
+                self.status = PuzzleStatus::Failed;
+                return Attempt::Wrong;
+            }
+
+            self.on_solution = false;
+        }
+
+        let _ = self.engine.move_piece(movement);
+        self.solver_moves_played += 1;
+
+        if self.solver_moves_played >= self.puzzle.solver_moves() || self.engine.winner().is_some()
+        {
+            self.status = PuzzleStatus::Solved;
+            return Attempt::Solved;
+        }
+
+        let reply = if self.on_solution {
+            Some(self.puzzle.solution[self.solver_moves_played * 2 - 1])
+        } else {
+            ai::search(&self.engine, self.remaining_plies().max(1)).best_move
+        };
+
+        match reply {
+            Some(reply) if self.engine.move_piece(&reply).is_ok() => Attempt::Correct { reply },
+            // The defender has no moves left, so the solver has won.
+            _ => {
+                self.status = PuzzleStatus::Solved;
+                Attempt::Solved
+            }
+        }
+    }
+
+    /// The move the solver's choice is measured against: the written
+    /// solution while play follows it, otherwise the best move by search.
+    fn reference_move(&self) -> Option<Move> {
+        if self.on_solution {
+            self.puzzle.solution.get(self.solver_moves_played * 2).copied()
+        } else {
+            ai::search(&self.engine, self.remaining_plies()).best_move
+        }
+    }
+
+    /// Plies left in the solution, counting the solver's next move, capped
+    /// at the deepest search the analysis allows.
+    fn remaining_plies(&self) -> u8 {
+        let remaining = self.puzzle.solution.len() - self.solver_moves_played * 2;
+        remaining.clamp(1, analysis::MAX_DEPTH as usize) as u8
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::analysis;
+    use super::super::board::{Coordinate, GamePiece, Move, PieceColor};
+    use super::super::game::GameEngine;
+    use super::{Attempt, Puzzle, PuzzleSession, PuzzleStatus};
+
+    fn king(color: PieceColor) -> GamePiece {
+        GamePiece::crown(GamePiece::new(color))
+    }
+
+    /// Black's king takes one man, White's last man has a single move, and
+    /// the king takes it too.
+    fn two_step_puzzle() -> Puzzle {
+        let start = GameEngine::from_position(
+            &[
+                (Coordinate(4, 5), king(PieceColor::Black)),
+                (Coordinate(3, 4), GamePiece::new(PieceColor::White)),
+                (Coordinate(0, 1), GamePiece::new(PieceColor::White)),
+            ],
+            PieceColor::Black,
+        );
+
+        Puzzle::new(
+            start,
+            vec![
+                Move::new((4, 5), (2, 3)),
+                Move::new((0, 1), (1, 2)),
+                Move::new((2, 3), (0, 1)),
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn solves_along_the_line() {
+        let mut session = PuzzleSession::new(two_step_puzzle());
+
+        assert_eq!(session.hint(), Some(Move::new((4, 5), (2, 3))));
+        assert_eq!(
+            session.attempt(&Move::new((4, 5), (2, 3))),
+            Attempt::Correct {
+                reply: Move::new((0, 1), (1, 2))
+            }
+        );
+        assert_eq!(session.attempt(&Move::new((2, 3), (0, 1))), Attempt::Solved);
+        assert_eq!(session.status(), PuzzleStatus::Solved);
+        assert_eq!(session.engine().winner(), Some(PieceColor::Black));
+    }
+
+    #[test]
+    fn rejects_wrong_and_illegal_moves() {
+        let mut session = PuzzleSession::new(two_step_puzzle());
+
+        assert_eq!(session.attempt(&Move::new((4, 5), (4, 3))), Attempt::Illegal);
+        assert_eq!(session.status(), PuzzleStatus::InProgress);
+
+        assert_eq!(session.attempt(&Move::new((4, 5), (5, 6))), Attempt::Wrong);
+        assert_eq!(session.status(), PuzzleStatus::Failed);
+        assert_eq!(session.attempt(&Move::new((4, 5), (2, 3))), Attempt::Illegal);
+
+        session.reset();
+        assert_eq!(session.status(), PuzzleStatus::InProgress);
+        assert_eq!(session.engine().move_count(), 0);
+    }
+
+    #[test]
+    fn accepts_equally_winning_alternative() {
+        let start = GameEngine::from_position(
+            &[
+                (Coordinate(4, 5), king(PieceColor::Black)),
+                (Coordinate(2, 5), king(PieceColor::Black)),
+                (Coordinate(3, 4), GamePiece::new(PieceColor::White)),
+            ],
+            PieceColor::Black,
+        );
+        let puzzle = Puzzle::new(start, vec![Move::new((4, 5), (2, 3))]).unwrap();
+        let mut session = PuzzleSession::new(puzzle);
+
+        assert_eq!(session.attempt(&Move::new((2, 5), (4, 3))), Attempt::Solved);
+    }
+
+    #[test]
+    fn caps_search_depth_on_long_lines() {
+        let start = GameEngine::from_position(
+            &[
+                (Coordinate(1, 4), king(PieceColor::Black)),
+                (Coordinate(6, 1), king(PieceColor::White)),
+            ],
+            PieceColor::Black,
+        );
+        // The two kings shuffle back and forth for 41 plies.
+        let shuffle = [
+            Move::new((1, 4), (0, 5)),
+            Move::new((6, 1), (7, 2)),
+            Move::new((0, 5), (1, 4)),
+            Move::new((7, 2), (6, 1)),
+        ];
+        let solution = shuffle.iter().copied().cycle().take(41).collect();
+        let mut session = PuzzleSession::new(Puzzle::new(start, solution).unwrap());
+
+        assert_eq!(session.remaining_plies(), analysis::MAX_DEPTH);
+
+        // Leaving the line makes the defender's replies come from search.
+        assert!(matches!(
+            session.attempt(&Move::new((1, 4), (2, 5))),
+            Attempt::Correct { .. }
+        ));
+        assert!(session.hint().is_some());
+    }
+
+    #[test]
+    fn parses_and_writes_text() {
+        let puzzle = two_step_puzzle();
+        let text = puzzle.to_text();
+        let parsed = Puzzle::parse(&text).unwrap();
+
+        assert_eq!(parsed.solution, puzzle.solution);
+        assert_eq!(parsed.to_text(), text);
+        assert!(Puzzle::parse("Bbbbbbbbbbbbbeeeeeeeewwwwwwwwwwww 11-15 22-18").is_err());
+        assert!(Puzzle::parse("nonsense 11-15").is_err());
+    }
+}
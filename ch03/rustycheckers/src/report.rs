@@ -0,0 +1,333 @@
+//! Statistics about a finished game, worked out by replaying its moves.
+
+use super::ai::{self, Evaluator, Weights};
+use super::analysis::BLUNDER_THRESHOLD;
+use super::board::{Move, PieceColor};
+use super::game::GameEngine;
+use super::protocol::format_move;
+
+/// What one side did over a game.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SideStats {
+    pub captures: u32,
+    /// The ply, counting from zero, of each move that made a king.
+    pub kings: Vec<usize>,
+    /// Total thinking time, if every one of the side's moves was timed.
+    pub time_ms: Option<u64>,
+}
+
+/// One move of the game.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PlyStats {
+    pub movement: Move,
+    pub color: PieceColor,
+    pub capture: bool,
+    pub crowned: bool,
+    /// Time spent choosing the move, if it was timed.
+    pub time_ms: Option<u32>,
+}
+
+/// A move after which the evaluation changed sharply.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Swing {
+    /// The ply of the move, counting from zero.
+    pub ply: usize,
+    /// Evaluations before and after the move, from Black's side.
+    pub before: i32,
+    pub after: i32,
+}
+
+/// A post-game report. Scores are from Black's side: positive favors Black.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GameReport {
+    pub black: SideStats,
+    pub white: SideStats,
+    /// The most captures made in a single turn. Under `Rules::Standard` a
+    /// multi-jump is several capturing plies in a row by the same side.
+    pub longest_jump_chain: u32,
+    pub plies: Vec<PlyStats>,
+    /// Material after each ply, starting with the position before the
+    /// first move.
+    pub material: Vec<i32>,
+    /// Evaluation after each ply, starting with the position before the
+    /// first move.
+    pub evaluation: Vec<i32>,
+    /// Moves that changed the evaluation by at least `BLUNDER_THRESHOLD`.
+    pub swings: Vec<Swing>,
+    /// The winner, if the game ended with a side unable to move.
+    pub winner: Option<PieceColor>,
+}
+
+impl GameReport {
+    pub fn side(&self, color: PieceColor) -> &SideStats {
+        match color {
+            PieceColor::Black => &self.black,
+            PieceColor::White => &self.white,
+        }
+    }
+
+    /// Writes the report as a JSON object.
+    pub fn to_json(&self) -> String {
+        let plies: Vec<String> = self
+            .plies
+            .iter()
+            .map(|ply| {
+                format!(
+                    "{{\"color\":{},\"move\":\"{}\",\"capture\":{},\"crowned\":{},\"time_ms\":{}}}",
+                    color_json(Some(ply.color)),
+                    format_move(&ply.movement),
+                    ply.capture,
+                    ply.crowned,
+                    optional_json(ply.time_ms)
+                )
+            })
+            .collect();
+        let swings: Vec<String> = self
+            .swings
+            .iter()
+            .map(|swing| {
+                format!(
+                    "{{\"ply\":{},\"before\":{},\"after\":{}}}",
+                    swing.ply, swing.before, swing.after
+                )
+            })
+            .collect();
+
+        format!(
+            "{{\"winner\":{},\"black\":{},\"white\":{},\"longest_jump_chain\":{},\
+             \"moves\":[{}],\"material\":{},\"evaluation\":{},\"swings\":[{}]}}",
+            color_json(self.winner),
+            side_json(&self.black),
+            side_json(&self.white),
+            self.longest_jump_chain,
+            plies.join(","),
+            list_json(&self.material),
+            list_json(&self.evaluation),
+            swings.join(",")
+        )
+    }
+}
+
+/// Replays a game and reports on it, evaluating each position with a
+/// search of `depth` plies. `times` gives the milliseconds spent on each
+/// move, and may be shorter than `moves` or empty if moves were not timed.
+///
+/// Stops at the first illegal move.
+pub fn report(start: &GameEngine, moves: &[Move], times: &[u32], depth: u8) -> GameReport {
+    report_with(start, moves, times, depth, &Weights::default())
+}
+
+/// Like `report`, evaluating positions with the given evaluator.
+pub fn report_with<E: Evaluator + ?Sized>(
+    start: &GameEngine,
+    moves: &[Move],
+    times: &[u32],
+    depth: u8,
+    evaluator: &E,
+) -> GameReport {
+    let mut engine = start.clone();
+    let mut report = GameReport {
+        black: SideStats::default(),
+        white: SideStats::default(),
+        longest_jump_chain: 0,
+        plies: Vec::new(),
+        material: vec![for_black(&engine, ai::evaluate(&engine))],
+        evaluation: vec![evaluate(&engine, depth, evaluator)],
+        swings: Vec::new(),
+        winner: None,
+    };
+    // Captures so far in the turn being played.
+    let mut chain = 0;
+
+    for (ply, movement) in moves.iter().enumerate() {
+        let color = engine.current_turn();
+        let capture = engine.is_capture(movement);
+        let crowned = match engine.move_piece(movement) {
+            Ok(result) => result.crowned,
+            Err(_) => break,
+        };
+
+        let side = match color {
+            PieceColor::Black => &mut report.black,
+            PieceColor::White => &mut report.white,
+        };
+        if capture {
+            side.captures += 1;
+            chain += 1;
+            report.longest_jump_chain = report.longest_jump_chain.max(chain);
+        }
+        if crowned {
+            side.kings.push(ply);
+        }
+        // A jump that keeps the turn is continued by the next ply.
+        if engine.current_turn() != color {
+            chain = 0;
+        }
+
+        report.plies.push(PlyStats {
+            movement: *movement,
+            color,
+            capture,
+            crowned,
+            time_ms: times.get(ply).copied(),
+        });
+        report
+            .material
+            .push(for_black(&engine, ai::evaluate(&engine)));
+
+        let before = report.evaluation[ply];
+        let after = evaluate(&engine, depth, evaluator);
+        report.evaluation.push(after);
+        if (after - before).abs() >= BLUNDER_THRESHOLD {
+            report.swings.push(Swing { ply, before, after });
+        }
+    }
+
+    for color in [PieceColor::Black, PieceColor::White] {
+        let mut times = report
+            .plies
+            .iter()
+            .filter(|ply| ply.color == color)
+            .map(|ply| ply.time_ms.map(u64::from));
+        let total = times.try_fold(0, |total, time| time.map(|time| total + time));
+
+        match color {
+            PieceColor::Black => report.black.time_ms = total,
+            PieceColor::White => report.white.time_ms = total,
+        }
+    }
+
+    report.winner = engine.winner();
+    report
+}
+
+/// Turns a score for the side to move into one from Black's side.
+fn for_black(engine: &GameEngine, score: i32) -> i32 {
+    match engine.current_turn() {
+        PieceColor::Black => score,
+        PieceColor::White => -score,
+    }
+}
+
+fn evaluate<E: Evaluator + ?Sized>(engine: &GameEngine, depth: u8, evaluator: &E) -> i32 {
+    let score = if engine.winner().is_some() {
+        -ai::WIN_SCORE
+    } else if depth == 0 {
+        evaluator.evaluate(engine)
+    } else {
+        ai::search_with(engine, depth, evaluator).score
+    };
+
+    for_black(engine, score)
+}
+
+fn color_json(color: Option<PieceColor>) -> &'static str {
+    match color {
+        Some(PieceColor::Black) => "\"black\"",
+        Some(PieceColor::White) => "\"white\"",
+        None => "null",
+    }
+}
+
+fn optional_json<T: ToString>(value: Option<T>) -> String {
+    value.map_or("null".to_owned(), |value| value.to_string())
+}
+
+fn list_json(values: &[i32]) -> String {
+    let values: Vec<String> = values.iter().map(i32::to_string).collect();
+    format!("[{}]", values.join(","))
+}
+
+fn side_json(side: &SideStats) -> String {
+    let kings: Vec<String> = side.kings.iter().map(usize::to_string).collect();
+
+    format!(
+        "{{\"captures\":{},\"kings\":[{}],\"time_ms\":{}}}",
+        side.captures,
+        kings.join(","),
+        optional_json(side.time_ms)
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::board::{Coordinate, GamePiece, Move, PieceColor};
+    use super::super::game::{GameEngine, Rules};
+    use super::report;
+
+    #[test]
+    fn counts_captures_and_material() {
+        let moves = [
+            Move::new((2, 5), (3, 4)),
+            Move::new((5, 2), (4, 3)),
+            Move::new((3, 4), (5, 2)),
+        ];
+        let report = report(&GameEngine::new(), &moves, &[1200, 800, 500], 0);
+
+        assert_eq!(report.black.captures, 1);
+        assert_eq!(report.white.captures, 0);
+        assert_eq!(report.longest_jump_chain, 1);
+        assert_eq!(report.material, vec![0, 0, 0, 100]);
+        assert_eq!(report.black.time_ms, Some(1700));
+        assert_eq!(report.white.time_ms, Some(800));
+        assert!(report.plies[2].capture);
+        assert_eq!(report.swings.len(), 0);
+        assert_eq!(report.winner, None);
+    }
+
+    #[test]
+    fn counts_multi_jumps_as_one_chain() {
+        let black = GamePiece::new(PieceColor::Black);
+        let white = GamePiece::new(PieceColor::White);
+        let start = GameEngine::from_position(
+            &[
+                (Coordinate(1, 6), black),
+                (Coordinate(6, 7), black),
+                (Coordinate(2, 5), white),
+                (Coordinate(4, 3), white),
+                (Coordinate(7, 0), white),
+            ],
+            PieceColor::Black,
+        )
+        .with_rules(Rules::Standard);
+        // Black jumps twice in one turn, then White moves.
+        let moves = [
+            Move::new((1, 6), (3, 4)),
+            Move::new((3, 4), (5, 2)),
+            Move::new((7, 0), (6, 1)),
+        ];
+        let report = report(&start, &moves, &[], 0);
+
+        assert_eq!(report.plies.len(), 3);
+        assert_eq!(report.black.captures, 2);
+        assert_eq!(report.longest_jump_chain, 2);
+    }
+
+    #[test]
+    fn records_kings_and_the_winner() {
+        let black = GamePiece::new(PieceColor::Black);
+        let white = GamePiece::new(PieceColor::White);
+        let start = GameEngine::from_position(
+            &[(Coordinate(2, 1), black), (Coordinate(3, 0), white)],
+            PieceColor::Black,
+        );
+        // Black crowns, White steps forward, and the king takes White's
+        // last man.
+        let moves = [
+            Move::new((2, 1), (1, 0)),
+            Move::new((3, 0), (2, 1)),
+            Move::new((1, 0), (3, 2)),
+        ];
+        let report = report(&start, &moves, &[], 2);
+
+        assert_eq!(report.black.kings, vec![0]);
+        assert_eq!(report.black.captures, 1);
+        assert_eq!(report.black.time_ms, None);
+        assert_eq!(report.winner, Some(PieceColor::Black));
+        assert_eq!(report.material.last(), Some(&160));
+
+        let json = report.to_json();
+        assert!(json.starts_with("{\"winner\":\"black\",\"black\":{\"captures\":1,\"kings\":[0]"));
+        assert!(json.contains("{\"color\":\"black\",\"move\":\"27-32\",\"capture\":false,\"crowned\":true,\"time_ms\":null}"));
+    }
+}
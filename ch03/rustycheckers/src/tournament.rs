@@ -0,0 +1,536 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+
+use super::ai::{self, Weights};
+use super::board::{Move, PieceColor};
+use super::game::GameEngine;
+use super::mcts::{self, Rng};
+use super::network::Network;
+use super::pdn::{GameRecord, GameResult};
+
+/// Z-score for 95% confidence intervals.
+const CONFIDENCE_Z: f64 = 1.96;
+
+/// How an engine configuration picks its moves.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Strategy {
+    AlphaBeta { depth: u8, weights: Weights },
+    Mcts { iterations: u32 },
+    /// Alpha-beta with the network evaluation instead of material.
+    Network { depth: u8, network: Network },
+}
+
+impl Strategy {
+    /// Parses a comma-separated spec such as `depth=4,king=200`,
+    /// `mcts=2000` or `depth=4,network=FILE`, where `FILE` may be `embedded`
+    /// for the built-in network. Alpha-beta settings not given keep their
+    /// defaults. Specs mixing `mcts` with alpha-beta settings, or `network`
+    /// with material weights, are refused rather than half ignored.
+    pub fn parse(spec: &str) -> Result<Strategy, String> {
+        let mut depth = None;
+        let mut weights = Weights::default();
+        let mut weighted = false;
+        let mut iterations = None;
+        let mut network = None;
+
+        for setting in spec.split(',').filter(|setting| !setting.is_empty()) {
+            let (key, value) = setting
+                .split_once('=')
+                .ok_or_else(|| format!("expected key=value, got {}", setting))?;
+
+            let weight = match key {
+                "mcts" => {
+                    iterations = Some(parse_setting(key, value)?);
+                    continue;
+                }
+                "network" => {
+                    network = Some(load_network(value)?);
+                    continue;
+                }
+                "depth" => {
+                    depth = Some(parse_setting(key, value)?);
+                    continue;
+                }
+                "man" => &mut weights.man,
+                "king" => &mut weights.king,
+                "advancement" => &mut weights.advancement,
+                "back_row" => &mut weights.back_row,
+                _ => return Err(format!("unknown setting {}", key)),
+            };
+            *weight = parse_setting(key, value)?;
+            weighted = true;
+        }
+
+        if let Some(iterations) = iterations {
+            if depth.is_some() || weighted || network.is_some() {
+                return Err("mcts can't be combined with alpha-beta settings".to_owned());
+            }
+            return Ok(Strategy::Mcts { iterations });
+        }
+
+        let depth = depth.unwrap_or(4);
+        match network {
+            Some(_) if weighted => {
+                Err("network can't be combined with material weights".to_owned())
+            }
+            Some(network) => Ok(Strategy::Network { depth, network }),
+            None => Ok(Strategy::AlphaBeta { depth, weights }),
+        }
+    }
+
+    /// Chooses a move for the side to move, if it has any.
+    pub fn choose(&self, engine: &GameEngine, rng: &mut Rng) -> Option<Move> {
+        match self {
+            Strategy::AlphaBeta { depth, weights } => {
+                ai::search_with(engine, *depth, weights).best_move
+            }
+            Strategy::Mcts { iterations } => mcts::search(engine, *iterations, rng),
+            Strategy::Network { depth, network } => {
+                ai::search_with(engine, *depth, network).best_move
+            }
+        }
+    }
+}
+
+fn parse_setting<T: std::str::FromStr>(key: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value {} for {}", value, key))
+}
+
+fn load_network(path: &str) -> Result<Network, String> {
+    if path == "embedded" {
+        return Ok(Network::embedded());
+    }
+
+    File::open(path)
+        .and_then(|file| Network::load(&mut BufReader::new(file)))
+        .map_err(|error| format!("could not read network {}: {}", path, error))
+}
+
+/// A named engine configuration taking part in a tournament.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Contestant {
+    pub name: String,
+    pub strategy: Strategy,
+}
+
+/// Rules for playing and adjudicating games.
+#[derive(Clone, Debug)]
+pub struct Settings {
+    /// Games still going after this many plies are drawn.
+    pub max_plies: u32,
+    /// A material lead (in evaluation units) that counts as decisive...
+    pub adjudication_margin: i32,
+    /// ...once it has been held for this many consecutive plies.
+    pub adjudication_plies: u32,
+    /// Use at most this many openings from `openings()`.
+    pub openings: usize,
+    pub seed: u64,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            max_plies: 200,
+            adjudication_margin: 3 * ai::MAN_VALUE,
+            adjudication_plies: 10,
+            openings: usize::MAX,
+            seed: 1,
+        }
+    }
+}
+
+/// Why a game ended.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Termination {
+    NoMoves,
+    /// A side's strategy chose no move or an illegal one.
+    Forfeit,
+    Repetition,
+    MoveLimit,
+    Adjudicated,
+}
+
+/// A finished tournament game.
+#[derive(Clone, Debug)]
+pub struct GameSummary {
+    pub black: usize,
+    pub white: usize,
+    pub opening: usize,
+    pub record: GameRecord,
+    pub termination: Termination,
+}
+
+/// Returns the balanced opening set: every first move for Black paired with
+/// every reply for White, in a stable order.
+pub fn openings() -> Vec<Vec<Move>> {
+    let engine = GameEngine::new();
+    let mut openings = Vec::new();
+
+    for first in engine.valid_moves() {
+        let mut after_first = engine.clone();
+        if after_first.move_piece(&first).is_err() {
+            continue;
+        }
+
+        for reply in after_first.valid_moves() {
+            openings.push(vec![first, reply]);
+        }
+    }
+
+    openings
+}
+
+/// Plays a single game from an opening and adjudicates the result.
+pub fn play_game(
+    black: &Contestant,
+    white: &Contestant,
+    opening: &[Move],
+    settings: &Settings,
+    rng: &mut Rng,
+) -> (GameRecord, Termination) {
+    let mut engine = GameEngine::new();
+    let mut moves = Vec::new();
+    let mut seen: HashMap<u64, u32> = HashMap::new();
+    let mut decisive_plies = 0;
+
+    let (result, termination) = loop {
+        if let Some(winner) = engine.winner() {
+            break (result_for(winner), Termination::NoMoves);
+        }
+
+        let repetitions = seen.entry(engine.position_hash()).or_insert(0);
+        *repetitions += 1;
+        if *repetitions >= 3 {
+            break (GameResult::Draw, Termination::Repetition);
+        }
+
+        if engine.move_count() >= settings.max_plies {
+            break (GameResult::Draw, Termination::MoveLimit);
+        }
+
+        // Material from Black's point of view.
+        let material = match engine.current_turn() {
+            PieceColor::Black => ai::evaluate(&engine),
+            PieceColor::White => -ai::evaluate(&engine),
+        };
+
+        if material.abs() >= settings.adjudication_margin {
+            decisive_plies += 1;
+        } else {
+            decisive_plies = 0;
+        }
+
+        if decisive_plies >= settings.adjudication_plies {
+            let leader = if material > 0 {
+                PieceColor::Black
+            } else {
+                PieceColor::White
+            };
+            break (result_for(leader), Termination::Adjudicated);
+        }
+
+        let movement = match opening.get(moves.len()) {
+            Some(movement) => Some(*movement),
+            None => match engine.current_turn() {
+                PieceColor::Black => black.strategy.choose(&engine, rng),
+                PieceColor::White => white.strategy.choose(&engine, rng),
+            },
+        };
+
+        match movement.map(|movement| (movement, engine.move_piece(&movement))) {
+            Some((movement, Ok(_))) => moves.push(movement),
+            // An unplayable move forfeits the game.
+            _ => {
+                break (
+                    result_for(engine.current_turn().opponent()),
+                    Termination::Forfeit,
+                )
+            }
+        }
+    };
+
+    let mut record = GameRecord::new(moves, result);
+    record.set_tag("Black", &black.name);
+    record.set_tag("White", &white.name);
+    record.set_tag("Termination", &format!("{:?}", termination));
+
+    (record, termination)
+}
+
+fn result_for(winner: PieceColor) -> GameResult {
+    match winner {
+        PieceColor::Black => GameResult::BlackWins,
+        PieceColor::White => GameResult::WhiteWins,
+    }
+}
+
+/// Plays a round robin: every pair of contestants plays every opening twice,
+/// once with each color. `on_game` is called as each game finishes.
+pub fn round_robin<F: FnMut(&GameSummary)>(
+    contestants: &[Contestant],
+    settings: &Settings,
+    mut on_game: F,
+) -> Vec<GameSummary> {
+    let openings = openings();
+    let openings = &openings[..settings.openings.min(openings.len())];
+    let mut rng = Rng::new(settings.seed);
+    let mut games = Vec::new();
+
+    for first in 0..contestants.len() {
+        for second in (first + 1)..contestants.len() {
+            for (opening_index, opening) in openings.iter().enumerate() {
+                for (black, white) in [(first, second), (second, first)] {
+                    let (mut record, termination) = play_game(
+                        &contestants[black],
+                        &contestants[white],
+                        opening,
+                        settings,
+                        &mut rng,
+                    );
+
+                    record.set_tag("Event", "rustycheckers round robin");
+                    record.set_tag("Round", &(games.len() + 1).to_string());
+
+                    let summary = GameSummary {
+                        black,
+                        white,
+                        opening: opening_index,
+                        record,
+                        termination,
+                    };
+
+                    on_game(&summary);
+                    games.push(summary);
+                }
+            }
+        }
+    }
+
+    games
+}
+
+/// A contestant's results and Elo estimate relative to the rest of the field.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Standing {
+    pub name: String,
+    pub games: u32,
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+    /// Points scored, counting a draw as half a win.
+    pub score: f64,
+    pub elo: f64,
+    /// Half-width of the 95% confidence interval for `elo`.
+    pub elo_error: f64,
+}
+
+/// Summarizes each contestant's results, best first.
+pub fn standings(contestants: &[Contestant], games: &[GameSummary]) -> Vec<Standing> {
+    let mut standings: Vec<Standing> = contestants
+        .iter()
+        .enumerate()
+        .map(|(index, contestant)| {
+            let scores: Vec<f64> = games
+                .iter()
+                .filter_map(|game| points_for(index, game))
+                .collect();
+
+            let count = |value: f64| scores.iter().filter(|score| **score == value).count() as u32;
+            let (elo, elo_error) = elo_estimate(&scores);
+
+            Standing {
+                name: contestant.name.clone(),
+                games: scores.len() as u32,
+                wins: count(1.0),
+                draws: count(0.5),
+                losses: count(0.0),
+                score: scores.iter().sum(),
+                elo,
+                elo_error,
+            }
+        })
+        .collect();
+
+    standings.sort_by(|a, b| b.elo.total_cmp(&a.elo));
+    standings
+}
+
+/// Returns the points a contestant earned in a game, if they played in it.
+fn points_for(contestant: usize, game: &GameSummary) -> Option<f64> {
+    let color = if game.black == contestant {
+        PieceColor::Black
+    } else if game.white == contestant {
+        PieceColor::White
+    } else {
+        return None;
+    };
+
+    Some(match (game.record.result, color) {
+        (GameResult::BlackWins, PieceColor::Black) | (GameResult::WhiteWins, PieceColor::White) => {
+            1.0
+        }
+        (GameResult::BlackWins, _) | (GameResult::WhiteWins, _) => 0.0,
+        _ => 0.5,
+    })
+}
+
+/// Converts an expected score (0 to 1) to an Elo rating difference.
+pub fn elo_difference(score: f64) -> f64 {
+    let score = score.clamp(0.001, 0.999);
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+/// Estimates an Elo difference and its 95% error margin from per-game
+/// scores, using the standard error of the mean score.
+pub fn elo_estimate(scores: &[f64]) -> (f64, f64) {
+    if scores.is_empty() {
+        return (0.0, 0.0);
+    }
+
+    let count = scores.len() as f64;
+    let mean = scores.iter().sum::<f64>() / count;
+    let variance = scores
+        .iter()
+        .map(|score| (score - mean).powi(2))
+        .sum::<f64>()
+        / count;
+    let margin = CONFIDENCE_Z * (variance / count).sqrt();
+
+    let elo = elo_difference(mean);
+    let low = elo_difference(mean - margin);
+    let high = elo_difference(mean + margin);
+
+    (elo, (high - low) / 2.0)
+}
+
+/// Writes every game as a PDN archive.
+pub fn to_pdn(games: &[GameSummary]) -> String {
+    games.iter().map(|game| game.record.to_pdn()).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::ai::Weights;
+    use super::super::board::Move;
+    use super::super::mcts::Rng;
+    use super::super::pdn::GameResult;
+    use super::{
+        elo_difference, elo_estimate, openings, play_game, round_robin, standings, Contestant,
+        Settings, Strategy, Termination,
+    };
+
+    #[test]
+    fn parses_strategies() {
+        assert_eq!(
+            Strategy::parse("depth=3,king=200").unwrap(),
+            Strategy::AlphaBeta {
+                depth: 3,
+                weights: Weights {
+                    king: 200,
+                    ..Weights::default()
+                }
+            }
+        );
+        assert_eq!(
+            Strategy::parse("mcts=500").unwrap(),
+            Strategy::Mcts { iterations: 500 }
+        );
+        assert!(Strategy::parse("depth").is_err());
+        assert!(Strategy::parse("depth=300").is_err());
+        assert!(Strategy::parse("depth=-1").is_err());
+        assert!(Strategy::parse("mcts=5000000000").is_err());
+        assert!(Strategy::parse("king=x").is_err());
+        assert!(Strategy::parse("speed=9").is_err());
+        assert!(matches!(
+            Strategy::parse("network=embedded,depth=2").unwrap(),
+            Strategy::Network { depth: 2, .. }
+        ));
+        assert!(Strategy::parse("network=no/such/file").is_err());
+    }
+
+    #[test]
+    fn refuses_mixed_strategies() {
+        assert!(Strategy::parse("depth=3,mcts=200").is_err());
+        assert!(Strategy::parse("mcts=200,king=150").is_err());
+        assert!(Strategy::parse("mcts=200,network=embedded").is_err());
+        assert!(Strategy::parse("network=embedded,man=120").is_err());
+        assert!(Strategy::parse("back_row=5,network=embedded").is_err());
+    }
+
+    #[test]
+    fn opening_set_covers_first_two_plies() {
+        let openings = openings();
+
+        assert_eq!(openings.len(), 49);
+        assert!(openings.iter().all(|opening| opening.len() == 2));
+    }
+
+    #[test]
+    fn illegal_moves_forfeit() {
+        let contestant = Contestant {
+            name: "depth 1".to_string(),
+            strategy: Strategy::parse("depth=1").unwrap(),
+        };
+        let opening = [Move::new((0, 5), (0, 4))];
+
+        let (record, termination) = play_game(
+            &contestant,
+            &contestant,
+            &opening,
+            &Settings::default(),
+            &mut Rng::new(1),
+        );
+
+        assert_eq!(termination, Termination::Forfeit);
+        assert_eq!(record.result, GameResult::WhiteWins);
+    }
+
+    #[test]
+    fn elo_math() {
+        assert!(elo_difference(0.5).abs() < 1e-9);
+        assert!((elo_difference(0.75) - 190.85).abs() < 0.01);
+        assert!(elo_difference(1.0) > 1000.0);
+
+        let (elo, error) = elo_estimate(&[1.0, 0.0, 0.5, 0.5]);
+        assert!(elo.abs() < 1e-9);
+        assert!(error > 0.0);
+    }
+
+    #[test]
+    fn plays_color_swapped_pairs() {
+        let contestants = vec![
+            Contestant {
+                name: "shallow".to_owned(),
+                strategy: Strategy::parse("depth=1").unwrap(),
+            },
+            Contestant {
+                name: "deeper".to_owned(),
+                strategy: Strategy::parse("depth=3").unwrap(),
+            },
+        ];
+        let settings = Settings {
+            openings: 2,
+            max_plies: 80,
+            ..Settings::default()
+        };
+
+        let mut finished = 0;
+        let games = round_robin(&contestants, &settings, |_game| finished += 1);
+
+        assert_eq!(games.len(), 4);
+        assert_eq!(finished, 4);
+        assert_eq!((games[0].black, games[0].white), (0, 1));
+        assert_eq!((games[1].black, games[1].white), (1, 0));
+        assert!(games
+            .iter()
+            .all(|game| game.record.result != GameResult::Unfinished));
+
+        let table = standings(&contestants, &games);
+        assert_eq!(table.len(), 2);
+        assert_eq!(table[0].games, 4);
+        assert!((table[0].score + table[1].score - 4.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,102 @@
+//! Zobrist keys for identifying positions.
+//!
+//! A position's key is the XOR of one random number per piece on the board,
+//! plus `WHITE_TO_MOVE` when it is White's turn. Because XOR undoes itself,
+//! `GameEngine` keeps the key up to date one piece at a time as the board
+//! changes. The numbers are generated at compile time from a fixed seed, so
+//! keys are stable across runs, builds and platforms.
+
+use super::board::{Coordinate, GamePiece, PieceColor};
+
+/// Black man, black king, white man, white king.
+const PIECE_KINDS: usize = 4;
+const SQUARES: usize = 64;
+const SEED: u64 = 0x5EED_C4EC_4E25_0001;
+
+/// Advances a splitmix64 generator, returning the new state and its output.
+const fn splitmix(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (state, z ^ (z >> 31))
+}
+
+const fn piece_table() -> [[u64; PIECE_KINDS]; SQUARES] {
+    let mut table = [[0; PIECE_KINDS]; SQUARES];
+    let mut state = SEED;
+    let mut square = 0;
+
+    while square < SQUARES {
+        let mut kind = 0;
+        while kind < PIECE_KINDS {
+            let (next, key) = splitmix(state);
+            state = next;
+            table[square][kind] = key;
+            kind += 1;
+        }
+        square += 1;
+    }
+
+    table
+}
+
+const PIECE_KEYS: [[u64; PIECE_KINDS]; SQUARES] = piece_table();
+
+/// Mixed into the key when White is to move.
+pub const WHITE_TO_MOVE: u64 = splitmix(!SEED).1;
+
+/// The key for a piece standing on a square.
+pub fn piece_key(location: Coordinate, piece: GamePiece) -> u64 {
+    let Coordinate(x, y) = location;
+    let kind = match (piece.color, piece.crowned) {
+        (PieceColor::Black, false) => 0,
+        (PieceColor::Black, true) => 1,
+        (PieceColor::White, false) => 2,
+        (PieceColor::White, true) => 3,
+    };
+
+    PIECE_KEYS[(y * 8 + x) % SQUARES][kind]
+}
+
+/// The key for the side to move.
+pub fn turn_key(turn: PieceColor) -> u64 {
+    match turn {
+        PieceColor::Black => 0,
+        PieceColor::White => WHITE_TO_MOVE,
+    }
+}
+
+/// Computes a key from scratch.
+pub fn position_key(pieces: &[(Coordinate, GamePiece)], turn: PieceColor) -> u64 {
+    pieces
+        .iter()
+        .fold(turn_key(turn), |key, (location, piece)| {
+            key ^ piece_key(*location, *piece)
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::board::{Coordinate, GamePiece, PieceColor};
+    use super::super::game::GameEngine;
+    use super::{piece_key, PIECE_KEYS, WHITE_TO_MOVE};
+    use std::collections::HashSet;
+
+    #[test]
+    fn keys_are_distinct() {
+        let mut keys: HashSet<u64> = PIECE_KEYS.iter().flatten().copied().collect();
+        keys.insert(WHITE_TO_MOVE);
+
+        assert_eq!(keys.len(), 64 * 4 + 1);
+        assert!(!keys.contains(&0));
+    }
+
+    #[test]
+    fn keys_are_stable() {
+        // Saved hashes and opening books depend on these never changing.
+        let black = GamePiece::new(PieceColor::Black);
+        assert_eq!(piece_key(Coordinate(0, 7), black), 0xEC33_391A_372E_324A);
+        assert_eq!(GameEngine::new().position_hash(), 0x58A4_DA32_F2AE_2DCA);
+    }
+}
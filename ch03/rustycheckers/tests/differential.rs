@@ -0,0 +1,414 @@
+//! Differential tests against the hand-written WebAssembly checkers engine
+//! in `ch02/checkers.wat`.
+//!
+//! Random legal games are played on `GameEngine` and, move for move, on the
+//! `.wat` module running in an embedded interpreter. After every move the
+//! two must agree on every square, on crowning and on whose turn it is. A
+//! disagreement is shrunk to a short move list before it is reported.
+//!
+//! The `.wat` engine has two known differences, each pinned by its own test
+//! below so a fix to the module shows up as a failure here:
+//!
+//! - `initBoard` places White's second row twice and never its third, so
+//!   the games start from `wat_start` instead of the standard position.
+//! - Jumps do not remove the jumped piece, so the random games only play
+//!   quiet moves.
+
+use rustycheckers::board::{Coordinate, GamePiece, Move, PieceColor};
+use rustycheckers::game::GameEngine;
+use rustycheckers::mcts::Rng;
+use rustycheckers::protocol::format_move;
+use wasmi::{Caller, Engine, Linker, Module, Store, TypedFunc};
+
+const WAT_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../ch02/checkers.wat");
+
+const GAMES: u64 = 100;
+const MAX_PLIES: usize = 150;
+
+/// A notification sent by the `.wat` module to its host.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Event {
+    PieceMoved(i32, i32, i32, i32),
+    PieceCrowned(i32, i32),
+}
+
+/// A running instance of `checkers.wat`.
+struct WatCheckers {
+    store: Store<Vec<Event>>,
+    get_piece: TypedFunc<(i32, i32), i32>,
+    is_crowned: TypedFunc<i32, i32>,
+    get_turn_owner: TypedFunc<(), i32>,
+    move_piece: TypedFunc<(i32, i32, i32, i32), i32>,
+}
+
+impl WatCheckers {
+    fn load() -> (Engine, Module) {
+        let wasm = wat::parse_file(WAT_PATH).expect("checkers.wat should assemble");
+        let engine = Engine::default();
+        let module = Module::new(&engine, &wasm[..]).expect("checkers.wat should validate");
+
+        (engine, module)
+    }
+
+    /// Instantiates the module and calls its `initBoard`.
+    fn new(engine: &Engine, module: &Module) -> WatCheckers {
+        let mut store = Store::new(engine, Vec::new());
+        let mut linker = <Linker<Vec<Event>>>::new(engine);
+
+        linker
+            .func_wrap(
+                "events",
+                "piece_moved",
+                |mut caller: Caller<'_, Vec<Event>>, from_x, from_y, to_x, to_y| {
+                    caller
+                        .data_mut()
+                        .push(Event::PieceMoved(from_x, from_y, to_x, to_y));
+                },
+            )
+            .unwrap();
+        linker
+            .func_wrap(
+                "events",
+                "piece_crowned",
+                |mut caller: Caller<'_, Vec<Event>>, x, y| {
+                    caller.data_mut().push(Event::PieceCrowned(x, y));
+                },
+            )
+            .unwrap();
+
+        let instance = linker
+            .instantiate(&mut store, module)
+            .and_then(|instance| instance.start(&mut store))
+            .expect("checkers.wat should instantiate");
+
+        let init_board = instance
+            .get_typed_func::<(), ()>(&store, "initBoard")
+            .unwrap();
+        init_board.call(&mut store, ()).unwrap();
+
+        WatCheckers {
+            get_piece: instance.get_typed_func(&store, "getPiece").unwrap(),
+            is_crowned: instance.get_typed_func(&store, "isCrowned").unwrap(),
+            get_turn_owner: instance.get_typed_func(&store, "getTurnOwner").unwrap(),
+            move_piece: instance.get_typed_func(&store, "move").unwrap(),
+            store,
+        }
+    }
+
+    fn piece(&mut self, x: usize, y: usize) -> i32 {
+        self.get_piece
+            .call(&mut self.store, (x as i32, y as i32))
+            .unwrap()
+    }
+
+    fn crowned(&mut self, piece: i32) -> bool {
+        self.is_crowned.call(&mut self.store, piece).unwrap() == 1
+    }
+
+    fn turn_owner(&mut self) -> i32 {
+        self.get_turn_owner.call(&mut self.store, ()).unwrap()
+    }
+
+    /// Plays a move, returning the module's result code and the
+    /// notifications it sent.
+    fn play(&mut self, movement: &Move) -> (i32, Vec<Event>) {
+        let Move {
+            from: Coordinate(from_x, from_y),
+            to: Coordinate(to_x, to_y),
+        } = *movement;
+
+        self.store.data_mut().clear();
+        let result = self
+            .move_piece
+            .call(
+                &mut self.store,
+                (from_x as i32, from_y as i32, to_x as i32, to_y as i32),
+            )
+            .unwrap_or(-1);
+
+        (result, self.store.data().clone())
+    }
+}
+
+/// Where and how the two engines first disagreed.
+#[derive(Debug)]
+struct Divergence {
+    /// Number of moves played before the disagreement was seen.
+    step: usize,
+    detail: String,
+}
+
+/// Encodes a square the way both engines do: 0 for empty, otherwise the
+/// color flag plus 4 for a crowned piece.
+fn rust_square(engine: &GameEngine, x: usize, y: usize) -> i32 {
+    match engine.get_piece(Coordinate(x, y)) {
+        Ok(Some(piece)) => piece.into(),
+        _ => 0,
+    }
+}
+
+fn compare(engine: &GameEngine, wat: &mut WatCheckers) -> Result<(), String> {
+    for y in 0..8 {
+        for x in 0..8 {
+            let expected = rust_square(engine, x, y);
+            let actual = wat.piece(x, y);
+
+            if expected != actual {
+                return Err(format!(
+                    "square ({}, {}) holds {} in Rust but {} in wat",
+                    x, y, expected, actual
+                ));
+            }
+
+            if actual != 0 && engine.is_crowned(Coordinate(x, y)) != wat.crowned(actual) {
+                return Err(format!("crowning differs at ({}, {})", x, y));
+            }
+        }
+    }
+
+    let expected: i32 = GamePiece::new(engine.current_turn()).into();
+    let actual = wat.turn_owner();
+    if expected != actual {
+        return Err(format!(
+            "turn owner is {} in Rust but {} in wat",
+            expected, actual
+        ));
+    }
+
+    Ok(())
+}
+
+/// The position `initBoard` sets up: the standard start without White's
+/// third row.
+fn wat_start() -> GameEngine {
+    let start = GameEngine::new();
+    let pieces: Vec<(Coordinate, GamePiece)> = (0..8)
+        .flat_map(|y| (0..8).map(move |x| Coordinate(x, y)))
+        .filter(|square| square.1 != 2)
+        .filter_map(|square| match start.get_piece(square) {
+            Ok(Some(piece)) => Some((square, piece)),
+            _ => None,
+        })
+        .collect();
+
+    GameEngine::from_position(&pieces, start.current_turn())
+}
+
+/// Plays `moves` on fresh instances of both engines, comparing them before
+/// the first move and after each one. The moves must be legal in Rust.
+fn replay(engine: &Engine, module: &Module, moves: &[Move]) -> Result<(), Divergence> {
+    let mut rust = wat_start();
+    let mut wat = WatCheckers::new(engine, module);
+
+    compare(&rust, &mut wat).map_err(|detail| Divergence { step: 0, detail })?;
+
+    for (index, movement) in moves.iter().enumerate() {
+        let step = index + 1;
+        let crowned = rust
+            .move_piece(movement)
+            .expect("replayed moves should be legal")
+            .crowned;
+        let (result, events) = wat.play(movement);
+
+        if result != 1 {
+            return Err(Divergence {
+                step,
+                detail: format!("wat rejected {} with {}", format_move(movement), result),
+            });
+        }
+
+        let crown_events = events
+            .iter()
+            .filter(|event| matches!(event, Event::PieceCrowned(..)))
+            .count();
+        if crowned != (crown_events == 1) {
+            return Err(Divergence {
+                step,
+                detail: format!("crowning notifications differ: {:?}", events),
+            });
+        }
+
+        compare(&rust, &mut wat).map_err(|detail| Divergence { step, detail })?;
+    }
+
+    Ok(())
+}
+
+/// Picks uniformly random quiet moves until none are left or the game runs
+/// long.
+fn random_game(rng: &mut Rng) -> Vec<Move> {
+    let mut engine = wat_start();
+    let mut moves = Vec::new();
+
+    while moves.len() < MAX_PLIES {
+        let mut legal = engine.valid_moves();
+        legal.retain(|movement| !engine.is_capture(movement));
+        if legal.is_empty() {
+            break;
+        }
+
+        let movement = legal[rng.below(legal.len())];
+        engine.move_piece(&movement).unwrap();
+        moves.push(movement);
+    }
+
+    moves
+}
+
+fn is_legal(moves: &[Move]) -> bool {
+    let mut engine = wat_start();
+    moves
+        .iter()
+        .all(|movement| engine.move_piece(movement).is_ok())
+}
+
+/// Shrinks a failing move list: cuts it after the first failure, then keeps
+/// dropping pairs of moves (one per side) and single moves while the rest
+/// stays legal and still fails.
+fn minimize<F: Fn(&[Move]) -> Option<usize>>(moves: &[Move], failing_step: F) -> Vec<Move> {
+    let mut moves = match failing_step(moves) {
+        Some(step) => moves[..step].to_vec(),
+        None => return moves.to_vec(),
+    };
+
+    let mut shrunk = true;
+    while shrunk {
+        shrunk = false;
+
+        let cuts = (0..moves.len().saturating_sub(1))
+            .map(|index| index..index + 2)
+            .chain((0..moves.len()).map(|index| index..index + 1));
+
+        for cut in cuts {
+            let mut candidate = moves.clone();
+            candidate.drain(cut);
+
+            if !is_legal(&candidate) {
+                continue;
+            }
+
+            if let Some(step) = failing_step(&candidate) {
+                candidate.truncate(step);
+                moves = candidate;
+                shrunk = true;
+                break;
+            }
+        }
+    }
+
+    moves
+}
+
+fn describe(moves: &[Move]) -> String {
+    if moves.is_empty() {
+        return "(start position)".to_owned();
+    }
+
+    moves
+        .iter()
+        .map(format_move)
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+#[test]
+fn engines_agree_on_random_games() {
+    let (engine, module) = WatCheckers::load();
+
+    for seed in 1..=GAMES {
+        let moves = random_game(&mut Rng::new(seed));
+
+        if let Err(divergence) = replay(&engine, &module, &moves) {
+            let failing_step =
+                |moves: &[Move]| replay(&engine, &module, moves).err().map(|d| d.step);
+            let minimized = minimize(&moves, failing_step);
+            let reason = replay(&engine, &module, &minimized).unwrap_err();
+
+            panic!(
+                "seed {} diverged after {} moves ({})\nminimized to {} moves: {}\n{}",
+                seed,
+                divergence.step,
+                divergence.detail,
+                minimized.len(),
+                describe(&minimized),
+                reason.detail
+            );
+        }
+    }
+}
+
+#[test]
+fn wat_leaves_whites_third_row_empty() {
+    let (engine, module) = WatCheckers::load();
+    let mut wat = WatCheckers::new(&engine, &module);
+    let standard = GameEngine::new();
+
+    assert!((0..8).any(|x| rust_square(&standard, x, 2) != 0));
+    assert!((0..8).all(|x| wat.piece(x, 2) == 0));
+    assert_eq!(compare(&wat_start(), &mut wat), Ok(()));
+}
+
+#[test]
+fn wat_keeps_jumped_pieces() {
+    let (engine, module) = WatCheckers::load();
+    let mut wat = WatCheckers::new(&engine, &module);
+    let mut rust = wat_start();
+    let mut rng = Rng::new(1);
+
+    // Play quiet moves until a capture comes up, then take it.
+    let capture = loop {
+        let legal = rust.valid_moves();
+        if let Some(capture) = legal.iter().find(|movement| rust.is_capture(movement)) {
+            break *capture;
+        }
+
+        let movement = legal[rng.below(legal.len())];
+        rust.move_piece(&movement).unwrap();
+        assert_eq!(wat.play(&movement).0, 1);
+    };
+
+    let Coordinate(x, y) = capture.jumped().unwrap();
+    rust.move_piece(&capture).unwrap();
+    assert_eq!(wat.play(&capture).0, 1);
+
+    assert_eq!(rust_square(&rust, x, y), 0);
+    assert_ne!(wat.piece(x, y), 0);
+}
+
+#[test]
+fn minimizes_to_a_short_legal_sequence() {
+    // Pretend the engines disagree once a Black piece crosses the middle of
+    // the board.
+    let failing_step = |moves: &[Move]| {
+        let mut engine = wat_start();
+        moves
+            .iter()
+            .position(|movement| {
+                let crossed = engine.current_turn() == PieceColor::Black && movement.to.1 < 4;
+                let _ = engine.move_piece(movement);
+                crossed
+            })
+            .map(|index| index + 1)
+    };
+
+    let mut rng = Rng::new(7);
+    let moves = loop {
+        let moves = random_game(&mut rng);
+        if failing_step(&moves).is_some_and(|step| step > 6) {
+            break moves;
+        }
+    };
+
+    let minimized = minimize(&moves, failing_step);
+
+    assert!(is_legal(&minimized));
+    assert_eq!(failing_step(&minimized), Some(minimized.len()));
+    assert!(minimized.len() < failing_step(&moves).unwrap());
+
+    // No single move can be dropped without losing the failure.
+    for index in 0..minimized.len() {
+        let mut candidate = minimized.clone();
+        candidate.remove(index);
+        assert!(!is_legal(&candidate) || failing_step(&candidate).is_none());
+    }
+}
@@ -0,0 +1,138 @@
+//! Property tests of `GameEngine` invariants over random move sequences.
+//!
+//! Each sequence mixes legal moves with arbitrary, mostly illegal ones
+//! (including coordinates off the board and moves from empty squares).
+//! After every step the engine must still agree with a from-scratch
+//! regeneration of its move lists, so these tests mostly exercise the
+//! incremental `update_valid_moves` bookkeeping.
+
+use proptest::prelude::*;
+use rustycheckers::board::{Move, PieceColor};
+use rustycheckers::game::GameEngine;
+use rustycheckers::protocol::position_string;
+
+/// One step of a generated game.
+#[derive(Clone, Debug)]
+enum Step {
+    /// Plays the legal move at this index, modulo the number of legal moves.
+    Legal(usize),
+    /// Tries an arbitrary move, which is usually illegal.
+    Arbitrary(Move),
+}
+
+fn step() -> impl Strategy<Value = Step> {
+    // Coordinates run past the board edge on purpose.
+    let coordinate = (0usize..10, 0usize..10);
+
+    prop_oneof![
+        4 => any::<usize>().prop_map(Step::Legal),
+        1 => (coordinate.clone(), coordinate)
+            .prop_map(|(from, to)| Step::Arbitrary(Move::new(from, to))),
+    ]
+}
+
+fn count(engine: &GameEngine, color: PieceColor) -> usize {
+    engine
+        .pieces()
+        .iter()
+        .filter(|(_location, piece)| piece.color == color)
+        .count()
+}
+
+/// The move list a new engine would generate for the same position.
+fn regenerated_moves(engine: &GameEngine) -> Vec<Move> {
+    GameEngine::from_position(&engine.pieces(), engine.current_turn()).valid_moves()
+}
+
+/// The Zobrist key computed from scratch for the same position.
+fn regenerated_hash(engine: &GameEngine) -> u64 {
+    GameEngine::from_position(&engine.pieces(), engine.current_turn()).position_hash()
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(256))]
+
+    #[test]
+    fn invariants_hold_over_random_games(steps in prop::collection::vec(step(), 0..200)) {
+        let mut engine = GameEngine::new();
+
+        for step in steps {
+            let movement = match step {
+                Step::Legal(index) => {
+                    let legal = engine.valid_moves();
+                    if legal.is_empty() {
+                        break;
+                    }
+                    legal[index % legal.len()]
+                }
+                Step::Arbitrary(movement) => movement,
+            };
+
+            let mover = engine.current_turn();
+            let opponent = mover.opponent();
+            let is_legal = engine.valid_moves().contains(&movement);
+            let is_capture = is_legal && engine.is_capture(&movement);
+            let before = (count(&engine, mover), count(&engine, opponent));
+            let position = position_string(&engine);
+            let move_count = engine.move_count();
+
+            let result = engine.move_piece(&movement);
+            let after = (count(&engine, mover), count(&engine, opponent));
+
+            prop_assert_eq!(result.is_ok(), is_legal);
+
+            if is_legal {
+                prop_assert_eq!(engine.current_turn(), opponent);
+                prop_assert_eq!(engine.move_count(), move_count + 1);
+            } else {
+                // A rejected move changes nothing.
+                prop_assert_eq!(position_string(&engine), position);
+                prop_assert_eq!(engine.move_count(), move_count);
+            }
+
+            // Piece counts never increase, and a capture removes exactly one
+            // opposing piece.
+            prop_assert_eq!(after.0, before.0);
+            if is_capture {
+                prop_assert_eq!(after.1 + 1, before.1);
+            } else {
+                prop_assert_eq!(after.1, before.1);
+            }
+
+            prop_assert_eq!(engine.valid_moves(), regenerated_moves(&engine));
+            prop_assert_eq!(engine.position_hash(), regenerated_hash(&engine));
+        }
+    }
+
+    #[test]
+    fn moves_from_arbitrary_positions_never_panic(
+        pieces in prop::collection::btree_map((0usize..8, 0usize..8), (any::<bool>(), any::<bool>()), 0..24),
+        black_to_move in any::<bool>(),
+        moves in prop::collection::vec(((0usize..10, 0usize..10), (0usize..10, 0usize..10)), 0..50),
+    ) {
+        use rustycheckers::board::{Coordinate, GamePiece};
+
+        // Place pieces on the dark squares only.
+        let pieces: Vec<(Coordinate, GamePiece)> = pieces
+            .into_iter()
+            .filter(|((x, y), _piece)| (x + y) % 2 == 1)
+            .map(|((x, y), (is_black, crowned))| {
+                let color = if is_black { PieceColor::Black } else { PieceColor::White };
+                let piece = GamePiece::new(color);
+                (Coordinate(x, y), if crowned { GamePiece::crown(piece) } else { piece })
+            })
+            .collect();
+        let turn = if black_to_move { PieceColor::Black } else { PieceColor::White };
+        let mut engine = GameEngine::from_position(&pieces, turn);
+
+        for (from, to) in moves {
+            let before = count(&engine, PieceColor::Black) + count(&engine, PieceColor::White);
+            let _ = engine.move_piece(&Move::new(from, to));
+            let after = count(&engine, PieceColor::Black) + count(&engine, PieceColor::White);
+
+            prop_assert!(after <= before);
+            prop_assert_eq!(engine.valid_moves(), regenerated_moves(&engine));
+            prop_assert_eq!(engine.position_hash(), regenerated_hash(&engine));
+        }
+    }
+}
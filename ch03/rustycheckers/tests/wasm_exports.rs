@@ -0,0 +1,528 @@
+//! Tests of the exported WebAssembly interface.
+//!
+//! Builds the crate for `wasm32-unknown-unknown`, loads the module in an
+//! embedded runtime with recording stubs for the `notify_*` imports, and
+//! drives games through `get_piece`, `get_current_turn` and `move_piece`
+//! the way `web/index.js` does.
+
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::OnceLock;
+
+use rustycheckers::{ai, analysis};
+use rustycheckers::board::{Coordinate, GamePiece, Move, PieceColor};
+use rustycheckers::game::GameEngine;
+use rustycheckers::mcts::Rng;
+use rustycheckers::network::Network;
+use rustycheckers::puzzle::Puzzle;
+use rustycheckers::report;
+use wasmi::{Caller, Engine, Instance, Linker, Module, Store, TypedFunc, WasmParams, WasmResults};
+
+const TARGET: &str = "wasm32-unknown-unknown";
+
+const BLACK: i32 = 1;
+const WHITE: i32 = 2;
+
+/// A notification sent by the module to its host.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Event {
+    PieceMoved(i32, i32, i32, i32),
+    PieceCrowned(i32, i32),
+    PuzzleSolved,
+    PuzzleFailed,
+}
+
+/// Builds the wasm module once per test run and returns its bytes.
+fn wasm_bytes() -> &'static [u8] {
+    static WASM: OnceLock<Vec<u8>> = OnceLock::new();
+
+    WASM.get_or_init(|| {
+        let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        // A separate target directory keeps this build from waiting on the
+        // lock held by the `cargo test` that is running us.
+        let target_dir = manifest_dir.join("target").join("wasm-tests");
+
+        let status = Command::new(env!("CARGO"))
+            .current_dir(&manifest_dir)
+            .args(["build", "--lib", "--release", "--target", TARGET])
+            .arg("--target-dir")
+            .arg(&target_dir)
+            .status()
+            .expect("cargo should run");
+        assert!(
+            status.success(),
+            "building for {} failed; is the target installed? \
+             (rustup target add {})",
+            TARGET,
+            TARGET
+        );
+
+        let artifact = target_dir
+            .join(TARGET)
+            .join("release")
+            .join("rustycheckers.wasm");
+        std::fs::read(&artifact).expect("the wasm artifact should exist")
+    })
+}
+
+/// A fresh instance of the module, with its own game.
+struct Checkers {
+    store: Store<Vec<Event>>,
+    instance: Instance,
+    get_piece: TypedFunc<(i32, i32), i32>,
+    get_current_turn: TypedFunc<(), i32>,
+    move_piece: TypedFunc<(i32, i32, i32, i32), i32>,
+    position_hash: TypedFunc<(), i64>,
+}
+
+impl Checkers {
+    fn new() -> Checkers {
+        let engine = Engine::default();
+        let module = Module::new(&engine, wasm_bytes()).expect("the module should validate");
+        let mut store = Store::new(&engine, Vec::new());
+        let mut linker = <Linker<Vec<Event>>>::new(&engine);
+
+        linker
+            .func_wrap(
+                "env",
+                "notify_piece_moved",
+                |mut caller: Caller<'_, Vec<Event>>, from_x, from_y, to_x, to_y| {
+                    caller
+                        .data_mut()
+                        .push(Event::PieceMoved(from_x, from_y, to_x, to_y));
+                },
+            )
+            .unwrap();
+        linker
+            .func_wrap(
+                "env",
+                "notify_piece_crowned",
+                |mut caller: Caller<'_, Vec<Event>>, x, y| {
+                    caller.data_mut().push(Event::PieceCrowned(x, y));
+                },
+            )
+            .unwrap();
+        linker
+            .func_wrap(
+                "env",
+                "notify_puzzle_solved",
+                |mut caller: Caller<'_, Vec<Event>>| {
+                    caller.data_mut().push(Event::PuzzleSolved);
+                },
+            )
+            .unwrap();
+        linker
+            .func_wrap(
+                "env",
+                "notify_puzzle_failed",
+                |mut caller: Caller<'_, Vec<Event>>| {
+                    caller.data_mut().push(Event::PuzzleFailed);
+                },
+            )
+            .unwrap();
+
+        let instance = linker
+            .instantiate(&mut store, &module)
+            .and_then(|instance| instance.start(&mut store))
+            .expect("the module should instantiate with only the notify imports");
+
+        Checkers {
+            get_piece: instance.get_typed_func(&store, "get_piece").unwrap(),
+            get_current_turn: instance.get_typed_func(&store, "get_current_turn").unwrap(),
+            move_piece: instance.get_typed_func(&store, "move_piece").unwrap(),
+            position_hash: instance.get_typed_func(&store, "position_hash").unwrap(),
+            instance,
+            store,
+        }
+    }
+
+    fn piece(&mut self, x: i32, y: i32) -> i32 {
+        self.get_piece.call(&mut self.store, (x, y)).unwrap()
+    }
+
+    fn turn(&mut self) -> i32 {
+        self.get_current_turn.call(&mut self.store, ()).unwrap()
+    }
+
+    /// Plays a move and returns its result code.
+    fn play(&mut self, from_x: i32, from_y: i32, to_x: i32, to_y: i32) -> i32 {
+        self.move_piece
+            .call(&mut self.store, (from_x, from_y, to_x, to_y))
+            .unwrap()
+    }
+
+    fn hash(&mut self) -> u64 {
+        self.position_hash.call(&mut self.store, ()).unwrap() as u64
+    }
+
+    /// Calls any other export.
+    fn call<P: WasmParams, R: WasmResults>(&mut self, name: &str, params: P) -> R {
+        self.instance
+            .get_typed_func::<P, R>(&self.store, name)
+            .unwrap_or_else(|_| panic!("{} should be exported", name))
+            .call(&mut self.store, params)
+            .unwrap()
+    }
+
+    /// Reads a run of bytes from the module's memory.
+    fn read(&mut self, address: i32, length: i32) -> Vec<u8> {
+        let memory = self
+            .instance
+            .get_memory(&self.store, "memory")
+            .expect("memory should be exported");
+        let mut bytes = vec![0; length as usize];
+        memory
+            .read(&self.store, address as usize, &mut bytes)
+            .unwrap();
+        bytes
+    }
+
+    /// Writes bytes into the module's memory.
+    fn write(&mut self, address: i32, bytes: &[u8]) {
+        let memory = self
+            .instance
+            .get_memory(&self.store, "memory")
+            .expect("memory should be exported");
+        memory
+            .write(&mut self.store, address as usize, bytes)
+            .unwrap();
+    }
+
+    /// Writes puzzle text to the puzzle buffer and loads it.
+    fn load_puzzle(&mut self, text: &str) -> i32 {
+        let address = self.call::<(), i32>("puzzle_buffer", ());
+        self.write(address, text.as_bytes());
+        self.call::<i32, i32>("load_puzzle", text.len() as i32)
+    }
+
+    /// Takes the notifications received so far.
+    fn events(&mut self) -> Vec<Event> {
+        std::mem::take(self.store.data_mut())
+    }
+}
+
+fn play_move(checkers: &mut Checkers, movement: &Move) -> i32 {
+    let Move {
+        from: Coordinate(from_x, from_y),
+        to: Coordinate(to_x, to_y),
+    } = *movement;
+
+    checkers.play(from_x as i32, from_y as i32, to_x as i32, to_y as i32)
+}
+
+/// Packs a move the way the hint and analysis exports do.
+fn encode(movement: &Move) -> i32 {
+    u16::try_from(*movement).unwrap().into()
+}
+
+#[test]
+fn start_position_encoding() {
+    let mut checkers = Checkers::new();
+
+    assert_eq!(checkers.turn(), BLACK);
+    assert_eq!(checkers.piece(0, 7), BLACK);
+    assert_eq!(checkers.piece(1, 0), WHITE);
+    assert_eq!(checkers.piece(0, 4), -1);
+    assert_eq!(checkers.piece(8, 0), -1);
+    assert_eq!(checkers.piece(-1, 3), -1);
+    assert!(checkers.events().is_empty());
+}
+
+/// The script `web/index.js` runs, with its console output turned into
+/// assertions.
+#[test]
+fn index_js_script() {
+    let mut checkers = Checkers::new();
+
+    assert_eq!(checkers.play(0, 5, 1, 4), 1);
+    assert_eq!(checkers.events(), vec![Event::PieceMoved(0, 5, 1, 4)]);
+    assert_eq!(checkers.turn(), WHITE);
+    assert_eq!(checkers.piece(1, 4), BLACK);
+    assert_eq!(checkers.piece(0, 5), -1);
+
+    // Black cannot move on White's turn.
+    assert_eq!(checkers.play(1, 4, 2, 3), 0);
+    assert!(checkers.events().is_empty());
+    assert_eq!(checkers.turn(), WHITE);
+
+    assert_eq!(checkers.play(0, 0, 0, 2), 0);
+    assert_eq!(checkers.play(-1, 0, 9, 9), 0);
+    assert!(checkers.events().is_empty());
+}
+
+#[test]
+fn capture_removes_the_jumped_piece() {
+    let mut checkers = Checkers::new();
+
+    // 11-15 22-18 15x22
+    assert_eq!(checkers.play(2, 5, 3, 4), 1);
+    assert_eq!(checkers.play(5, 2, 4, 3), 1);
+    assert_eq!(checkers.play(3, 4, 5, 2), 1);
+
+    assert_eq!(checkers.piece(4, 3), -1);
+    assert_eq!(checkers.piece(5, 2), BLACK);
+    assert_eq!(
+        checkers.events(),
+        vec![
+            Event::PieceMoved(2, 5, 3, 4),
+            Event::PieceMoved(5, 2, 4, 3),
+            Event::PieceMoved(3, 4, 5, 2),
+        ]
+    );
+}
+
+#[test]
+fn rejects_coordinates_off_the_board() {
+    let mut checkers = Checkers::new();
+
+    assert_eq!(checkers.piece(-1, 0), -1);
+    assert_eq!(checkers.piece(0, i32::MIN), -1);
+    assert_eq!(checkers.play(-2, 5, 3, 4), 0);
+    assert_eq!(checkers.play(2, 5, 3, -4), 0);
+    assert_eq!(checkers.turn(), BLACK);
+    assert!(checkers.events().is_empty());
+
+    assert_eq!(checkers.call::<i32, i32>("begin_editing", 0), 1);
+    assert_eq!(checkers.call::<_, i32>("editor_place", (-1, 0, BLACK)), 1);
+    assert_eq!(checkers.call::<_, i32>("editor_get_piece", (-1, 0)), -1);
+    assert_eq!(checkers.call::<_, i32>("editor_remove", (0, -7)), -1);
+}
+
+#[test]
+fn plays_from_whites_side() {
+    let mut checkers = Checkers::new();
+
+    assert_eq!(checkers.call::<i32, i32>("new_game", WHITE), 1);
+    assert_eq!(checkers.call::<i32, i32>("set_perspective", WHITE), 1);
+    assert_eq!(checkers.call::<i32, i32>("set_perspective", 4), 0);
+    assert_eq!(checkers.call::<(), i32>("get_perspective", ()), WHITE);
+    assert_eq!(checkers.turn(), WHITE);
+
+    // White's men are now along the bottom, at y = 5 to 7.
+    assert_eq!(checkers.piece(0, 7), WHITE);
+    assert_eq!(checkers.piece(1, 0), BLACK);
+
+    // The board move (1, 2) to (2, 3), as White sees it.
+    assert_eq!(checkers.play(6, 5, 5, 4), 1);
+    assert_eq!(checkers.events(), vec![Event::PieceMoved(6, 5, 5, 4)]);
+    assert_eq!(checkers.piece(5, 4), WHITE);
+
+    let mut native = GameEngine::starting_with(PieceColor::White);
+    native.move_piece(&Move::new((1, 2), (2, 3))).unwrap();
+    assert_eq!(checkers.hash(), native.position_hash());
+
+    let best = ai::search(&native, 3).best_move.unwrap();
+    let hint = checkers.call::<i32, i32>("get_hint", 3);
+    assert_eq!(hint, encode(&best.seen_by(PieceColor::White)));
+}
+
+#[test]
+fn reports_on_the_game() {
+    let mut checkers = Checkers::new();
+    let moves = [
+        Move::new((2, 5), (3, 4)),
+        Move::new((5, 2), (4, 3)),
+        Move::new((3, 4), (5, 2)),
+    ];
+
+    for (movement, millis) in moves.iter().zip([900, 1500]) {
+        assert_eq!(play_move(&mut checkers, movement), 1);
+        assert_eq!(checkers.call::<i32, i32>("record_move_time", millis), 1);
+    }
+    assert_eq!(checkers.call::<i32, i32>("record_move_time", 10), 0);
+    assert_eq!(play_move(&mut checkers, &moves[2]), 1);
+
+    let length = checkers.call::<i32, i32>("game_report", 2);
+    let address = checkers.call::<(), i32>("report_buffer", ());
+    let json = String::from_utf8(checkers.read(address, length)).unwrap();
+
+    let expected = report::report(&GameEngine::new(), &moves, &[900, 1500], 2);
+    assert_eq!(json, expected.to_json());
+    assert!(json.contains("\"black\":{\"captures\":1,\"kings\":[],\"time_ms\":null}"));
+    assert!(json.contains("\"white\":{\"captures\":0,\"kings\":[],\"time_ms\":1500}"));
+}
+
+#[test]
+fn position_hash_matches_native_engine() {
+    let mut checkers = Checkers::new();
+    let mut native = GameEngine::new();
+
+    assert_eq!(checkers.hash(), native.position_hash());
+
+    let movement = Move::new((2, 5), (3, 4));
+    native.move_piece(&movement).unwrap();
+    assert_eq!(play_move(&mut checkers, &movement), 1);
+    assert_eq!(checkers.hash(), native.position_hash());
+}
+
+#[test]
+fn editor_sets_up_a_position() {
+    let mut checkers = Checkers::new();
+
+    assert_eq!(checkers.call::<i32, i32>("begin_editing", 0), 1);
+    // An empty board has no pieces to play with.
+    assert_eq!(checkers.call::<(), i32>("editor_validate", ()), 5);
+
+    assert_eq!(checkers.call::<_, i32>("editor_place", (3, 4, BLACK)), 0);
+    assert_eq!(checkers.call::<_, i32>("editor_place", (4, 3, WHITE)), 0);
+    assert_eq!(checkers.call::<_, i32>("editor_place", (0, 0, WHITE)), 2);
+    assert_eq!(checkers.call::<_, i32>("editor_place", (1, 0, 3)), -1);
+    assert_eq!(
+        checkers.call::<_, i32>("editor_toggle_king", (3, 4)),
+        BLACK | 4
+    );
+    assert_eq!(checkers.call::<_, i32>("editor_set_turn", WHITE), 1);
+    assert_eq!(checkers.call::<(), i32>("editor_start", ()), 0);
+
+    assert_eq!(checkers.turn(), WHITE);
+    assert_eq!(checkers.piece(3, 4), BLACK | 4);
+    assert_eq!(checkers.piece(0, 7), -1);
+    // White takes the king.
+    assert_eq!(checkers.play(4, 3, 2, 5), 1);
+    assert_eq!(checkers.piece(3, 4), -1);
+}
+
+#[test]
+fn plays_puzzles() {
+    let mut checkers = Checkers::new();
+    // Black's king takes one man, White's last man has a single move, and
+    // the king takes it too.
+    let king = GamePiece::crown(GamePiece::new(PieceColor::Black));
+    let start = GameEngine::from_position(
+        &[
+            (Coordinate(4, 5), king),
+            (Coordinate(3, 4), GamePiece::new(PieceColor::White)),
+            (Coordinate(0, 1), GamePiece::new(PieceColor::White)),
+        ],
+        PieceColor::Black,
+    );
+    let solution = vec![
+        Move::new((4, 5), (2, 3)),
+        Move::new((0, 1), (1, 2)),
+        Move::new((2, 3), (0, 1)),
+    ];
+    let puzzle = Puzzle::new(start, solution).unwrap();
+
+    assert_eq!(checkers.load_puzzle("nonsense 11-15"), 0);
+    assert_eq!(checkers.call::<i32, i32>("load_puzzle", 1025), 0);
+    assert_eq!(checkers.call::<i32, i32>("load_puzzle", -1), 0);
+    assert_eq!(checkers.load_puzzle(&puzzle.to_text()), 1);
+    assert_eq!(checkers.piece(4, 5), BLACK | 4);
+    assert_eq!(checkers.piece(0, 7), -1);
+
+    // A legal move that doesn't win fails the puzzle.
+    assert_eq!(checkers.call::<_, i32>("puzzle_move", (4, 5, 5, 6)), 3);
+    assert_eq!(checkers.events(), vec![Event::PuzzleFailed]);
+    assert_eq!(checkers.call::<_, i32>("puzzle_move", (4, 5, 2, 3)), 0);
+
+    assert_eq!(checkers.call::<(), i32>("reset_puzzle", ()), 1);
+    assert_eq!(checkers.piece(4, 5), BLACK | 4);
+    assert_eq!(checkers.piece(3, 4), WHITE);
+    assert_eq!(checkers.turn(), BLACK);
+
+    assert_eq!(checkers.call::<_, i32>("puzzle_move", (4, 5, 2, 3)), 1);
+    assert_eq!(
+        checkers.events(),
+        vec![Event::PieceMoved(4, 5, 2, 3), Event::PieceMoved(0, 1, 1, 2)]
+    );
+    assert_eq!(checkers.piece(3, 4), -1);
+
+    assert_eq!(checkers.call::<_, i32>("puzzle_move", (2, 3, 0, 1)), 2);
+    assert_eq!(
+        checkers.events(),
+        vec![Event::PieceMoved(2, 3, 0, 1), Event::PuzzleSolved]
+    );
+    assert_eq!(checkers.piece(0, 1), BLACK | 4);
+
+    assert_eq!(checkers.call::<(), i32>("reset_puzzle", ()), 1);
+    assert_eq!(checkers.piece(4, 5), BLACK | 4);
+    assert_eq!(checkers.piece(3, 4), WHITE);
+    assert_eq!(checkers.piece(0, 1), WHITE);
+    assert_eq!(checkers.piece(0, 5), -1);
+    assert!(checkers.events().is_empty());
+}
+
+#[test]
+fn embedded_network_gives_hints() {
+    let mut checkers = Checkers::new();
+    let engine = GameEngine::new();
+    let network = Network::embedded();
+
+    assert_eq!(checkers.call::<i32, i32>("set_evaluation", 2), -1);
+    assert_eq!(checkers.call::<i32, i32>("set_evaluation", 1), 0);
+
+    // The wasm build runs the same network as the native one.
+    let best = ai::search_with(&engine, 3, &network).best_move.unwrap();
+    assert_eq!(checkers.call::<i32, i32>("get_hint", 3), encode(&best));
+
+    // Analysis uses the same evaluation as the hints.
+    let analysis = analysis::analyze_with(&engine, 3, &network);
+    assert_eq!(
+        checkers.call::<i32, i32>("analyze_position", 3),
+        analysis.moves.len() as i32
+    );
+    assert_eq!(checkers.call::<(), i32>("get_evaluation", ()), analysis.score);
+
+    // So does the game report.
+    let length = checkers.call::<i32, i32>("game_report", 2);
+    let address = checkers.call::<(), i32>("report_buffer", ());
+    let json = String::from_utf8(checkers.read(address, length)).unwrap();
+    assert_eq!(json, report::report_with(&engine, &[], &[], 2, &network).to_json());
+
+    assert_eq!(checkers.call::<i32, i32>("set_evaluation", 0), 0);
+    let best = ai::search(&engine, 3).best_move.unwrap();
+    assert_eq!(checkers.call::<i32, i32>("get_hint", 3), encode(&best));
+}
+
+/// Plays random games through the exports and checks every result code,
+/// notification and square against the native engine.
+#[test]
+fn random_games_match_native_engine() {
+    let mut crownings = 0;
+
+    for seed in 1..=20 {
+        let mut rng = Rng::new(seed);
+        let mut native = GameEngine::new();
+        let mut checkers = Checkers::new();
+
+        for _ply in 0..200 {
+            let legal = native.valid_moves();
+            if legal.is_empty() {
+                break;
+            }
+
+            let movement = legal[rng.below(legal.len())];
+            let result = native.move_piece(&movement).unwrap();
+            let Move { from, to } = movement;
+
+            let mut expected = vec![Event::PieceMoved(
+                from.0 as i32,
+                from.1 as i32,
+                to.0 as i32,
+                to.1 as i32,
+            )];
+            if result.crowned {
+                expected.push(Event::PieceCrowned(to.0 as i32, to.1 as i32));
+                crownings += 1;
+            }
+
+            assert_eq!(play_move(&mut checkers, &movement), 1);
+            assert_eq!(checkers.events(), expected);
+        }
+
+        for y in 0..8 {
+            for x in 0..8 {
+                let expected = match native.get_piece(Coordinate(x, y)) {
+                    Ok(Some(piece)) => piece.into(),
+                    _ => -1,
+                };
+                assert_eq!(checkers.piece(x as i32, y as i32), expected);
+            }
+        }
+
+        let turn: i32 = GamePiece::new(native.current_turn()).into();
+        assert_eq!(checkers.turn(), turn);
+    }
+
+    assert!(crownings > 0, "the games should include a crowning");
+}
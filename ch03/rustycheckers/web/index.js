@@ -7,6 +7,12 @@ fetch('./build/rustycheckers.wasm')
       },
       notify_piece_crowned: (x, y) => {
         console.log(`A piece was crowned at (${x}, ${y})`);
+      },
+      notify_puzzle_solved: () => {
+        console.log('Puzzle solved');
+      },
+      notify_puzzle_failed: () => {
+        console.log('Puzzle failed');
       }
     }
   }))
@@ -1,134 +1,104 @@
-import { DIRS, Display, Engine as RotEngine, Path } from "rot-js"
-import Borrowmir from "./Borrowmir"
-import Player from "./Player"
-import PlayerTracker from "./PlayerTracker"
-import Point from "./Point"
+import { DIRS } from "rot-js"
 import { GameEngine } from "./roguewasm"
-
-export enum ActionType {
-  Move,
-  Dig
+import { clearSave, writeSave } from "./SaveStore"
+
+export type InventoryEntry = {
+  name: string
+  icon: string
+  color: string
+  category: string
+  description: string
+  equipped: boolean
 }
 
 export default class BeingActionHandler {
-  display: Display
   gameEngine: GameEngine
-  rotEngine: RotEngine
   window: Window
-  playerTracker: PlayerTracker
+  showMessages: (messages: string[]) => void
+  showInventory: (inventory: InventoryEntry[]) => void
 
   OPEN_KEYCODES = [13, 32]
+  PICK_UP_KEYCODE = 71
+  DESCEND_KEY = ">"
+  ASCEND_KEY = "<"
+  // 1 to 9, then 0, pick inventory slots 0 to 9.
+  SLOT_KEYCODES = [49, 50, 51, 52, 53, 54, 55, 56, 57, 48]
   MOVE_KEYCODES = [38, 33, 39, 34, 40, 35, 37, 36]
-  VALID_KEYCODES = this.OPEN_KEYCODES.concat(this.MOVE_KEYCODES)
   KEYS_TO_DIRS = this.MOVE_KEYCODES.reduce((acc: Map<number, number>, value: number, index: number) => {
     return acc.set(value, index)
   }, new Map<number, number>())
 
-  constructor(display: Display, gameEngine: GameEngine, rotEngine: RotEngine, window: Window, playerTracker: PlayerTracker) {
-    this.display = display
+  constructor(
+    gameEngine: GameEngine,
+    window: Window,
+    showMessages: (messages: string[]) => void,
+    showInventory: (inventory: InventoryEntry[]) => void
+  ) {
     this.gameEngine = gameEngine
-    this.rotEngine = rotEngine
     this.window = window
-    this.playerTracker = playerTracker
+    this.showMessages = showMessages
+    this.showInventory = showInventory
   }
 
-  act(actor: Borrowmir | Player) {
-    if (actor instanceof Player) {
-      this.rotEngine.lock();
-      this.window.addEventListener("keydown", this.getKeydownHandler(actor));
-    } else {
-      this.moveEnemy(actor)
-    }
+  // Forwards key presses to the engine, which runs the monsters' turns
+  // until it's the player's go again.
+  listen() {
+    this.window.addEventListener("keydown", this.handleKeydown)
   }
 
-  moveEnemy(enemy: Borrowmir) {
-    const playerPosition = this.playerTracker.getPosition()
-    const x = playerPosition.x
-    const y = playerPosition.y
-    let path: Point[] = [];
-
-    const passCallback = (x: number, y: number) => {
-      return this.gameEngine.free_cell(x, y)
-    }
-
-    const pathCallback = (x: number, y: number) => {
-      path.push(new Point(x, y))
-    }
-
-    const pathFinder = new Path.AStar(x, y, passCallback, { topology: 4 })
-
-    if (!enemy.being) {
-      // TODO: Something smarter here
+  handleKeydown = (event: KeyboardEvent) => {
+    const keyCode = event.keyCode
+
+    if (!this.gameEngine.player_turn_pending()) { return }
+
+    const slot = this.SLOT_KEYCODES.indexOf(keyCode)
+
+    if (this.OPEN_KEYCODES.includes(keyCode)) {
+      this.gameEngine.open_box()
+    } else if (event.key === this.DESCEND_KEY) {
+      this.gameEngine.descend()
+    } else if (event.key === this.ASCEND_KEY) {
+      this.gameEngine.ascend()
+    } else if (keyCode === this.PICK_UP_KEYCODE) {
+      this.gameEngine.pick_up()
+    } else if (slot >= 0) {
+      // Shift and a slot drops the item instead of using it.
+      if (event.shiftKey) {
+        this.gameEngine.drop_item(slot)
+      } else {
+        this.gameEngine.use_item(slot)
+      }
+    } else if (!this.movePlayer(keyCode)) {
       return
     }
 
-    pathFinder.compute(enemy.being.x(), enemy.being.y(), pathCallback)
-
-    let nextMove = path.shift()
-
-    while (nextMove) {
-      let x = nextMove.x
-      let y = nextMove.y
-
-      this.moveActor(enemy, x, y);
-    }
-
-    this.rotEngine.lock();
-    this.window.alert("Game over - you were captured by the Borrow Checker!!");
+    this.gameEngine.run_until_player_turn()
+    this.refresh()
+    this.persist()
   }
 
-  openBox(player: Player) {
-    if (!player.being) {
-      throw new Error('Player is missing a being!')
+  // Saves after every turn; a finished run can't be continued.
+  persist() {
+    if (this.gameEngine.is_game_over()) {
+      clearSave()
+    } else {
+      writeSave(this.gameEngine.save())
     }
+  }
 
-    this.gameEngine.open_box(player.being)
+  // Shows the messages logged since the last refresh, and the inventory.
+  refresh() {
+    this.showMessages(this.gameEngine.take_messages())
+    this.showInventory(this.gameEngine.inventory())
   }
 
-  movePlayer(player: Player, keyCode: number) {
+  movePlayer(keyCode: number): boolean {
     const directionIndex = this.KEYS_TO_DIRS.get(keyCode)
 
-    if (directionIndex === undefined) { return }
+    if (directionIndex === undefined) { return false }
 
     const [xDelta, yDelta] = DIRS[8][directionIndex]
-    const newX = player.getX() + xDelta
-    const newY = player.getY() + yDelta
-
-    return this.moveActor(player, newX, newY)
-  }
-
-  moveActor(actor: Player | Borrowmir, x: number, y: number) {
-    const being = actor.being
-
-    if (!being) {
-      throw new Error('Actor is missing a being!')
-    }
-
-
-    if (!this.gameEngine.free_cell(x, y) || !being) { return }
-
-    this.gameEngine.move_being(being, x, y)
-  }
-
-  getKeydownHandler(player: Player): (event: KeyboardEvent) => void {
-    const keyListener = (event: KeyboardEvent) => {
-      const keyCode = event.keyCode
-
-      if (!(keyCode in this.VALID_KEYCODES)) { return; }
-
-      // If the keypress is our "open" command, just do that and quit
-      if (keyCode in this.OPEN_KEYCODES) {
-          this.openBox(player)
-      } else {
-        // Otherwise, evaluate the movement
-        this.movePlayer(player, keyCode)
-      }
-
-      window.removeEventListener("keydown", keyListener);
-
-      this.rotEngine.unlock();
-    }
 
-    return keyListener
+    return this.gameEngine.move_player(xDelta, yDelta)
   }
 }
@@ -1,13 +1,8 @@
+import BeingActionHandler from "./BeingActionHandler"
 import BeingCreator from "./BeingCreator"
-import Borrowmir from "./Borrowmir"
-import Player from "./Player"
-import PlayerTracker from "./PlayerTracker"
-import FreeCells from "./FreeCells"
-import Point from "./Point"
 
-import { Engine as RotEngine, Map } from "rot-js"
-import Simple from "rot-js/lib/scheduler/simple"
-import { GameEngine } from "./roguewasm"
+import { GameEngine, MapStyle } from "./roguewasm"
+import { readSave } from "./SaveStore"
 
 export type Dimensions = {
   height: number
@@ -15,70 +10,41 @@ export type Dimensions = {
 }
 
 export type GameContext = {
-  freeCells: FreeCells,
   gameEngine: GameEngine,
-  rotEngine: RotEngine,
-  scheduler: Simple
+  actionHandler: BeingActionHandler
 }
 
 export default class Game {
   context: GameContext
   creator: BeingCreator
   dimensions: Dimensions
-  enemy?: Borrowmir
-  player?: Player
-  playerTracker: PlayerTracker
 
-  constructor(context: GameContext, creator: BeingCreator, playerTracker: PlayerTracker, dimensions: Dimensions) {
+  constructor(context: GameContext, creator: BeingCreator, dimensions: Dimensions) {
     this.context = context
     this.creator = creator
-    this.playerTracker = playerTracker
     this.dimensions = dimensions
   }
 
+  // The engine stocks each level, and makes the deeper ones as the player
+  // takes the stairs.
   generateMap = (): void => {
-    const digger = new Map.Digger(this.dimensions.height, this.dimensions.width)
+    this.context.gameEngine.generate_map(this.dimensions.width, this.dimensions.height, MapStyle.Rooms)
 
-    const digCallback = (x: number, y: number, value: number) => {
-      if (!value) {
-        var key = new Point(x, y)
-        this.context.freeCells.push(key)
-      }
+    this.creator.createPlayer()
 
-      this.context.gameEngine.on_dig(x, y, value)
-    }
-
-    digger.create(digCallback.bind(this))
-
-    this.generateBoxes()
     this.context.gameEngine.draw_map()
-
-    this.player = this.creator.createPlayer(this.context.freeCells)
-
-    const playerPosition = new Point(this.player.getX(), this.player.getY())
-    this.playerTracker.setPosition(playerPosition)
-
-    this.enemy = this.creator.createEnemy(this.context.freeCells)
   }
 
-  generateBoxes = (): void => {
-    for (let i = 0; i < 10; i++) {
-      const randomCell = this.context.freeCells.popRandomCell()
-
-      this.context.gameEngine.place_box(randomCell.x, randomCell.y);
+  // Continues the saved run if `resume` is set and there is one.
+  start = (resume: boolean) => {
+    const saved = resume ? readSave() : null
 
-      if (i == 9) {
-        this.context.gameEngine.mark_prize(randomCell.x, randomCell.y);
-      }
+    if (saved === null || !this.context.gameEngine.load(saved)) {
+      this.generateMap()
     }
-  }
-
-  start = () => {
-    this.generateMap()
-
-    this.context.scheduler.add(this.player, true);
-    this.context.scheduler.add(this.enemy, true);
 
-    this.context.rotEngine.start();
+    this.context.gameEngine.run_until_player_turn()
+    this.context.actionHandler.refresh()
+    this.context.actionHandler.listen()
   }
 }
@@ -1,11 +0,0 @@
-export default class Point {
-  public x: number
-  public y: number
-
-  constructor(x: number, y: number) {
-    this.x = x
-    this.y = y
-  }
-
-  key = (): string => `${this.x},${this.y}`
-}
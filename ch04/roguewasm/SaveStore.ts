@@ -0,0 +1,45 @@
+// Keeps one saved game in localStorage, so a reload picks up the run.
+const SAVE_KEY = "roguewasm-save"
+
+export function readSave(): Uint8Array | null {
+  const encoded = window.localStorage.getItem(SAVE_KEY)
+
+  if (encoded === null) {
+    return null
+  }
+
+  // Bad base64 can't be a save; drop it so the next start is clean.
+  let binary: string
+  try {
+    binary = window.atob(encoded)
+  } catch (error) {
+    console.error("Discarding a corrupt save", error)
+    clearSave()
+    return null
+  }
+
+  const bytes = new Uint8Array(binary.length)
+  for (let i = 0; i < binary.length; i++) {
+    bytes[i] = binary.charCodeAt(i)
+  }
+
+  return bytes
+}
+
+// Storage can be full or disabled; the run goes on unsaved then.
+export function writeSave(bytes: Uint8Array) {
+  let binary = ""
+  for (let i = 0; i < bytes.length; i++) {
+    binary += String.fromCharCode(bytes[i])
+  }
+
+  try {
+    window.localStorage.setItem(SAVE_KEY, window.btoa(binary))
+  } catch (error) {
+    console.error("Couldn't save the game", error)
+  }
+}
+
+export function clearSave() {
+  window.localStorage.removeItem(SAVE_KEY)
+}
@@ -1,45 +1,111 @@
-import BeingActionHandler from "./BeingActionHandler"
+import BeingActionHandler, { InventoryEntry } from "./BeingActionHandler"
 import BeingCreator from "./BeingCreator"
-import FreeCells from "./FreeCells"
 import Game, { Dimensions, GameContext } from "./Game"
-import PlayerTracker from "./PlayerTracker"
-import Point from "./Point"
-import { Display, Engine as RotEngine } from "rot-js"
+import { Display } from "rot-js"
 import { GameEngine } from "./roguewasm"
-import Simple from "rot-js/lib/scheduler/simple"
+import { clearSave } from "./SaveStore"
 
 const WINDOW_WIDTH = 125
 const WINDOW_HEIGHT = 40
 
+// Field names match the `Stats` struct serialized by the Rust side.
 export type Stats = {
-  hitPoints: number
-  maxHitpoints: number
+  hitpoints: number
+  max_hitpoints: number
   moves: number
+  depth: number
 }
 
 export function stats_updated(stats: Stats) {
-  let hitPoints = document.getElementById("hitpoints")
-  let maxHitpoints = document.getElementById("max_hitpoints")
-  let moves = document.getElementById("moves")
+  let hitPoints = document.getElementById("hit-points")
+  let maxHitpoints = document.getElementById("max-hitpoints")
+  let moves = document.getElementById("move-counter")
+  let depth = document.getElementById("depth")
 
-  if (!hitPoints || !maxHitpoints || !moves) {
+  if (!hitPoints || !maxHitpoints || !moves || !depth) {
     return
   }
 
-  hitPoints.textContent = stats.hitPoints.toString()
-  maxHitpoints.textContent = stats.maxHitpoints.toString()
+  hitPoints.textContent = stats.hitpoints.toString()
+  maxHitpoints.textContent = stats.max_hitpoints.toString()
   moves.textContent = stats.moves.toString()
+  depth.textContent = stats.depth.toString()
+}
+
+// A run can be replayed, or shared as a challenge, with `?seed=N`.
+function seedParam(): number | null {
+  const param = new URLSearchParams(window.location.search).get("seed")
+  const seed = param === null ? NaN : parseInt(param, 10)
+
+  return isNaN(seed) ? null : seed >>> 0
+}
+
+// Drops the saved run and reloads without a seed, for a fresh random run.
+function listenForNewGame() {
+  const button = document.getElementById("new-game")
+
+  if (!button) {
+    return
+  }
+
+  button.addEventListener("click", () => {
+    if (window.confirm("Abandon this run and start a new one?")) {
+      clearSave()
+      window.location.href = window.location.pathname
+    }
+  })
+}
+
+const MESSAGE_LIMIT = 8
+
+export function showMessages(messages: string[]) {
+  const log = document.getElementById("message-log")
+
+  if (!log) {
+    return
+  }
+
+  for (const message of messages) {
+    const line = document.createElement("li")
+    line.textContent = message
+    log.appendChild(line)
+  }
+
+  while (log.children.length > MESSAGE_LIMIT && log.firstChild) {
+    log.removeChild(log.firstChild)
+  }
+}
+
+export function showInventory(inventory: InventoryEntry[]) {
+  const list = document.getElementById("inventory")
+
+  if (!list) {
+    return
+  }
+
+  list.innerHTML = ""
+  inventory.forEach((item, slot) => {
+    const line = document.createElement("li")
+    const icon = document.createElement("span")
+    icon.textContent = item.icon
+    icon.style.color = item.color
+    line.appendChild(icon)
+    line.appendChild(document.createTextNode(
+      ` ${(slot + 1) % 10}: ${item.name} (${item.description})${item.equipped ? " [equipped]" : ""}`
+    ))
+    list.appendChild(line)
+  })
 }
 
 const runGame = () => {
   const dimensions = { height: WINDOW_WIDTH, width: WINDOW_WIDTH }
   const display = new Display({width: dimensions.width, height: dimensions.height})
-  const gameEngine = new GameEngine(display)
-  const scheduler = new Simple();
-  const rotEngine = new RotEngine(scheduler);
-  const playerTracker = new PlayerTracker(new Point(0, 0))
-  const actionHandler = new BeingActionHandler(display, gameEngine, rotEngine, window, playerTracker)
-  const creator = new BeingCreator(display, gameEngine, actionHandler)
+  const requestedSeed = seedParam()
+  const seed = requestedSeed === null ? Math.floor(Math.random() * 0x100000000) : requestedSeed
+  console.log(`Seed: ${seed}`)
+  const gameEngine = new GameEngine(display, seed)
+  const actionHandler = new BeingActionHandler(gameEngine, window, showMessages, showInventory)
+  const creator = new BeingCreator(gameEngine)
 
 
   const container = display.getContainer()
@@ -47,11 +113,9 @@ const runGame = () => {
   const canvasElement = document.getElementById(canvasId)
   const context: GameContext = {
     gameEngine,
-    freeCells: new FreeCells(),
-    rotEngine,
-    scheduler
+    actionHandler
   }
-  const game = new Game(context, creator, playerTracker, dimensions)
+  const game = new Game(context, creator, dimensions)
 
   if (!container) {
     console.error("Failed to instantiate display container")
@@ -64,7 +128,9 @@ const runGame = () => {
   }
 
   canvasElement.appendChild(container)
-  game.start()
+  listenForNewGame()
+  // A seed in the URL asks for that run from the start, not the saved one.
+  game.start(requestedSeed === null)
 }
 
 runGame()
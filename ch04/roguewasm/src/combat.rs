@@ -0,0 +1,112 @@
+//! Melee combat rolls.
+
+use super::rng::Rng;
+
+/// The chance to hit, in percent, between evenly matched fighters.
+const BASE_HIT_CHANCE: i32 = 70;
+/// Each point of attack over the defender's defense adds this much.
+const HIT_CHANCE_PER_POINT: i32 = 5;
+const MIN_HIT_CHANCE: i32 = 5;
+const MAX_HIT_CHANCE: i32 = 95;
+/// The chance, in percent, that a hit is critical.
+const CRITICAL_CHANCE: i32 = 5;
+const CRITICAL_MULTIPLIER: i32 = 2;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct CombatStats {
+    pub attack: i32,
+    pub defense: i32,
+    pub min_damage: i32,
+    pub max_damage: i32,
+}
+
+impl CombatStats {
+    pub const fn new(attack: i32, defense: i32, min_damage: i32, max_damage: i32) -> CombatStats {
+        CombatStats {
+            attack,
+            defense,
+            min_damage,
+            max_damage,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Outcome {
+    Miss,
+    Hit(i32),
+    Critical(i32),
+}
+
+impl Outcome {
+    pub fn damage(self) -> i32 {
+        match self {
+            Outcome::Miss => 0,
+            Outcome::Hit(damage) | Outcome::Critical(damage) => damage,
+        }
+    }
+}
+
+/// The percent chance that `attacker` hits `defender`.
+pub fn hit_chance(attacker: &CombatStats, defender: &CombatStats) -> i32 {
+    let chance = BASE_HIT_CHANCE + (attacker.attack - defender.defense) * HIT_CHANCE_PER_POINT;
+
+    chance.clamp(MIN_HIT_CHANCE, MAX_HIT_CHANCE)
+}
+
+/// Rolls one attack.
+pub fn attack(attacker: &CombatStats, defender: &CombatStats, rng: &mut Rng) -> Outcome {
+    if rng.range(0, 100) >= hit_chance(attacker, defender) {
+        return Outcome::Miss;
+    }
+
+    let damage = rng.range(attacker.min_damage, attacker.max_damage + 1);
+    if rng.range(0, 100) < CRITICAL_CHANCE {
+        Outcome::Critical(damage * CRITICAL_MULTIPLIER)
+    } else {
+        Outcome::Hit(damage)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::rng::Rng;
+    use super::{attack, hit_chance, CombatStats, Outcome};
+
+    #[test]
+    fn hit_chance_follows_attack_and_defense() {
+        let even = CombatStats::new(3, 3, 1, 4);
+        let strong = CombatStats::new(7, 3, 1, 4);
+        let wall = CombatStats::new(0, 40, 1, 4);
+
+        assert_eq!(hit_chance(&even, &even), 70);
+        assert_eq!(hit_chance(&strong, &even), 90);
+        assert_eq!(hit_chance(&even, &wall), 5);
+        assert_eq!(hit_chance(&wall, &even), 55);
+    }
+
+    #[test]
+    fn damage_stays_in_range_and_crits_double_it() {
+        let fighter = CombatStats::new(5, 5, 2, 6);
+        let mut rng = Rng::new(3);
+        let (mut misses, mut hits, mut crits) = (0, 0, 0);
+
+        for _ in 0..2000 {
+            match attack(&fighter, &fighter, &mut rng) {
+                Outcome::Miss => misses += 1,
+                Outcome::Hit(damage) => {
+                    assert!((2..=6).contains(&damage));
+                    hits += 1;
+                }
+                Outcome::Critical(damage) => {
+                    assert!((4..=12).contains(&damage) && damage % 2 == 0);
+                    crits += 1;
+                }
+            }
+        }
+
+        assert!(misses > 450 && misses < 750);
+        assert!(hits > crits * 5);
+        assert!(crits > 0);
+    }
+}
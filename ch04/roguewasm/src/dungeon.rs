@@ -0,0 +1,486 @@
+//! Dungeon generators. Each one fills a grid of walls with floor, leaves a
+//! wall around the edge, and keeps every floor tile reachable from every
+//! other. `TileGrid::place_stairs` then links the level to the ones above
+//! and below.
+
+use super::rng::Rng;
+use super::GridPoint;
+use std::collections::VecDeque;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Tile {
+    Wall,
+    Floor,
+    UpStairs,
+    DownStairs,
+}
+
+impl Tile {
+    /// How the tile is drawn, or `None` for wall.
+    pub fn glyph(self) -> Option<&'static str> {
+        match self {
+            Tile::Wall => None,
+            Tile::Floor => Some("."),
+            Tile::UpStairs => Some("<"),
+            Tile::DownStairs => Some(">"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TileGrid {
+    width: i32,
+    height: i32,
+    tiles: Vec<Tile>,
+}
+
+impl TileGrid {
+    /// A grid that is all wall.
+    pub fn new(width: i32, height: i32) -> TileGrid {
+        let width = width.max(0);
+        let height = height.max(0);
+
+        TileGrid {
+            width,
+            height,
+            tiles: vec![Tile::Wall; (width * height) as usize],
+        }
+    }
+
+    pub fn in_bounds(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && x < self.width && y < self.height
+    }
+
+    /// The tile at `(x, y)`. Everything off the grid is wall.
+    pub fn get(&self, x: i32, y: i32) -> Tile {
+        if self.in_bounds(x, y) {
+            self.tiles[(y * self.width + x) as usize]
+        } else {
+            Tile::Wall
+        }
+    }
+
+    pub fn set(&mut self, x: i32, y: i32, tile: Tile) {
+        if self.in_bounds(x, y) {
+            self.tiles[(y * self.width + x) as usize] = tile;
+        }
+    }
+
+    /// Every floor tile, row by row.
+    pub fn floor_cells(&self) -> Vec<GridPoint> {
+        let mut cells = Vec::new();
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if self.get(x, y) == Tile::Floor {
+                    cells.push(GridPoint { x, y });
+                }
+            }
+        }
+
+        cells
+    }
+
+    /// Every tile that isn't wall, with its position, row by row.
+    pub fn open_cells(&self) -> Vec<(GridPoint, Tile)> {
+        let mut cells = Vec::new();
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let tile = self.get(x, y);
+                if tile != Tile::Wall {
+                    cells.push((GridPoint { x, y }, tile));
+                }
+            }
+        }
+
+        cells
+    }
+
+    /// Turns a random floor tile into the up stairs, and the floor tile
+    /// farthest from it into the down stairs, so each level has to be
+    /// crossed. Returns where they went, or `None` if there isn't room.
+    pub fn place_stairs(&mut self, rng: &mut Rng) -> Option<(GridPoint, GridPoint)> {
+        let cells = self.floor_cells();
+        if cells.len() < 2 {
+            return None;
+        }
+
+        let up = cells[rng.below(cells.len())].clone();
+        let down = cells
+            .iter()
+            .max_by_key(|cell| (cell.x - up.x).abs() + (cell.y - up.y).abs())?
+            .clone();
+
+        self.set(up.x, up.y, Tile::UpStairs);
+        self.set(down.x, down.y, Tile::DownStairs);
+
+        Some((up, down))
+    }
+
+    /// Sets floor, keeping the outer edge as wall.
+    fn carve(&mut self, x: i32, y: i32) {
+        if x > 0 && y > 0 && x < self.width - 1 && y < self.height - 1 {
+            self.set(x, y, Tile::Floor);
+        }
+    }
+
+    fn carve_room(&mut self, room: &Rect) {
+        for y in room.y..room.y + room.height {
+            for x in room.x..room.x + room.width {
+                self.carve(x, y);
+            }
+        }
+    }
+
+    /// Carves an L-shaped corridor, turning at a random corner.
+    fn carve_corridor(&mut self, from: &GridPoint, to: &GridPoint, rng: &mut Rng) {
+        let corner = if rng.chance(0.5) {
+            GridPoint { x: to.x, y: from.y }
+        } else {
+            GridPoint { x: from.x, y: to.y }
+        };
+
+        self.carve_line(from, &corner);
+        self.carve_line(&corner, to);
+    }
+
+    /// Carves a horizontal or vertical line.
+    fn carve_line(&mut self, from: &GridPoint, to: &GridPoint) {
+        for y in from.y.min(to.y)..=from.y.max(to.y) {
+            for x in from.x.min(to.x)..=from.x.max(to.x) {
+                self.carve(x, y);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Rect {
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+}
+
+impl Rect {
+    fn center(&self) -> GridPoint {
+        GridPoint {
+            x: self.x + self.width / 2,
+            y: self.y + self.height / 2,
+        }
+    }
+
+    /// True if the rectangles overlap or are closer than `margin` tiles.
+    fn near(&self, other: &Rect, margin: i32) -> bool {
+        self.x - margin < other.x + other.width
+            && other.x - margin < self.x + self.width
+            && self.y - margin < other.y + other.height
+            && other.y - margin < self.y + self.height
+    }
+}
+
+const ROOM_MIN: i32 = 4;
+const ROOM_MAX: i32 = 12;
+const MAX_ROOMS: usize = 20;
+const ROOM_ATTEMPTS: usize = 200;
+
+/// Scatters rooms that don't touch, joining each to the one placed before it.
+pub fn rooms_and_corridors(width: i32, height: i32, rng: &mut Rng) -> TileGrid {
+    let mut grid = TileGrid::new(width, height);
+    let mut rooms: Vec<Rect> = Vec::new();
+
+    for _ in 0..ROOM_ATTEMPTS {
+        if rooms.len() == MAX_ROOMS {
+            break;
+        }
+
+        let room_width = rng.range(ROOM_MIN, ROOM_MAX + 1).min(width - 2);
+        let room_height = rng.range(ROOM_MIN, ROOM_MAX / 2 + 1).min(height - 2);
+        if room_width < 1 || room_height < 1 {
+            break;
+        }
+
+        let room = Rect {
+            x: rng.range(1, width - room_width),
+            y: rng.range(1, height - room_height),
+            width: room_width,
+            height: room_height,
+        };
+        if rooms.iter().any(|other| room.near(other, 2)) {
+            continue;
+        }
+
+        grid.carve_room(&room);
+        if let Some(previous) = rooms.last() {
+            grid.carve_corridor(&previous.center(), &room.center(), rng);
+        }
+        rooms.push(room);
+    }
+
+    grid
+}
+
+const LEAF_MIN: i32 = 8;
+
+/// Splits the map in two again and again, puts a room in each piece, and
+/// joins the pieces back up the tree.
+pub fn bsp(width: i32, height: i32, rng: &mut Rng) -> TileGrid {
+    let mut grid = TileGrid::new(width, height);
+    let area = Rect {
+        x: 1,
+        y: 1,
+        width: width - 2,
+        height: height - 2,
+    };
+
+    if area.width > 0 && area.height > 0 {
+        split(&mut grid, &area, rng);
+    }
+
+    grid
+}
+
+/// Fills `area` and returns a point inside one of its rooms.
+fn split(grid: &mut TileGrid, area: &Rect, rng: &mut Rng) -> GridPoint {
+    let can_split_x = area.width >= LEAF_MIN * 2;
+    let can_split_y = area.height >= LEAF_MIN * 2;
+
+    let split_x = match (can_split_x, can_split_y) {
+        (false, false) => return place_room(grid, area, rng),
+        (true, false) => true,
+        (false, true) => false,
+        // Prefer cutting the long side, so pieces stay roughly square.
+        (true, true) if area.width > area.height * 2 => true,
+        (true, true) if area.height > area.width * 2 => false,
+        (true, true) => rng.chance(0.5),
+    };
+
+    let (first, second) = if split_x {
+        let cut = rng.range(LEAF_MIN, area.width - LEAF_MIN + 1);
+        (
+            Rect {
+                width: cut,
+                ..area.clone()
+            },
+            Rect {
+                x: area.x + cut,
+                width: area.width - cut,
+                ..area.clone()
+            },
+        )
+    } else {
+        let cut = rng.range(LEAF_MIN, area.height - LEAF_MIN + 1);
+        (
+            Rect {
+                height: cut,
+                ..area.clone()
+            },
+            Rect {
+                y: area.y + cut,
+                height: area.height - cut,
+                ..area.clone()
+            },
+        )
+    };
+
+    let a = split(grid, &first, rng);
+    let b = split(grid, &second, rng);
+    grid.carve_corridor(&a, &b, rng);
+
+    if rng.chance(0.5) {
+        a
+    } else {
+        b
+    }
+}
+
+/// Carves a room inside a leaf, leaving a wall on its right and bottom so
+/// neighbouring rooms don't merge.
+fn place_room(grid: &mut TileGrid, leaf: &Rect, rng: &mut Rng) -> GridPoint {
+    let max_width = (leaf.width - 1).max(1);
+    let max_height = (leaf.height - 1).max(1);
+    let width = rng.range(ROOM_MIN.min(max_width), max_width + 1);
+    let height = rng.range(ROOM_MIN.min(max_height), max_height + 1);
+
+    let room = Rect {
+        x: rng.range(leaf.x, leaf.x + max_width - width + 1),
+        y: rng.range(leaf.y, leaf.y + max_height - height + 1),
+        width,
+        height,
+    };
+    grid.carve_room(&room);
+
+    room.center()
+}
+
+const CAVE_FILL: f64 = 0.45;
+const CAVE_STEPS: usize = 5;
+
+/// Fills the map with noise and smooths it into caves, keeping only the
+/// largest cave.
+pub fn caves(width: i32, height: i32, rng: &mut Rng) -> TileGrid {
+    let mut grid = TileGrid::new(width, height);
+
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            if !rng.chance(CAVE_FILL) {
+                grid.set(x, y, Tile::Floor);
+            }
+        }
+    }
+
+    for _ in 0..CAVE_STEPS {
+        let mut next = TileGrid::new(width, height);
+
+        for y in 1..height - 1 {
+            for x in 1..width - 1 {
+                if walls_around(&grid, x, y) < 5 {
+                    next.set(x, y, Tile::Floor);
+                }
+            }
+        }
+
+        grid = next;
+    }
+
+    keep_largest_region(&mut grid);
+    grid
+}
+
+/// Counts walls in the 3x3 block centred on `(x, y)`.
+fn walls_around(grid: &TileGrid, x: i32, y: i32) -> usize {
+    let mut walls = 0;
+
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            if grid.get(x + dx, y + dy) == Tile::Wall {
+                walls += 1;
+            }
+        }
+    }
+
+    walls
+}
+
+/// Walls off every floor region but the biggest.
+fn keep_largest_region(grid: &mut TileGrid) {
+    let mut region = vec![usize::MAX; grid.tiles.len()];
+    let mut sizes = Vec::new();
+
+    for start in grid.floor_cells() {
+        let index = (start.y * grid.width + start.x) as usize;
+        if region[index] != usize::MAX {
+            continue;
+        }
+
+        let id = sizes.len();
+        let mut size = 0;
+        let mut queue = VecDeque::new();
+        region[index] = id;
+        queue.push_back(start);
+
+        while let Some(point) = queue.pop_front() {
+            size += 1;
+
+            for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+                let (x, y) = (point.x + dx, point.y + dy);
+                if grid.get(x, y) != Tile::Floor {
+                    continue;
+                }
+
+                let index = (y * grid.width + x) as usize;
+                if region[index] == usize::MAX {
+                    region[index] = id;
+                    queue.push_back(GridPoint { x, y });
+                }
+            }
+        }
+
+        sizes.push(size);
+    }
+
+    let largest = (0..sizes.len()).max_by_key(|&id| sizes[id]);
+
+    for (index, tile) in grid.tiles.iter_mut().enumerate() {
+        if *tile == Tile::Floor && Some(region[index]) != largest {
+            *tile = Tile::Wall;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::rng::Rng;
+    use super::{bsp, caves, rooms_and_corridors, Tile, TileGrid};
+    use std::collections::HashSet;
+
+    fn connected(grid: &TileGrid) -> bool {
+        let cells = grid.floor_cells();
+        let mut seen = HashSet::new();
+        let mut stack = vec![cells[0].clone()];
+
+        while let Some(point) = stack.pop() {
+            if !seen.insert(point.clone()) {
+                continue;
+            }
+            for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+                let next = super::GridPoint {
+                    x: point.x + dx,
+                    y: point.y + dy,
+                };
+                if grid.get(next.x, next.y) == Tile::Floor {
+                    stack.push(next);
+                }
+            }
+        }
+
+        seen.len() == cells.len()
+    }
+
+    #[test]
+    fn generators_make_closed_connected_maps() {
+        let generators: [fn(i32, i32, &mut Rng) -> TileGrid; 3] = [rooms_and_corridors, bsp, caves];
+
+        for generate in generators {
+            for seed in 0..10 {
+                let grid = generate(80, 40, &mut Rng::new(seed));
+
+                assert!(grid.floor_cells().len() > 100);
+                assert!(connected(&grid));
+                for x in 0..80 {
+                    assert_eq!(grid.get(x, 0), Tile::Wall);
+                    assert_eq!(grid.get(x, 39), Tile::Wall);
+                }
+                for y in 0..40 {
+                    assert_eq!(grid.get(0, y), Tile::Wall);
+                    assert_eq!(grid.get(79, y), Tile::Wall);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn stairs_are_far_apart_on_the_floor() {
+        let mut grid = rooms_and_corridors(80, 40, &mut Rng::new(3));
+        let floor = grid.floor_cells().len();
+        let (up, down) = grid.place_stairs(&mut Rng::new(3)).unwrap();
+
+        assert_eq!(grid.get(up.x, up.y), Tile::UpStairs);
+        assert_eq!(grid.get(down.x, down.y), Tile::DownStairs);
+        assert_eq!(grid.floor_cells().len(), floor - 2);
+        assert_eq!(grid.open_cells().len(), floor);
+        assert!((down.x - up.x).abs() + (down.y - up.y).abs() > 20);
+        assert!(TileGrid::new(3, 3).place_stairs(&mut Rng::new(3)).is_none());
+    }
+
+    #[test]
+    fn seeds_repeat() {
+        let first = caves(60, 30, &mut Rng::new(7)).floor_cells();
+        let second = caves(60, 30, &mut Rng::new(7)).floor_cells();
+        let other = caves(60, 30, &mut Rng::new(8)).floor_cells();
+
+        assert_eq!(first, second);
+        assert_ne!(first, other);
+    }
+}
@@ -0,0 +1,368 @@
+//! Entities and their components. An entity is just an id; what it is
+//! depends on which components it has, so a monster, the player and an
+//! item on the floor are all stored the same way.
+//!
+//! Components are kept in ordered maps so that systems always visit
+//! entities in the same order, which keeps seeded games repeatable.
+
+use super::combat::CombatStats;
+use super::items::ItemKind;
+use super::GridPoint;
+use std::collections::{BTreeMap, BTreeSet};
+
+pub type Entity = u32;
+
+/// Drawing order: higher layers are drawn over lower ones.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+pub enum Layer {
+    Item,
+    Monster,
+    Player,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Renderable {
+    pub icon: String,
+    pub color: String,
+    pub layer: Layer,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Health {
+    pub hitpoints: i32,
+    pub max_hitpoints: i32,
+}
+
+impl Health {
+    pub fn new(hitpoints: i32) -> Health {
+        Health {
+            hitpoints,
+            max_hitpoints: hitpoints,
+        }
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.hitpoints <= 0
+    }
+}
+
+/// How a monster picks its moves.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Ai {
+    /// Heads straight for the player.
+    Chase,
+    /// Chases the player, but runs once badly hurt.
+    Cowardly,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Inventory {
+    pub items: Vec<Entity>,
+    pub capacity: usize,
+}
+
+/// What a fighter has equipped, out of the items in their inventory.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Equipment {
+    pub weapon: Option<Entity>,
+    pub armor: Option<Entity>,
+}
+
+impl Equipment {
+    pub fn is_equipped(&self, item: Entity) -> bool {
+        self.weapon == Some(item) || self.armor == Some(item)
+    }
+
+    pub fn unequip(&mut self, item: Entity) {
+        if self.weapon == Some(item) {
+            self.weapon = None;
+        }
+        if self.armor == Some(item) {
+            self.armor = None;
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct World {
+    next_entity: Entity,
+    pub names: BTreeMap<Entity, String>,
+    pub positions: BTreeMap<Entity, GridPoint>,
+    pub renderables: BTreeMap<Entity, Renderable>,
+    pub healths: BTreeMap<Entity, Health>,
+    pub combat: BTreeMap<Entity, CombatStats>,
+    pub ais: BTreeMap<Entity, Ai>,
+    pub inventories: BTreeMap<Entity, Inventory>,
+    /// Entities nothing else can share a cell with.
+    pub blocking: BTreeSet<Entity>,
+    /// Boxes the player can open.
+    pub containers: BTreeSet<Entity>,
+    /// Containers that need a key.
+    pub locked: BTreeSet<Entity>,
+    pub items: BTreeMap<Entity, ItemKind>,
+    pub equipment: BTreeMap<Entity, Equipment>,
+}
+
+impl World {
+    pub fn new() -> World {
+        World::default()
+    }
+
+    /// A new entity with no components.
+    pub fn spawn(&mut self) -> Entity {
+        let entity = self.next_entity;
+        self.next_entity += 1;
+        entity
+    }
+
+    /// Removes an entity and all its components.
+    pub fn despawn(&mut self, entity: Entity) {
+        self.names.remove(&entity);
+        self.positions.remove(&entity);
+        self.renderables.remove(&entity);
+        self.healths.remove(&entity);
+        self.combat.remove(&entity);
+        self.ais.remove(&entity);
+        self.inventories.remove(&entity);
+        self.blocking.remove(&entity);
+        self.containers.remove(&entity);
+        self.locked.remove(&entity);
+        self.items.remove(&entity);
+        self.equipment.remove(&entity);
+    }
+
+    pub fn clear(&mut self) {
+        *self = World {
+            next_entity: self.next_entity,
+            ..World::default()
+        };
+    }
+
+    /// The blocking entity standing on `point`, if any.
+    pub fn blocker_at(&self, point: &GridPoint) -> Option<Entity> {
+        self.blocking
+            .iter()
+            .copied()
+            .find(|entity| self.positions.get(entity) == Some(point))
+    }
+
+    /// The container on `point`, if any.
+    pub fn container_at(&self, point: &GridPoint) -> Option<Entity> {
+        self.containers
+            .iter()
+            .copied()
+            .find(|entity| self.positions.get(entity) == Some(point))
+    }
+
+    /// The items lying on `point`.
+    pub fn items_at(&self, point: &GridPoint) -> Vec<Entity> {
+        self.items
+            .keys()
+            .copied()
+            .filter(|entity| self.positions.get(entity) == Some(point))
+            .collect()
+    }
+
+    /// The entity on `point` that should be drawn, if any.
+    pub fn renderable_at(&self, point: &GridPoint) -> Option<&Renderable> {
+        self.positions
+            .iter()
+            .filter(|(_, position)| *position == point)
+            .filter_map(|(entity, _)| self.renderables.get(entity))
+            .max_by_key(|renderable| renderable.layer)
+    }
+
+    pub fn name(&self, entity: Entity) -> &str {
+        self.names.get(&entity).map_or("something", String::as_str)
+    }
+
+    /// Puts `item` in `owner`'s pack, off the floor. Returns false if the
+    /// pack is full.
+    pub fn give(&mut self, owner: Entity, item: Entity) -> bool {
+        let inventory = match self.inventories.get_mut(&owner) {
+            Some(inventory) if inventory.items.len() < inventory.capacity => inventory,
+            _ => return false,
+        };
+
+        inventory.items.push(item);
+        self.positions.remove(&item);
+        true
+    }
+
+    /// Takes `item` out of `owner`'s pack, unequipping it.
+    pub fn remove_from_inventory(&mut self, owner: Entity, item: Entity) {
+        if let Some(inventory) = self.inventories.get_mut(&owner) {
+            inventory.items.retain(|&carried| carried != item);
+        }
+        if let Some(equipment) = self.equipment.get_mut(&owner) {
+            equipment.unequip(item);
+        }
+    }
+
+    /// The item in `owner`'s pack at `slot`.
+    pub fn carried(&self, owner: Entity, slot: usize) -> Option<Entity> {
+        self.inventories.get(&owner)?.items.get(slot).copied()
+    }
+
+    /// The first item in `owner`'s pack that matches.
+    pub fn find_carried(
+        &self,
+        owner: Entity,
+        matches: impl Fn(ItemKind) -> bool,
+    ) -> Option<Entity> {
+        self.inventories
+            .get(&owner)?
+            .items
+            .iter()
+            .copied()
+            .find(|item| self.items.get(item).is_some_and(|&kind| matches(kind)))
+    }
+
+    /// Unlocks `container` with one of `owner`'s keys, which is used up.
+    /// Returns false if it's locked and `owner` has no key.
+    pub fn unlock(&mut self, owner: Entity, container: Entity) -> bool {
+        if !self.locked.contains(&container) {
+            return true;
+        }
+
+        match self.find_carried(owner, |kind| kind == ItemKind::Key) {
+            Some(key) => {
+                self.remove_from_inventory(owner, key);
+                self.despawn(key);
+                self.locked.remove(&container);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::items::ItemKind;
+    use super::super::GridPoint;
+    use super::{Entity, Equipment, Health, Inventory, Layer, Renderable, World};
+
+    fn renderable(icon: &str, layer: Layer) -> Renderable {
+        Renderable {
+            icon: icon.to_owned(),
+            color: "#fff".to_owned(),
+            layer,
+        }
+    }
+
+    /// An entity carrying nothing, with room for `capacity` items.
+    fn carrier(world: &mut World, capacity: usize) -> Entity {
+        let carrier = world.spawn();
+        world.inventories.insert(
+            carrier,
+            Inventory {
+                items: Vec::new(),
+                capacity,
+            },
+        );
+        world.equipment.insert(carrier, Equipment::default());
+        carrier
+    }
+
+    fn item(world: &mut World, kind: ItemKind, at: GridPoint) -> Entity {
+        let item = world.spawn();
+        world.items.insert(item, kind);
+        world.positions.insert(item, at);
+        item
+    }
+
+    #[test]
+    fn packs_hold_up_to_their_capacity() {
+        let mut world = World::new();
+        let here = GridPoint { x: 1, y: 1 };
+        let player = carrier(&mut world, 1);
+        let first = item(&mut world, ItemKind::Key, here.clone());
+        let second = item(&mut world, ItemKind::Key, here.clone());
+
+        assert!(world.give(player, first));
+        assert!(!world.give(player, second));
+        assert_eq!(world.inventories[&player].items, vec![first]);
+        assert_eq!(world.items_at(&here), vec![second]);
+        assert_eq!(world.carried(player, 0), Some(first));
+        assert_eq!(world.carried(player, 1), None);
+    }
+
+    #[test]
+    fn dropping_an_item_unequips_it() {
+        let mut world = World::new();
+        let player = carrier(&mut world, 5);
+        let sword = item(
+            &mut world,
+            ItemKind::Weapon {
+                attack: 2,
+                min_damage: 3,
+                max_damage: 6,
+            },
+            GridPoint { x: 1, y: 1 },
+        );
+
+        assert!(world.give(player, sword));
+        world.equipment.get_mut(&player).unwrap().weapon = Some(sword);
+        world.remove_from_inventory(player, sword);
+
+        assert!(world.inventories[&player].items.is_empty());
+        assert_eq!(world.equipment[&player], Equipment::default());
+    }
+
+    #[test]
+    fn locked_boxes_use_up_a_key() {
+        let mut world = World::new();
+        let here = GridPoint { x: 1, y: 1 };
+        let player = carrier(&mut world, 5);
+        let key = item(&mut world, ItemKind::Key, here.clone());
+        let boxes = [world.spawn(), world.spawn()];
+        for container in boxes {
+            world.containers.insert(container);
+            world.locked.insert(container);
+        }
+
+        assert!(world.give(player, key));
+        assert!(world.unlock(player, boxes[0]));
+        assert!(!world.locked.contains(&boxes[0]));
+        assert!(world.inventories[&player].items.is_empty());
+        assert!(!world.items.contains_key(&key));
+
+        assert!(!world.unlock(player, boxes[1]));
+        assert!(world.locked.contains(&boxes[1]));
+        // Unlocked boxes need no key.
+        assert!(world.unlock(player, boxes[0]));
+    }
+
+    #[test]
+    fn entities_are_their_components() {
+        let mut world = World::new();
+        let here = GridPoint { x: 3, y: 4 };
+
+        let potion = world.spawn();
+        world.positions.insert(potion, here.clone());
+        world
+            .renderables
+            .insert(potion, renderable("!", Layer::Item));
+
+        assert_eq!(world.blocker_at(&here), None);
+        assert_eq!(world.renderable_at(&here).unwrap().icon, "!");
+
+        let rat = world.spawn();
+        world.positions.insert(rat, here.clone());
+        world
+            .renderables
+            .insert(rat, renderable("r", Layer::Monster));
+        world.healths.insert(rat, Health::new(5));
+        world.blocking.insert(rat);
+
+        assert_eq!(world.blocker_at(&here), Some(rat));
+        assert_eq!(world.renderable_at(&here).unwrap().icon, "r");
+
+        world.despawn(rat);
+        assert!(!world.positions.contains_key(&rat));
+        assert!(world.healths.is_empty());
+        assert_eq!(world.renderable_at(&here).unwrap().icon, "!");
+        assert_ne!(world.spawn(), rat);
+    }
+}
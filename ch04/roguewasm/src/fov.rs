@@ -0,0 +1,128 @@
+//! Field of view by recursive shadowcasting.
+
+use super::GridPoint;
+use std::collections::HashSet;
+
+/// How each of the eight octants maps onto the grid.
+const OCTANTS: [[i32; 4]; 8] = [
+    [1, 0, 0, 1],
+    [0, 1, 1, 0],
+    [0, -1, 1, 0],
+    [-1, 0, 0, 1],
+    [-1, 0, 0, -1],
+    [0, -1, -1, 0],
+    [0, 1, -1, 0],
+    [1, 0, 0, -1],
+];
+
+/// The cells visible from `origin` within `radius`. Cells that block sight
+/// are visible themselves but hide what's behind them.
+pub fn visible_cells<F>(origin: &GridPoint, radius: i32, blocks: F) -> HashSet<GridPoint>
+where
+    F: Fn(i32, i32) -> bool,
+{
+    let mut visible = HashSet::new();
+    visible.insert(origin.clone());
+
+    for octant in &OCTANTS {
+        let mut caster = Caster {
+            origin,
+            radius,
+            octant,
+            blocks: &blocks,
+            visible: &mut visible,
+        };
+        caster.cast(1, 1.0, 0.0);
+    }
+
+    visible
+}
+
+struct Caster<'a, F> {
+    origin: &'a GridPoint,
+    radius: i32,
+    octant: &'a [i32; 4],
+    blocks: &'a F,
+    visible: &'a mut HashSet<GridPoint>,
+}
+
+impl<'a, F: Fn(i32, i32) -> bool> Caster<'a, F> {
+    /// Scans rows outward from `row`, lighting cells between the `start`
+    /// and `end` slopes and recursing around anything that blocks.
+    fn cast(&mut self, row: i32, mut start: f64, end: f64) {
+        if start < end {
+            return;
+        }
+
+        let [xx, xy, yx, yy] = *self.octant;
+        let mut next_start = start;
+
+        for distance in row..=self.radius {
+            let dy = -distance;
+            let mut blocked = false;
+
+            for dx in -distance..=0 {
+                let left = (dx as f64 - 0.5) / (dy as f64 + 0.5);
+                let right = (dx as f64 + 0.5) / (dy as f64 - 0.5);
+                if start < right {
+                    continue;
+                }
+                if end > left {
+                    break;
+                }
+
+                let x = self.origin.x + dx * xx + dy * xy;
+                let y = self.origin.y + dx * yx + dy * yy;
+                if dx * dx + dy * dy <= self.radius * self.radius {
+                    self.visible.insert(GridPoint { x, y });
+                }
+
+                let opaque = (self.blocks)(x, y);
+                if blocked {
+                    if opaque {
+                        next_start = right;
+                    } else {
+                        blocked = false;
+                        start = next_start;
+                    }
+                } else if opaque && distance < self.radius {
+                    blocked = true;
+                    self.cast(distance + 1, start, left);
+                    next_start = right;
+                }
+            }
+
+            if blocked {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::GridPoint;
+    use super::visible_cells;
+
+    #[test]
+    fn sees_a_radius_in_the_open() {
+        let origin = GridPoint { x: 0, y: 0 };
+        let visible = visible_cells(&origin, 3, |_, _| false);
+
+        assert!(visible.contains(&GridPoint { x: 3, y: 0 }));
+        assert!(visible.contains(&GridPoint { x: -2, y: 2 }));
+        assert!(!visible.contains(&GridPoint { x: 4, y: 0 }));
+        assert!(!visible.contains(&GridPoint { x: 3, y: 3 }));
+    }
+
+    #[test]
+    fn walls_cast_shadows() {
+        let origin = GridPoint { x: 0, y: 0 };
+        let visible = visible_cells(&origin, 8, |x, y| x == 2 && y == 0);
+
+        assert!(visible.contains(&GridPoint { x: 2, y: 0 }));
+        assert!(!visible.contains(&GridPoint { x: 3, y: 0 }));
+        assert!(!visible.contains(&GridPoint { x: 6, y: 0 }));
+        assert!(visible.contains(&GridPoint { x: 6, y: 4 }));
+    }
+}
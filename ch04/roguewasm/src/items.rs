@@ -0,0 +1,214 @@
+//! The items the dungeon can hold, and the loot tables boxes roll on.
+
+use super::rng::Rng;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum ItemKind {
+    /// Restores this many hitpoints.
+    HealingPotion(i32),
+    /// Doubles the drinker's speed for this many of their turns.
+    HastePotion(i32),
+    Weapon {
+        attack: i32,
+        min_damage: i32,
+        max_damage: i32,
+    },
+    Armor {
+        defense: i32,
+    },
+    /// Opens a locked box.
+    Key,
+}
+
+impl ItemKind {
+    pub fn category(self) -> &'static str {
+        match self {
+            ItemKind::HealingPotion(_) | ItemKind::HastePotion(_) => "potion",
+            ItemKind::Weapon { .. } => "weapon",
+            ItemKind::Armor { .. } => "armor",
+            ItemKind::Key => "key",
+        }
+    }
+
+    /// A short summary of what the item does.
+    pub fn describe(self) -> String {
+        match self {
+            ItemKind::HealingPotion(amount) => format!("heals {}", amount),
+            ItemKind::HastePotion(turns) => format!("haste for {} turns", turns),
+            ItemKind::Weapon {
+                attack,
+                min_damage,
+                max_damage,
+            } => format!("+{} attack, {}-{} damage", attack, min_damage, max_damage),
+            ItemKind::Armor { defense } => format!("+{} defense", defense),
+            ItemKind::Key => "opens a locked box".to_owned(),
+        }
+    }
+}
+
+pub struct ItemTemplate {
+    pub name: &'static str,
+    pub icon: &'static str,
+    pub color: &'static str,
+    pub kind: ItemKind,
+}
+
+pub const ITEMS: &[ItemTemplate] = &[
+    ItemTemplate {
+        name: "healing potion",
+        icon: "!",
+        color: "#ff69b4",
+        kind: ItemKind::HealingPotion(30),
+    },
+    ItemTemplate {
+        name: "greater healing potion",
+        icon: "!",
+        color: "#ff1493",
+        kind: ItemKind::HealingPotion(70),
+    },
+    ItemTemplate {
+        name: "haste potion",
+        icon: "!",
+        color: "#00bfff",
+        kind: ItemKind::HastePotion(20),
+    },
+    ItemTemplate {
+        name: "dagger",
+        icon: "/",
+        color: "#c0c0c0",
+        kind: ItemKind::Weapon {
+            attack: 1,
+            min_damage: 2,
+            max_damage: 6,
+        },
+    },
+    ItemTemplate {
+        name: "sword",
+        icon: "/",
+        color: "#e0e0ff",
+        kind: ItemKind::Weapon {
+            attack: 2,
+            min_damage: 4,
+            max_damage: 10,
+        },
+    },
+    ItemTemplate {
+        name: "war axe",
+        icon: "/",
+        color: "#ffa500",
+        kind: ItemKind::Weapon {
+            attack: 3,
+            min_damage: 6,
+            max_damage: 14,
+        },
+    },
+    ItemTemplate {
+        name: "leather armor",
+        icon: "[",
+        color: "#8b4513",
+        kind: ItemKind::Armor { defense: 1 },
+    },
+    ItemTemplate {
+        name: "chain mail",
+        icon: "[",
+        color: "#a9a9a9",
+        kind: ItemKind::Armor { defense: 3 },
+    },
+    ItemTemplate {
+        name: "plate armor",
+        icon: "[",
+        color: "#f0f0f0",
+        kind: ItemKind::Armor { defense: 5 },
+    },
+    ItemTemplate {
+        name: "key",
+        icon: "-",
+        color: "#ffd700",
+        kind: ItemKind::Key,
+    },
+];
+
+pub fn find(name: &str) -> Option<&'static ItemTemplate> {
+    ITEMS.iter().find(|item| item.name == name)
+}
+
+/// What a box holds.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Loot {
+    Nothing,
+    /// Hurts whoever opens the box by between the two amounts.
+    Trap(i32, i32),
+    Item(&'static str),
+}
+
+/// Loot and its relative weight.
+pub type LootTable = &'static [(Loot, u32)];
+
+pub const BOX_LOOT: LootTable = &[
+    (Loot::Nothing, 10),
+    (Loot::Trap(20, 40), 15),
+    (Loot::Item("healing potion"), 20),
+    (Loot::Item("greater healing potion"), 5),
+    (Loot::Item("haste potion"), 8),
+    (Loot::Item("dagger"), 8),
+    (Loot::Item("sword"), 5),
+    (Loot::Item("war axe"), 2),
+    (Loot::Item("leather armor"), 8),
+    (Loot::Item("chain mail"), 4),
+    (Loot::Item("plate armor"), 1),
+    (Loot::Item("key"), 10),
+];
+
+/// Locked boxes hold better things.
+pub const LOCKED_BOX_LOOT: LootTable = &[
+    (Loot::Item("greater healing potion"), 10),
+    (Loot::Item("haste potion"), 6),
+    (Loot::Item("sword"), 6),
+    (Loot::Item("war axe"), 4),
+    (Loot::Item("chain mail"), 6),
+    (Loot::Item("plate armor"), 3),
+];
+
+pub fn roll(table: LootTable, rng: &mut Rng) -> Loot {
+    let total: u32 = table.iter().map(|(_, weight)| weight).sum();
+    if total == 0 {
+        return Loot::Nothing;
+    }
+
+    let mut pick = rng.below(total as usize) as u32;
+    for (loot, weight) in table {
+        if pick < *weight {
+            return *loot;
+        }
+        pick -= weight;
+    }
+
+    Loot::Nothing
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::rng::Rng;
+    use super::{find, roll, Loot, BOX_LOOT, LOCKED_BOX_LOOT};
+
+    #[test]
+    fn loot_tables_name_real_items() {
+        for (loot, _) in BOX_LOOT.iter().chain(LOCKED_BOX_LOOT) {
+            if let Loot::Item(name) = loot {
+                assert!(find(name).is_some(), "no item called {}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn rolls_follow_the_weights() {
+        let table = &[(Loot::Nothing, 1), (Loot::Item("key"), 3)];
+        let mut rng = Rng::new(5);
+        let keys = (0..4000)
+            .filter(|_| roll(table, &mut rng) == Loot::Item("key"))
+            .count();
+
+        assert!(keys > 2800 && keys < 3200);
+        assert_eq!(roll(&[], &mut rng), Loot::Nothing);
+    }
+}
@@ -0,0 +1,154 @@
+//! Levels of the dungeon. The level the player is on keeps its entities in
+//! `GameEngine::world` and its turn order in `GameEngine::scheduler`;
+//! leaving packs them into its `Level` and coming back unpacks them, so
+//! everything stays where it was left.
+
+use super::dungeon::{Tile, TileGrid};
+use super::ecs::{Entity, World};
+use super::path::Topology;
+use super::scheduler::Scheduler;
+use super::GridPoint;
+use std::collections::{BTreeMap, HashSet};
+
+/// Boxes on every level.
+pub const BOX_COUNT: usize = 10;
+const BASE_MONSTER_COUNT: usize = 6;
+const MONSTERS_PER_DEPTH: usize = 2;
+/// Each level down adds this chance that a box rolls on the locked box
+/// table, up to `MAX_DEEP_LOOT_CHANCE`.
+const DEEP_LOOT_CHANCE_PER_DEPTH: f64 = 0.1;
+const MAX_DEEP_LOOT_CHANCE: f64 = 0.5;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Level {
+    pub map: TileGrid,
+    /// Cells that have been in sight at some point.
+    pub seen: HashSet<GridPoint>,
+    /// Floor cells with nothing placed on them yet, row by row.
+    pub free_cells: Vec<GridPoint>,
+    pub up_stairs: Option<GridPoint>,
+    pub down_stairs: Option<GridPoint>,
+    /// Where everything on the level stood when the player left.
+    pub positions: BTreeMap<Entity, GridPoint>,
+    /// The turn order of the monsters on the level.
+    pub scheduler: Scheduler,
+}
+
+impl Level {
+    /// Packs the level away as `player` leaves it: everything else placed
+    /// in `world`, and the turn order in `scheduler`, move into the level.
+    pub fn pack(&mut self, world: &mut World, scheduler: &mut Scheduler, player: Entity) {
+        scheduler.remove(player);
+        self.scheduler = std::mem::take(scheduler);
+        self.positions = std::mem::take(&mut world.positions);
+        self.positions.remove(&player);
+    }
+
+    /// Unpacks a level packed by `pack`, putting its entities back in
+    /// `world` and its turn order in `scheduler`.
+    pub fn unpack(&mut self, world: &mut World, scheduler: &mut Scheduler) {
+        world.positions = std::mem::take(&mut self.positions);
+        *scheduler = std::mem::take(&mut self.scheduler);
+    }
+
+    /// Where to put someone arriving by `stairs`: on them, or beside them
+    /// if something is standing there.
+    pub fn arrival_cell(&self, world: &World, stairs: &GridPoint) -> Option<GridPoint> {
+        let open = |point: &GridPoint| {
+            self.map.get(point.x, point.y) != Tile::Wall && world.blocker_at(point).is_none()
+        };
+        if open(stairs) {
+            return Some(stairs.clone());
+        }
+
+        Topology::Eight.neighbours(stairs).find(|point| open(point))
+    }
+}
+
+/// How many monsters a new level at `depth` starts with.
+pub fn monster_count(depth: u32) -> usize {
+    BASE_MONSTER_COUNT + MONSTERS_PER_DEPTH * depth.saturating_sub(1) as usize
+}
+
+/// The chance that an unlocked box at `depth` holds locked box loot.
+pub fn deep_loot_chance(depth: u32) -> f64 {
+    (DEEP_LOOT_CHANCE_PER_DEPTH * f64::from(depth.saturating_sub(1))).min(MAX_DEEP_LOOT_CHANCE)
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::dungeon::{Tile, TileGrid};
+    use super::super::ecs::World;
+    use super::super::items::ItemKind;
+    use super::super::scheduler::{Scheduler, TURN};
+    use super::super::GridPoint;
+    use super::Level;
+    use std::collections::HashSet;
+
+    #[test]
+    fn levels_keep_their_contents_while_the_player_is_away() {
+        let mut world = World::new();
+        let mut scheduler = Scheduler::new();
+        let player = world.spawn();
+        let monster = world.spawn();
+        let dropped = world.spawn();
+        world.positions.insert(player, GridPoint { x: 1, y: 1 });
+        world.positions.insert(monster, GridPoint { x: 3, y: 1 });
+        world.positions.insert(dropped, GridPoint { x: 2, y: 2 });
+        world.items.insert(dropped, ItemKind::Key);
+        scheduler.add(player, TURN);
+        scheduler.add(monster, TURN);
+        let seen = HashSet::from([GridPoint { x: 1, y: 1 }, GridPoint { x: 2, y: 2 }]);
+        let mut first = Level {
+            seen: seen.clone(),
+            ..Level::default()
+        };
+
+        // Down to a new level, which starts empty...
+        first.pack(&mut world, &mut scheduler, player);
+        assert!(world.positions.is_empty());
+        assert_eq!(scheduler.next(), None);
+
+        // ...and back up.
+        let mut first = first.clone();
+        first.unpack(&mut world, &mut scheduler);
+
+        assert_eq!(first.seen, seen);
+        assert_eq!(
+            world.positions.get(&monster),
+            Some(&GridPoint { x: 3, y: 1 })
+        );
+        assert_eq!(world.items_at(&GridPoint { x: 2, y: 2 }), vec![dropped]);
+        assert!(!world.positions.contains_key(&player));
+        assert_eq!(scheduler.speed(monster), Some(TURN));
+        assert_eq!(scheduler.speed(player), None);
+    }
+
+    #[test]
+    fn arrivals_step_aside_from_blocked_stairs() {
+        let mut map = TileGrid::new(5, 5);
+        map.set(2, 2, Tile::UpStairs);
+        map.set(3, 2, Tile::Floor);
+        let level = Level {
+            map,
+            ..Level::default()
+        };
+        let stairs = GridPoint { x: 2, y: 2 };
+        let mut world = World::new();
+
+        assert_eq!(level.arrival_cell(&world, &stairs), Some(stairs.clone()));
+
+        let monster = world.spawn();
+        world.positions.insert(monster, stairs.clone());
+        world.blocking.insert(monster);
+        assert_eq!(
+            level.arrival_cell(&world, &stairs),
+            Some(GridPoint { x: 3, y: 2 })
+        );
+
+        world.positions.insert(monster, GridPoint { x: 3, y: 2 });
+        let dropped = world.spawn();
+        world.positions.insert(dropped, stairs.clone());
+        assert_eq!(level.arrival_cell(&world, &stairs), Some(stairs));
+    }
+}
@@ -2,10 +2,31 @@
 extern crate serde_derive;
 
 extern crate wasm_bindgen;
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashSet};
 use wasm_bindgen::prelude::*;
 
+mod combat;
+mod dungeon;
+mod ecs;
+mod fov;
+mod items;
+mod level;
+mod monsters;
+mod path;
+mod rng;
+mod save;
+mod scheduler;
+mod systems;
+
+use combat::CombatStats;
+use dungeon::Tile;
+use ecs::{Entity, Equipment, Health, Inventory, Layer, Renderable, World};
+use items::{ItemKind, Loot};
+use level::Level;
+use path::{DijkstraMap, Topology};
+use rng::Rng;
+use scheduler::{Action, Scheduler, TURN};
+
 // Import 'window.alert'
 #[wasm_bindgen]
 extern "C" {
@@ -14,9 +35,7 @@ extern "C" {
     #[wasm_bindgen(js_namespace = console)]
     fn log(message: &str);
 
-    #[wasm_bindgen(module = "./index.html")]
-    fn stats_updated(stats: JsValue);
-
+    #[derive(Clone)]
     pub type Display;
 
     #[wasm_bindgen(method, structural, js_namespace = ROT)]
@@ -24,188 +43,905 @@ extern "C" {
 
     #[wasm_bindgen(method, structural, js_name = draw, js_namespace = ROT)]
     fn draw_color(this: &Display, x: i32, y: i32, character: &str, color: &str);
+
+    #[wasm_bindgen(method, structural, js_namespace = ROT)]
+    fn clear(this: &Display);
+}
+
+#[wasm_bindgen(raw_module = "./index.html")]
+extern "C" {
+    fn stats_updated(stats: JsValue);
 }
 
-#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Hash)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Hash, Serialize, Deserialize)]
 struct GridPoint {
     x: i32,
     y: i32,
 }
 
+#[derive(Serialize)]
+struct InventoryEntry {
+    name: String,
+    icon: String,
+    color: String,
+    category: String,
+    description: String,
+    equipped: bool,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct Stats {
     pub hitpoints: i32,
     pub max_hitpoints: i32,
     pub moves: i32,
+    pub depth: u32,
 }
 
+const PLAYER_HITPOINTS: i32 = 100;
+const PLAYER_COMBAT: CombatStats = CombatStats::new(5, 3, 3, 8);
+const PLAYER_CAPACITY: usize = 10;
+
+const BOX_COLOR: &str = "#fff";
+const LOCKED_BOX_COLOR: &str = "#ffd700";
+/// The chance that a placed box is locked.
+const LOCKED_CHANCE: f64 = 0.2;
+
+const DEFAULT_FOV_RADIUS: i32 = 8;
+/// The color of tiles that have been seen but aren't in view.
+const REMEMBERED_COLOR: &str = "#444";
+
+/// The dungeon generators `GameEngine::generate_map` can use.
 #[wasm_bindgen]
-pub struct Being {
-    location: GridPoint,
-    moves: i32,
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum MapStyle {
+    Rooms,
+    Bsp,
+    Caves,
+}
+
+#[wasm_bindgen]
+pub struct GameEngine {
     display: Display,
-    hitpoints: i32,
-    max_hitpoints: i32,
-    icon: String,
-    color: String,
+    /// The level the player is on. Its entities are in `world` and its
+    /// turn order in `scheduler` until the player leaves.
+    level: Level,
+    /// How far down the player is; the first level is 1.
+    depth: u32,
+    /// The levels the player isn't on, by depth.
+    levels: BTreeMap<u32, Level>,
+    /// The size and style of every level.
+    map_size: (i32, i32),
+    map_style: MapStyle,
+    seed: u32,
+    rng: Rng,
+    fov_radius: i32,
+    /// Cells in the player's sight right now.
+    visible: HashSet<GridPoint>,
+    topology: Topology,
+    scheduler: Scheduler,
+    world: World,
+    player: Option<Entity>,
+    moves: i32,
+    /// Player turns left until haste wears off.
+    haste_turns: i32,
+    game_over: bool,
+    /// Messages for the player, oldest first, until the page takes them.
+    messages: Vec<String>,
 }
 
 #[wasm_bindgen]
-impl Being {
+impl GameEngine {
+    /// Every random choice in the game comes from `seed`, so the same seed
+    /// always plays out the same way given the same input.
     #[wasm_bindgen(constructor)]
-    pub fn new(x: i32, y: i32, icon: &str, color: &str, display: Display) -> Being {
-        Being {
-            location: GridPoint { x, y },
+    pub fn new(display: Display, seed: u32) -> GameEngine {
+        GameEngine {
             display,
+            level: Level::default(),
+            depth: 1,
+            levels: BTreeMap::new(),
+            map_size: (0, 0),
+            map_style: MapStyle::Rooms,
+            haste_turns: 0,
+            seed,
+            rng: Rng::new(u64::from(seed)),
+            fov_radius: DEFAULT_FOV_RADIUS,
+            visible: HashSet::new(),
+            topology: Topology::Four,
+            scheduler: Scheduler::new(),
+            world: World::new(),
+            player: None,
             moves: 0,
-            max_hitpoints: 100,
-            hitpoints: 100,
-            icon: icon.to_owned(),
-            color: color.to_owned(),
+            game_over: false,
+            messages: Vec::new(),
         }
     }
 
-    pub fn x(&self) -> i32 {
-        self.location.x
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    /// Starts a new dungeon of `width` by `height` levels, and generates
+    /// and stocks its first level. Deeper levels are made as the player
+    /// reaches them.
+    pub fn generate_map(&mut self, width: i32, height: i32, style: MapStyle) {
+        self.map_size = (width, height);
+        self.map_style = style;
+        self.depth = 1;
+        self.levels.clear();
+        self.visible.clear();
+        self.world.clear();
+        self.player = None;
+        self.game_over = false;
+        self.haste_turns = 0;
+
+        self.build_level();
     }
 
-    pub fn y(&self) -> i32 {
-        self.location.y
+    /// The whole game, for `load` to pick up again later.
+    pub fn save(&self) -> Vec<u8> {
+        save::encode(&self.saved_game())
     }
 
-    pub fn draw(&self) {
-        &self
-            .display
-            .draw_color(self.location.x, self.location.y, &self.icon, &self.color);
+    /// Replaces the game with one from `save`. Returns false, leaving the
+    /// game as it was, if the bytes aren't a save this version can read.
+    pub fn load(&mut self, bytes: &[u8]) -> bool {
+        match save::decode(bytes) {
+            Ok(game) => {
+                self.restore(game);
+                true
+            }
+            Err(error) => {
+                log(&format!("Couldn't load the saved game: {}", error));
+                false
+            }
+        }
     }
 
-    pub fn move_to(&mut self, x: i32, y: i32) {
-        self.location = GridPoint { x, y };
-        self.draw();
+    /// How far down the player is; the first level is 1.
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
 
-        self.moves += 1;
-        self.emit_stats();
+    /// Takes the down stairs the player is standing on, if it's their
+    /// turn. Returns whether the player moved.
+    pub fn descend(&mut self) -> bool {
+        let player = match self.acting_player() {
+            Some(player) => player,
+            None => return false,
+        };
+        if self.world.positions.get(&player) != self.level.down_stairs.as_ref() {
+            self.log("There are no stairs down here.".to_owned());
+            return false;
+        }
+
+        self.change_level(player, self.depth + 1);
+        self.log(format!("You go down to depth {}.", self.depth));
+
+        true
     }
 
-    pub fn emit_stats(&self) {
-        let stats = Stats {
-            hitpoints: self.hitpoints,
-            max_hitpoints: self.max_hitpoints,
-            moves: self.moves,
+    /// Takes the up stairs the player is standing on, if it's their turn.
+    /// Returns whether the player moved.
+    pub fn ascend(&mut self) -> bool {
+        let player = match self.acting_player() {
+            Some(player) => player,
+            None => return false,
         };
+        if self.world.positions.get(&player) != self.level.up_stairs.as_ref() {
+            self.log("There are no stairs up here.".to_owned());
+            return false;
+        }
+        if self.depth == 1 {
+            self.log("The way out is sealed.".to_owned());
+            return false;
+        }
 
-        let js_value = serde_wasm_bindgen::to_value(&stats);
+        self.change_level(player, self.depth - 1);
+        self.log(format!("You go up to depth {}.", self.depth));
 
-        stats_updated(js_value.unwrap());
+        true
     }
 
-    pub fn take_damage(&mut self, damage: i32) -> i32 {
-        self.hitpoints = self.hitpoints - damage;
+    /// Places up to `count` boxes on free cells. Some are locked.
+    pub fn place_boxes(&mut self, count: usize) {
+        for _ in 0..count {
+            let cell = match self.take_free_cell() {
+                Some(cell) => cell,
+                None => break,
+            };
+            let locked = self.rng.chance(LOCKED_CHANCE);
+
+            self.spawn_box(&cell, locked);
+        }
+    }
+
+    /// Puts the player on the up stairs, or on a random free cell if the
+    /// level has none. Returns false if the map is full.
+    pub fn spawn_player(&mut self, icon: &str, color: &str) -> bool {
+        let stairs = self.level.up_stairs.clone();
+        let cell = match stairs.or_else(|| self.take_free_cell()) {
+            Some(cell) => cell,
+            None => return false,
+        };
+
+        if let Some(player) = self.player {
+            self.despawn(player);
+        }
+        let player = self.world.spawn();
+        self.world.names.insert(player, "you".to_owned());
+        self.world.positions.insert(player, cell);
+        self.world.renderables.insert(
+            player,
+            Renderable {
+                icon: icon.to_owned(),
+                color: color.to_owned(),
+                layer: Layer::Player,
+            },
+        );
+        self.world
+            .healths
+            .insert(player, Health::new(PLAYER_HITPOINTS));
+        self.world.combat.insert(player, PLAYER_COMBAT);
+        self.world.inventories.insert(
+            player,
+            Inventory {
+                items: Vec::new(),
+                capacity: PLAYER_CAPACITY,
+            },
+        );
+        self.world.equipment.insert(player, Equipment::default());
+        self.world.blocking.insert(player);
+        self.scheduler.add(player, TURN);
+
+        self.player = Some(player);
+        self.moves = 0;
         self.emit_stats();
-        self.hitpoints
+        self.update_fov();
+
+        true
     }
-}
 
-#[wasm_bindgen]
-pub struct GameEngine {
-    display: Display,
-    points: HashMap<GridPoint, String>,
-    prize_location: Option<GridPoint>,
-}
+    /// Puts a monster of the named kind on a random free cell. Returns
+    /// false if there's no such kind or the map is full.
+    pub fn spawn_monster(&mut self, kind: &str) -> bool {
+        let kind = match monsters::find(kind) {
+            Some(kind) => kind,
+            None => return false,
+        };
 
-#[wasm_bindgen]
-impl GameEngine {
-    #[wasm_bindgen(constructor)]
-    pub fn new(display: Display) -> GameEngine {
-        GameEngine {
-            display,
-            points: HashMap::new(),
-            prize_location: None,
+        self.spawn_monster_kind(kind)
+    }
+
+    /// Puts `count` monsters on free cells, of random kinds that belong at
+    /// this depth.
+    pub fn spawn_monsters(&mut self, count: usize) {
+        let kinds = monsters::at_depth(self.depth);
+        if kinds.is_empty() {
+            return;
+        }
+
+        for _ in 0..count {
+            let kind = kinds[self.rng.below(kinds.len())];
+            if !self.spawn_monster_kind(kind) {
+                break;
+            }
         }
     }
 
-    pub fn on_dig(&mut self, x: i32, y: i32, value: i32) {
-        if value == 0 {
-            let point = GridPoint { x, y };
+    /// The floor cells of the map as a flat list of `x, y` pairs, row by row.
+    pub fn floor_cells(&self) -> Vec<i32> {
+        self.level
+            .map
+            .open_cells()
+            .iter()
+            .flat_map(|(point, _)| [point.x, point.y])
+            .collect()
+    }
 
-            self.points.insert(point, ".".to_owned());
+    /// The player's entity id, for the pathfinding queries.
+    pub fn player(&self) -> Option<Entity> {
+        self.player
+    }
+
+    /// True when the game is waiting for the player to act.
+    pub fn player_turn_pending(&mut self) -> bool {
+        !self.game_over && self.player.is_some() && self.scheduler.next() == self.player
+    }
+
+    /// Lets the next monster in line act, unless the player is up next.
+    pub fn tick(&mut self) {
+        if self.game_over {
+            return;
+        }
+
+        let entity = match self.scheduler.next() {
+            Some(entity) if Some(entity) != self.player => entity,
+            _ => return,
+        };
+        if !self.world.ais.contains_key(&entity) {
+            return self.scheduler.remove(entity);
         }
+
+        let action = self.ai_turn(entity);
+        self.scheduler.spend(entity, action);
     }
 
-    pub fn draw_map(&self) {
-        for (key, value) in &self.points {
-            self.display.draw(key.x, key.y, &value);
+    /// Runs monster turns until the player is up or the game has ended.
+    pub fn run_until_player_turn(&mut self) {
+        while !self.game_over && self.player.is_some() && !self.player_turn_pending() {
+            self.tick();
         }
     }
 
-    pub fn redraw_at(&self, x: i32, y: i32) {
-        let grid_point = GridPoint { x, y };
+    pub fn is_game_over(&self) -> bool {
+        self.game_over
+    }
+
+    /// Hands over the messages logged since the last call, oldest first.
+    pub fn take_messages(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.messages)
+    }
+
+    /// Moves the player one step, or attacks a monster in the way, if it's
+    /// their turn. Returns whether the player acted.
+    pub fn move_player(&mut self, dx: i32, dy: i32) -> bool {
+        let player = match self.acting_player() {
+            Some(player) => player,
+            None => return false,
+        };
+        let target = match self.world.positions.get(&player) {
+            Some(position) => GridPoint {
+                x: position.x + dx,
+                y: position.y + dy,
+            },
+            None => return false,
+        };
+
+        match self.try_move(player, &target) {
+            Some(action) => {
+                if action == Action::Move {
+                    self.moves += 1;
+                    self.emit_stats();
+                    self.update_fov();
+                }
+                self.spend_turn(player, action);
+                true
+            }
+            None => false,
+        }
+    }
 
-        if let Some(cell) = self.points.get(&grid_point) {
-            self.display.draw(x, y, cell);
+    /// Passes the player's turn.
+    pub fn wait(&mut self) {
+        if let Some(player) = self.acting_player() {
+            self.spend_turn(player, Action::Wait);
+        }
+    }
+
+    /// Sets how fast the player acts; 100 is normal speed, 200 hasted and
+    /// 50 slowed.
+    pub fn set_player_speed(&mut self, speed: i32) {
+        if let Some(player) = self.player {
+            self.scheduler.set_speed(player, speed);
+        }
+    }
+
+    pub fn player_speed(&self) -> i32 {
+        self.player
+            .and_then(|player| self.scheduler.speed(player))
+            .unwrap_or(TURN)
+    }
+
+    pub fn set_fov_radius(&mut self, radius: i32) {
+        self.fov_radius = radius.max(0);
+    }
+
+    /// Recomputes what the player can see and redraws the cells that came
+    /// into or went out of view.
+    pub fn update_fov(&mut self) {
+        let origin = match self
+            .player
+            .and_then(|player| self.world.positions.get(&player))
+        {
+            Some(position) => position.clone(),
+            None => return,
+        };
+        let map = &self.level.map;
+        let visible =
+            fov::visible_cells(&origin, self.fov_radius, |x, y| map.get(x, y) == Tile::Wall);
+        let previous = std::mem::replace(&mut self.visible, visible);
+
+        self.level.seen.extend(self.visible.iter().cloned());
+        for point in previous.symmetric_difference(&self.visible) {
+            self.draw_cell(point);
+        }
+    }
+
+    /// Whether the cell is in the player's sight.
+    pub fn is_visible(&self, x: i32, y: i32) -> bool {
+        self.visible.contains(&GridPoint { x, y })
+    }
+
+    /// Draws the cells in view, and dims the ones that have only been seen.
+    pub fn draw_map(&self) {
+        for point in &self.level.seen {
+            self.draw_cell(point);
         }
     }
 
+    pub fn redraw_at(&self, x: i32, y: i32) {
+        self.draw_cell(&GridPoint { x, y });
+    }
+
     pub fn place_box(&mut self, x: i32, y: i32) {
         let grid_point = GridPoint { x, y };
 
-        self.points.insert(grid_point, "*".to_owned());
+        if self.free_cell(x, y) {
+            self.spawn_box(&grid_point, false);
+        }
     }
 
-    pub fn open_box(&mut self, player: &mut Being) {
-        let [x, y] = [player.x(), player.y()];
-        let grid_point = GridPoint { x, y };
+    /// Opens the box under the player, if it's their turn. Locked boxes
+    /// use up a key. Returns whether the player acted.
+    pub fn open_box(&mut self) -> bool {
+        let player = match self.acting_player() {
+            Some(player) => player,
+            None => return false,
+        };
+        let here = match self.world.positions.get(&player) {
+            Some(position) => position.clone(),
+            None => return false,
+        };
+        let container = match self.world.container_at(&here) {
+            Some(container) => container,
+            None => {
+                self.log("There's no box here.".to_owned());
+                return false;
+            }
+        };
 
-        {
-            let cell = self.points.get(&grid_point).unwrap();
+        let locked = self.world.locked.contains(&container);
+        if locked {
+            if !self.world.unlock(player, container) {
+                self.log("The box is locked.".to_owned());
+                return false;
+            }
+            self.log("You unlock the box with a key.".to_owned());
+        }
 
-            if cell != "*" {
-                alert("There's no prize box here.");
-                return;
+        self.despawn(container);
+        let deep_loot = self.rng.chance(level::deep_loot_chance(self.depth));
+        let table = if locked || deep_loot {
+            items::LOCKED_BOX_LOOT
+        } else {
+            items::BOX_LOOT
+        };
+        match items::roll(table, &mut self.rng) {
+            Loot::Nothing => self.log("The box is empty.".to_owned()),
+            Loot::Trap(low, high) => {
+                let damage = self.rng.range(low, high + 1);
+                self.log(format!("It's a trap! It hits you for {}.", damage));
+                self.damage(player, damage);
+            }
+            Loot::Item(name) => {
+                if let Some(template) = items::find(name) {
+                    self.spawn_item(template, Some(here));
+                    self.log(format!("The box holds a {}.", name));
+                }
             }
         }
+        self.spend_turn(player, Action::OpenBox);
 
-        if let Some(ref location) = self.prize_location {
-            if *location == grid_point {
-                alert("Congratulations! You've found the prize!!");
-            } else {
-                alert("Uh, oh; it's a trap!");
-                player.take_damage(30);
+        true
+    }
+
+    /// Picks up an item under the player. Returns whether the player acted.
+    pub fn pick_up(&mut self) -> bool {
+        let player = match self.acting_player() {
+            Some(player) => player,
+            None => return false,
+        };
+        let item = match self
+            .world
+            .positions
+            .get(&player)
+            .and_then(|here| self.world.items_at(here).first().copied())
+        {
+            Some(item) => item,
+            None => {
+                self.log("There's nothing here to pick up.".to_owned());
+                return false;
             }
+        };
+
+        if !self.give(player, item) {
+            self.log("You can't carry any more.".to_owned());
+            return false;
         }
+        let message = format!("You pick up the {}.", self.world.name(item));
+        self.log(message);
+        self.spend_turn(player, Action::PickUp);
 
-        self.remove_box(grid_point.x, grid_point.y);
+        true
     }
 
-    fn remove_box(&mut self, x: i32, y: i32) {
-        let location = GridPoint { x, y };
+    /// Drops the item in inventory slot `slot`. Returns whether the player
+    /// acted.
+    pub fn drop_item(&mut self, slot: usize) -> bool {
+        let player = match self.acting_player() {
+            Some(player) => player,
+            None => return false,
+        };
+        let (item, here) = match (
+            self.world.carried(player, slot),
+            self.world.positions.get(&player),
+        ) {
+            (Some(item), Some(here)) => (item, here.clone()),
+            _ => return false,
+        };
+
+        self.world.remove_from_inventory(player, item);
+        self.world.positions.insert(item, here);
+        let message = format!("You drop the {}.", self.world.name(item));
+        self.log(message);
+        self.spend_turn(player, Action::Drop);
 
-        self.points.insert(location, ".".to_owned());
+        true
     }
 
-    pub fn mark_prize(&mut self, x: i32, y: i32) {
-        let grid_point = GridPoint { x, y };
+    /// Uses the item in inventory slot `slot`: drinks potions, and equips
+    /// or takes off weapons and armor. Returns whether the player acted.
+    pub fn use_item(&mut self, slot: usize) -> bool {
+        let player = match self.acting_player() {
+            Some(player) => player,
+            None => return false,
+        };
+        let item = match self.world.carried(player, slot) {
+            Some(item) => item,
+            None => return false,
+        };
+        let name = self.world.name(item).to_owned();
+
+        let action = match self.world.items.get(&item).copied() {
+            Some(ItemKind::HealingPotion(amount)) => {
+                if let Some(health) = self.world.healths.get_mut(&player) {
+                    health.hitpoints = (health.hitpoints + amount).min(health.max_hitpoints);
+                }
+                self.world.remove_from_inventory(player, item);
+                self.world.despawn(item);
+                self.emit_stats();
+                self.log(format!("You drink the {} and feel better.", name));
+                Action::UseItem
+            }
+            Some(ItemKind::HastePotion(turns)) => {
+                systems::haste(&mut self.scheduler, player, &mut self.haste_turns, turns);
+                self.world.remove_from_inventory(player, item);
+                self.world.despawn(item);
+                self.log(format!("You drink the {} and speed up.", name));
+                Action::UseItem
+            }
+            Some(ItemKind::Weapon { .. }) | Some(ItemKind::Armor { .. }) => {
+                return self.equip(slot);
+            }
+            Some(ItemKind::Key) => {
+                self.log("Keys open locked boxes; open one to use it.".to_owned());
+                return false;
+            }
+            None => return false,
+        };
+        self.spend_turn(player, action);
 
-        if let Some(cell) = self.points.get(&grid_point) {
-            if cell == "*" {
-                self.prize_location = Some(grid_point);
+        true
+    }
+
+    /// Equips the weapon or armor in inventory slot `slot`, or takes it off
+    /// if it's already equipped. Returns whether the player acted.
+    pub fn equip(&mut self, slot: usize) -> bool {
+        let player = match self.acting_player() {
+            Some(player) => player,
+            None => return false,
+        };
+        let item = match self.world.carried(player, slot) {
+            Some(item) => item,
+            None => return false,
+        };
+        let kind = self.world.items.get(&item).copied();
+        let name = self.world.name(item).to_owned();
+        let equipment = self.world.equipment.entry(player).or_default();
+
+        let message = if equipment.is_equipped(item) {
+            equipment.unequip(item);
+            format!("You take off the {}.", name)
+        } else {
+            match kind {
+                Some(ItemKind::Weapon { .. }) => equipment.weapon = Some(item),
+                Some(ItemKind::Armor { .. }) => equipment.armor = Some(item),
+                _ => return false,
             }
-        }
+            format!("You equip the {}.", name)
+        };
+        self.log(message);
+        self.spend_turn(player, Action::Equip);
+
+        true
     }
 
-    pub fn move_being(&mut self, being: &mut Being, x: i32, y: i32) {
-        // replace being icon with what's underneath
-        self.redraw_at(being.x(), being.y());
+    /// The player's inventory, in slot order, as a list of
+    /// `{ name, icon, color, category, description, equipped }`.
+    pub fn inventory(&self) -> JsValue {
+        let entries: Vec<InventoryEntry> = self
+            .player
+            .and_then(|player| self.world.inventories.get(&player))
+            .map(|inventory| {
+                inventory
+                    .items
+                    .iter()
+                    .map(|&item| self.inventory_entry(item))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        serde_wasm_bindgen::to_value(&entries).unwrap()
+    }
+
+    /// How many more things the player can carry.
+    pub fn inventory_space(&self) -> usize {
+        self.player
+            .and_then(|player| self.world.inventories.get(&player))
+            .map_or(0, |inventory| {
+                inventory.capacity.saturating_sub(inventory.items.len())
+            })
+    }
 
-        being.move_to(x, y);
+    /// Sets how monsters move when pathing.
+    pub fn set_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+    }
+
+    /// The next cell on the shortest path from `entity` to `target`, as
+    /// `[x, y]`, or `undefined` if there's no way through.
+    pub fn next_step_toward(&self, entity: Entity, target: Entity) -> Option<Vec<i32>> {
+        let from = self.world.positions.get(&entity)?;
+        let to = self.world.positions.get(&target)?;
+
+        self.step_toward(from, to).map(|step| vec![step.x, step.y])
+    }
+
+    /// The next cell for `entity` to run away from `threat`, as `[x, y]`,
+    /// or `undefined` if it's cornered.
+    pub fn next_step_away(&self, entity: Entity, threat: Entity) -> Option<Vec<i32>> {
+        let from = self.world.positions.get(&entity)?;
+        let threat = self.world.positions.get(&threat)?;
+
+        self.step_away(from, threat)
+            .map(|step| vec![step.x, step.y])
     }
 
     pub fn free_cell(&self, x: i32, y: i32) -> bool {
-        let grid_point = GridPoint { x, y };
+        self.level.map.get(x, y) != Tile::Wall
+    }
+}
 
-        match self.points.get(&grid_point) {
-            Some(cell) => cell == "." || cell == "*",
-            None => false,
+impl GameEngine {
+    /// Generates the level at `self.depth` and stocks it with boxes and
+    /// monsters for that depth.
+    fn build_level(&mut self) {
+        let (width, height) = self.map_size;
+        let mut grid = match self.map_style {
+            MapStyle::Rooms => dungeon::rooms_and_corridors(width, height, &mut self.rng),
+            MapStyle::Bsp => dungeon::bsp(width, height, &mut self.rng),
+            MapStyle::Caves => dungeon::caves(width, height, &mut self.rng),
+        };
+        let stairs = grid.place_stairs(&mut self.rng);
+
+        self.level = Level {
+            up_stairs: stairs.as_ref().map(|(up, _)| up.clone()),
+            down_stairs: stairs.map(|(_, down)| down),
+            free_cells: grid.floor_cells(),
+            map: grid,
+            ..Level::default()
+        };
+        self.scheduler.clear();
+
+        self.place_boxes(level::BOX_COUNT);
+        self.spawn_monsters(level::monster_count(self.depth));
+    }
+
+    /// Moves the player to the level at `depth`, packing this one away and
+    /// generating the new one if it hasn't been visited. The player
+    /// arrives on the stairs leading back.
+    fn change_level(&mut self, player: Entity, depth: u32) {
+        let speed = self.player_speed();
+        let arriving_from_above = depth > self.depth;
+
+        let mut left = std::mem::take(&mut self.level);
+        left.pack(&mut self.world, &mut self.scheduler, player);
+        self.levels.insert(self.depth, left);
+        self.depth = depth;
+        self.visible.clear();
+
+        match self.levels.remove(&depth) {
+            Some(mut level) => {
+                level.unpack(&mut self.world, &mut self.scheduler);
+                self.level = level;
+            }
+            None => self.build_level(),
+        }
+
+        let stairs = if arriving_from_above {
+            self.level.up_stairs.clone()
+        } else {
+            self.level.down_stairs.clone()
+        };
+        let arrival = stairs
+            .and_then(|stairs| self.level.arrival_cell(&self.world, &stairs))
+            .or_else(|| self.take_free_cell());
+        if let Some(arrival) = arrival {
+            self.world.positions.insert(player, arrival);
         }
+        self.scheduler.add(player, speed);
+
+        self.display.clear();
+        self.update_fov();
+        self.draw_map();
+        self.emit_stats();
+    }
+
+    /// The player, if it's their turn.
+    fn acting_player(&mut self) -> Option<Entity> {
+        if self.player_turn_pending() {
+            self.player
+        } else {
+            None
+        }
+    }
+
+    /// Spends the player's time on `action`, and wears down haste.
+    fn spend_turn(&mut self, player: Entity, action: Action) {
+        self.scheduler.spend(player, action);
+
+        if systems::wear_haste(&mut self.scheduler, player, &mut self.haste_turns) {
+            self.log("You slow down.".to_owned());
+        }
+    }
+
+    fn spawn_box(&mut self, at: &GridPoint, locked: bool) {
+        let container = self.world.spawn();
+        let color = if locked { LOCKED_BOX_COLOR } else { BOX_COLOR };
+
+        self.world.names.insert(container, "box".to_owned());
+        self.world.positions.insert(container, at.clone());
+        self.world.renderables.insert(
+            container,
+            Renderable {
+                icon: "*".to_owned(),
+                color: color.to_owned(),
+                layer: Layer::Item,
+            },
+        );
+        self.world.containers.insert(container);
+        if locked {
+            self.world.locked.insert(container);
+        }
+        self.draw_cell(at);
+    }
+
+    /// Puts a new item on the floor at `at`, or nowhere if `at` is `None`.
+    fn spawn_item(&mut self, template: &items::ItemTemplate, at: Option<GridPoint>) -> Entity {
+        let item = self.world.spawn();
+
+        self.world.names.insert(item, template.name.to_owned());
+        self.world.renderables.insert(
+            item,
+            Renderable {
+                icon: template.icon.to_owned(),
+                color: template.color.to_owned(),
+                layer: Layer::Item,
+            },
+        );
+        self.world.items.insert(item, template.kind);
+        if let Some(at) = at {
+            self.world.positions.insert(item, at.clone());
+            self.draw_cell(&at);
+        }
+
+        item
+    }
+
+    fn spawn_monster_kind(&mut self, kind: &monsters::MonsterKind) -> bool {
+        let cell = match self.take_free_cell() {
+            Some(cell) => cell,
+            None => return false,
+        };
+
+        let monster = self.world.spawn();
+        self.world.names.insert(monster, kind.name.to_owned());
+        self.world.positions.insert(monster, cell.clone());
+        self.world.renderables.insert(
+            monster,
+            Renderable {
+                icon: kind.icon.to_owned(),
+                color: kind.color.to_owned(),
+                layer: Layer::Monster,
+            },
+        );
+        self.world
+            .healths
+            .insert(monster, Health::new(kind.hitpoints));
+        self.world.combat.insert(monster, kind.combat);
+        self.world.ais.insert(monster, kind.ai);
+        self.world.blocking.insert(monster);
+        self.scheduler.add(monster, kind.speed);
+        self.draw_cell(&cell);
+
+        true
+    }
+
+    fn inventory_entry(&self, item: Entity) -> InventoryEntry {
+        let renderable = self.world.renderables.get(&item);
+        let kind = self.world.items.get(&item);
+        let equipped = self
+            .player
+            .and_then(|player| self.world.equipment.get(&player))
+            .is_some_and(|equipment| equipment.is_equipped(item));
+
+        InventoryEntry {
+            name: self.world.name(item).to_owned(),
+            icon: renderable.map_or_else(String::new, |r| r.icon.clone()),
+            color: renderable.map_or_else(String::new, |r| r.color.clone()),
+            category: kind.map_or("", |kind| kind.category()).to_owned(),
+            description: kind.map_or_else(String::new, |kind| kind.describe()),
+            equipped,
+        }
+    }
+
+    fn emit_stats(&self) {
+        let health = match self
+            .player
+            .and_then(|player| self.world.healths.get(&player))
+        {
+            Some(health) => health,
+            None => return,
+        };
+        let stats = Stats {
+            hitpoints: health.hitpoints,
+            max_hitpoints: health.max_hitpoints,
+            moves: self.moves,
+            depth: self.depth,
+        };
+
+        let js_value = serde_wasm_bindgen::to_value(&stats);
+
+        stats_updated(js_value.unwrap());
+    }
+
+    fn take_free_cell(&mut self) -> Option<GridPoint> {
+        if self.level.free_cells.is_empty() {
+            return None;
+        }
+
+        let index = self.rng.below(self.level.free_cells.len());
+        Some(self.level.free_cells.swap_remove(index))
+    }
+
+    fn step_toward(&self, from: &GridPoint, to: &GridPoint) -> Option<GridPoint> {
+        let path = path::astar(from, to, self.topology, |point| {
+            self.free_cell(point.x, point.y)
+        })?;
+
+        path.into_iter().next()
+    }
+
+    fn step_away(&self, from: &GridPoint, threat: &GridPoint) -> Option<GridPoint> {
+        let passable = |point: &GridPoint| self.free_cell(point.x, point.y);
+        let flee = DijkstraMap::towards(std::slice::from_ref(threat), self.topology, passable)
+            .fleeing(passable);
+
+        flee.next_step(from)
+    }
+
+    fn log(&mut self, message: String) {
+        self.messages.push(message);
     }
 }
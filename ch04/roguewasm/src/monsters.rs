@@ -0,0 +1,100 @@
+//! The monsters the dungeon can hold. Adding a kind of monster is a new
+//! entry here; nothing on the JavaScript side needs to change.
+
+use super::combat::CombatStats;
+use super::ecs::Ai;
+
+pub struct MonsterKind {
+    pub name: &'static str,
+    pub icon: &'static str,
+    pub color: &'static str,
+    /// 100 is as fast as the player.
+    pub speed: i32,
+    /// The shallowest level it turns up on.
+    pub depth: u32,
+    pub hitpoints: i32,
+    pub combat: CombatStats,
+    pub ai: Ai,
+}
+
+pub const MONSTERS: &[MonsterKind] = &[
+    MonsterKind {
+        name: "rat",
+        icon: "r",
+        color: "#a0522d",
+        speed: 120,
+        depth: 1,
+        hitpoints: 8,
+        combat: CombatStats::new(2, 1, 1, 3),
+        ai: Ai::Cowardly,
+    },
+    MonsterKind {
+        name: "bat",
+        icon: "b",
+        color: "#9a9a9a",
+        speed: 150,
+        depth: 1,
+        hitpoints: 6,
+        combat: CombatStats::new(3, 2, 1, 2),
+        ai: Ai::Chase,
+    },
+    MonsterKind {
+        name: "kobold",
+        icon: "k",
+        color: "#cd853f",
+        speed: 100,
+        depth: 1,
+        hitpoints: 12,
+        combat: CombatStats::new(3, 2, 1, 4),
+        ai: Ai::Cowardly,
+    },
+    MonsterKind {
+        name: "goblin",
+        icon: "g",
+        color: "#3cb371",
+        speed: 100,
+        depth: 2,
+        hitpoints: 15,
+        combat: CombatStats::new(4, 2, 2, 5),
+        ai: Ai::Chase,
+    },
+    MonsterKind {
+        name: "Borrowmir",
+        icon: "B",
+        color: "#ff0000",
+        speed: 100,
+        depth: 3,
+        hitpoints: 40,
+        combat: CombatStats::new(4, 2, 2, 7),
+        ai: Ai::Chase,
+    },
+    MonsterKind {
+        name: "orc",
+        icon: "o",
+        color: "#6b8e23",
+        speed: 90,
+        depth: 3,
+        hitpoints: 25,
+        combat: CombatStats::new(5, 3, 3, 8),
+        ai: Ai::Chase,
+    },
+    MonsterKind {
+        name: "troll",
+        icon: "T",
+        color: "#2e8b57",
+        speed: 70,
+        depth: 4,
+        hitpoints: 50,
+        combat: CombatStats::new(6, 4, 5, 12),
+        ai: Ai::Chase,
+    },
+];
+
+pub fn find(name: &str) -> Option<&'static MonsterKind> {
+    MONSTERS.iter().find(|kind| kind.name == name)
+}
+
+/// The kinds of monster that turn up at `depth`.
+pub fn at_depth(depth: u32) -> Vec<&'static MonsterKind> {
+    MONSTERS.iter().filter(|kind| kind.depth <= depth).collect()
+}
@@ -0,0 +1,252 @@
+//! Pathfinding over the map: A* between two points, and Dijkstra maps that
+//! give every reachable cell its distance from a set of goals.
+
+use super::GridPoint;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use wasm_bindgen::prelude::*;
+
+/// Which neighbours a step can reach.
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Topology {
+    /// Up, down, left and right.
+    Four,
+    /// Diagonals as well, at the same cost.
+    Eight,
+}
+
+const FOUR: [(i32, i32); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+const EIGHT: [(i32, i32); 8] = [
+    (0, -1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+];
+
+/// Flee maps scale distances by this (as a fraction of 10) so that fleeing
+/// monsters prefer heading past the goal to a nearby dead end.
+const FLEE_SCALE: i32 = -12;
+
+impl Topology {
+    pub(crate) fn neighbours(self, point: &GridPoint) -> impl Iterator<Item = GridPoint> + '_ {
+        let steps: &[(i32, i32)] = match self {
+            Topology::Four => &FOUR,
+            Topology::Eight => &EIGHT,
+        };
+
+        steps.iter().map(move |(dx, dy)| GridPoint {
+            x: point.x + dx,
+            y: point.y + dy,
+        })
+    }
+
+    /// The number of steps between two points on an open map.
+    pub(crate) fn distance(self, from: &GridPoint, to: &GridPoint) -> i32 {
+        let dx = (from.x - to.x).abs();
+        let dy = (from.y - to.y).abs();
+
+        match self {
+            Topology::Four => dx + dy,
+            Topology::Eight => dx.max(dy),
+        }
+    }
+}
+
+/// The shortest path from `from` to `to`, not including `from`. `to` is
+/// always allowed as the last step, so a path can end on an occupied cell.
+pub fn astar<F>(
+    from: &GridPoint,
+    to: &GridPoint,
+    topology: Topology,
+    passable: F,
+) -> Option<Vec<GridPoint>>
+where
+    F: Fn(&GridPoint) -> bool,
+{
+    let mut open = BinaryHeap::new();
+    let mut came_from: HashMap<GridPoint, GridPoint> = HashMap::new();
+    let mut cost = HashMap::new();
+
+    cost.insert(from.clone(), 0);
+    open.push(Reverse((topology.distance(from, to), 0, from.clone())));
+
+    while let Some(Reverse((_, steps, point))) = open.pop() {
+        if point == *to {
+            let mut path = vec![point];
+            while let Some(previous) = came_from.get(path.last().unwrap()) {
+                path.push(previous.clone());
+            }
+            path.pop();
+            path.reverse();
+            return Some(path);
+        }
+        if cost.get(&point).is_some_and(|&best| steps > best) {
+            continue;
+        }
+
+        for next in topology.neighbours(&point) {
+            if next != *to && !passable(&next) {
+                continue;
+            }
+
+            let next_steps = steps + 1;
+            if cost.get(&next).is_none_or(|&best| next_steps < best) {
+                cost.insert(next.clone(), next_steps);
+                came_from.insert(next.clone(), point.clone());
+                let estimate = next_steps + topology.distance(&next, to);
+                open.push(Reverse((estimate, next_steps, next)));
+            }
+        }
+    }
+
+    None
+}
+
+/// The distance of every reachable cell from the nearest goal.
+#[derive(Clone, Debug)]
+pub struct DijkstraMap {
+    topology: Topology,
+    distances: HashMap<GridPoint, i32>,
+}
+
+impl DijkstraMap {
+    /// A map leading towards `goals`.
+    pub fn towards<F>(goals: &[GridPoint], topology: Topology, passable: F) -> DijkstraMap
+    where
+        F: Fn(&GridPoint) -> bool,
+    {
+        let sources = goals.iter().map(|goal| (goal.clone(), 0)).collect();
+
+        DijkstraMap::from_sources(sources, topology, passable)
+    }
+
+    /// A map leading away from this one's goals. Following it downhill
+    /// heads for the cells furthest away, but will double back past the
+    /// goals rather than walk into a corner.
+    pub fn fleeing<F>(&self, passable: F) -> DijkstraMap
+    where
+        F: Fn(&GridPoint) -> bool,
+    {
+        let sources = self
+            .distances
+            .iter()
+            .map(|(point, distance)| (point.clone(), distance * FLEE_SCALE / 10))
+            .collect();
+
+        DijkstraMap::from_sources(sources, self.topology, passable)
+    }
+
+    fn from_sources<F>(
+        sources: Vec<(GridPoint, i32)>,
+        topology: Topology,
+        passable: F,
+    ) -> DijkstraMap
+    where
+        F: Fn(&GridPoint) -> bool,
+    {
+        let mut distances = HashMap::new();
+        let mut open = BinaryHeap::new();
+
+        for (point, distance) in sources {
+            distances.insert(point.clone(), distance);
+            open.push(Reverse((distance, point)));
+        }
+
+        while let Some(Reverse((distance, point))) = open.pop() {
+            if distances.get(&point).is_some_and(|&best| distance > best) {
+                continue;
+            }
+
+            for next in topology.neighbours(&point) {
+                if !passable(&next) {
+                    continue;
+                }
+                if distances.get(&next).is_none_or(|&best| distance + 1 < best) {
+                    distances.insert(next.clone(), distance + 1);
+                    open.push(Reverse((distance + 1, next)));
+                }
+            }
+        }
+
+        DijkstraMap {
+            topology,
+            distances,
+        }
+    }
+
+    pub fn distance(&self, point: &GridPoint) -> Option<i32> {
+        self.distances.get(point).copied()
+    }
+
+    /// The neighbour of `from` that is furthest downhill, if any is lower
+    /// than `from` itself.
+    pub fn next_step(&self, from: &GridPoint) -> Option<GridPoint> {
+        let here = self.distance(from).unwrap_or(i32::MAX);
+
+        self.topology
+            .neighbours(from)
+            .filter_map(|next| self.distance(&next).map(|distance| (distance, next)))
+            .filter(|(distance, _)| *distance < here)
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, next)| next)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::GridPoint;
+    use super::{astar, DijkstraMap, Topology};
+
+    const MAP: [&str; 5] = [
+        "#########",
+        "#...#...#",
+        "#.#.#.#.#",
+        "#.#...#.#",
+        "#########",
+    ];
+
+    fn passable(point: &GridPoint) -> bool {
+        MAP.get(point.y as usize)
+            .and_then(|row| row.as_bytes().get(point.x as usize))
+            .is_some_and(|&cell| cell == b'.')
+    }
+
+    fn point(x: i32, y: i32) -> GridPoint {
+        GridPoint { x, y }
+    }
+
+    #[test]
+    fn astar_finds_the_shortest_path() {
+        let path = astar(&point(1, 3), &point(7, 3), Topology::Four, passable).unwrap();
+
+        assert_eq!(path.len(), 14);
+        assert_eq!(path.first(), Some(&point(1, 2)));
+        assert_eq!(path.last(), Some(&point(7, 3)));
+
+        let diagonal = astar(&point(1, 3), &point(7, 3), Topology::Eight, passable).unwrap();
+        assert!(diagonal.len() < path.len());
+
+        assert_eq!(
+            astar(&point(1, 1), &point(9, 9), Topology::Four, passable),
+            None
+        );
+    }
+
+    #[test]
+    fn dijkstra_maps_lead_to_and_from_goals() {
+        let towards = DijkstraMap::towards(&[point(7, 3)], Topology::Four, passable);
+
+        assert_eq!(towards.distance(&point(7, 3)), Some(0));
+        assert_eq!(towards.distance(&point(1, 3)), Some(14));
+        assert_eq!(towards.next_step(&point(1, 3)), Some(point(1, 2)));
+        assert_eq!(towards.next_step(&point(7, 3)), None);
+
+        let away = towards.fleeing(passable);
+        assert_eq!(away.next_step(&point(7, 2)), Some(point(7, 1)));
+    }
+}
@@ -0,0 +1,67 @@
+//! A small seeded random number generator, so a seed always gives the same
+//! dungeon.
+
+/// SplitMix64. Not suitable for anything but games.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `low..high`. Returns `low` if the range is empty.
+    pub fn range(&mut self, low: i32, high: i32) -> i32 {
+        if high <= low {
+            return low;
+        }
+
+        let span = (high - low) as u64;
+        low + (self.next_u64() % span) as i32
+    }
+
+    /// An index into a collection of `len` items. `len` must not be zero.
+    pub fn below(&mut self, len: usize) -> usize {
+        (self.next_u64() % len as u64) as usize
+    }
+
+    /// True with the given probability.
+    pub fn chance(&mut self, probability: f64) -> bool {
+        self.uniform() < probability
+    }
+
+    /// A number in `0.0..1.0`.
+    pub fn uniform(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Rng;
+
+    #[test]
+    fn seeds_repeat_and_ranges_hold() {
+        let mut first = Rng::new(42);
+        let mut second = Rng::new(42);
+
+        for _ in 0..1000 {
+            let value = first.range(-3, 4);
+            assert_eq!(value, second.range(-3, 4));
+            assert!((-3..4).contains(&value));
+        }
+        assert_eq!(first.range(5, 5), 5);
+        assert_ne!(Rng::new(1).next_u64(), Rng::new(2).next_u64());
+    }
+}
@@ -0,0 +1,178 @@
+//! Saved games. A save is the format version followed by the game state,
+//! encoded with bincode. Bump `VERSION` whenever `SavedGame` changes shape.
+
+use super::ecs::{Entity, World};
+use super::level::Level;
+use super::path::Topology;
+use super::rng::Rng;
+use super::scheduler::Scheduler;
+use super::{GameEngine, MapStyle};
+use std::collections::BTreeMap;
+
+pub const VERSION: u32 = 1;
+
+/// Everything in a game but the display and what's only on screen.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SavedGame {
+    pub level: Level,
+    pub depth: u32,
+    pub levels: BTreeMap<u32, Level>,
+    pub map_size: (i32, i32),
+    pub map_style: MapStyle,
+    pub seed: u32,
+    pub rng: Rng,
+    pub fov_radius: i32,
+    pub topology: Topology,
+    pub scheduler: Scheduler,
+    pub world: World,
+    pub player: Option<Entity>,
+    pub moves: i32,
+    pub haste_turns: i32,
+    pub game_over: bool,
+}
+
+pub fn encode(game: &SavedGame) -> Vec<u8> {
+    bincode::serialize(&(VERSION, game)).expect("game state always serializes")
+}
+
+/// Reads a save written by `encode`, or says why it can't.
+pub fn decode(bytes: &[u8]) -> Result<SavedGame, String> {
+    let version: u32 = bincode::deserialize(bytes).map_err(|error| error.to_string())?;
+
+    match version {
+        VERSION => {
+            let (_, game): (u32, SavedGame) =
+                bincode::deserialize(bytes).map_err(|error| error.to_string())?;
+            Ok(game)
+        }
+        _ => Err(format!("unknown save version {}", version)),
+    }
+}
+
+impl GameEngine {
+    pub(crate) fn saved_game(&self) -> SavedGame {
+        SavedGame {
+            level: self.level.clone(),
+            depth: self.depth,
+            levels: self.levels.clone(),
+            map_size: self.map_size,
+            map_style: self.map_style,
+            seed: self.seed,
+            rng: self.rng.clone(),
+            fov_radius: self.fov_radius,
+            topology: self.topology,
+            scheduler: self.scheduler.clone(),
+            world: self.world.clone(),
+            player: self.player,
+            moves: self.moves,
+            haste_turns: self.haste_turns,
+            game_over: self.game_over,
+        }
+    }
+
+    /// Replaces the game with a saved one and redraws it.
+    pub(crate) fn restore(&mut self, game: SavedGame) {
+        self.level = game.level;
+        self.depth = game.depth;
+        self.levels = game.levels;
+        self.map_size = game.map_size;
+        self.map_style = game.map_style;
+        self.seed = game.seed;
+        self.rng = game.rng;
+        self.fov_radius = game.fov_radius;
+        self.topology = game.topology;
+        self.scheduler = game.scheduler;
+        self.world = game.world;
+        self.player = game.player;
+        self.moves = game.moves;
+        self.haste_turns = game.haste_turns;
+        self.game_over = game.game_over;
+        self.visible.clear();
+        self.messages.clear();
+
+        self.display.clear();
+        self.update_fov();
+        self.draw_map();
+        self.emit_stats();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::dungeon::{Tile, TileGrid};
+    use super::super::ecs::{Health, World};
+    use super::super::level::Level;
+    use super::super::path::Topology;
+    use super::super::rng::Rng;
+    use super::super::scheduler::{Action, Scheduler, TURN};
+    use super::super::{GridPoint, MapStyle};
+    use super::{decode, encode, SavedGame, VERSION};
+    use std::collections::{BTreeMap, HashSet};
+
+    fn game() -> SavedGame {
+        let mut world = World::new();
+        let player = world.spawn();
+        world.positions.insert(player, GridPoint { x: 2, y: 3 });
+        world.healths.insert(player, Health::new(40));
+        let mut scheduler = Scheduler::new();
+        scheduler.add(player, TURN);
+        let mut rng = Rng::new(11);
+        rng.next_u64();
+        let mut map = TileGrid::new(5, 5);
+        map.set(2, 3, Tile::UpStairs);
+
+        SavedGame {
+            level: Level {
+                map,
+                seen: HashSet::from([GridPoint { x: 2, y: 3 }]),
+                up_stairs: Some(GridPoint { x: 2, y: 3 }),
+                ..Level::default()
+            },
+            depth: 2,
+            levels: BTreeMap::from([(1, Level::default())]),
+            map_size: (80, 40),
+            map_style: MapStyle::Caves,
+            seed: 11,
+            rng,
+            fov_radius: 8,
+            topology: Topology::Eight,
+            scheduler,
+            world,
+            player: Some(player),
+            moves: 17,
+            haste_turns: 0,
+            game_over: false,
+        }
+    }
+
+    #[test]
+    fn saves_load_back_the_same_game() {
+        let saved = game();
+        let mut loaded = decode(&encode(&saved)).unwrap();
+        let mut original = saved.clone();
+        let player = loaded.player.unwrap();
+
+        assert_eq!(loaded.depth, 2);
+        assert_eq!(loaded.map_style, MapStyle::Caves);
+        assert_eq!(loaded.level.map.get(2, 3), Tile::UpStairs);
+        assert_eq!(loaded.level.map.get(1, 3), Tile::Wall);
+        assert_eq!(loaded.level.seen, original.level.seen);
+        assert_eq!(loaded.world.healths[&player].hitpoints, 40);
+        assert_eq!(loaded.world.spawn(), original.world.spawn());
+        assert_eq!(loaded.rng.next_u64(), original.rng.next_u64());
+        assert_eq!(loaded.scheduler.next(), Some(player));
+        loaded.scheduler.spend(player, Action::Move);
+        original.scheduler.spend(player, Action::Move);
+        assert_eq!(loaded.scheduler.next(), original.scheduler.next());
+    }
+
+    #[test]
+    fn other_versions_and_garbage_are_refused() {
+        let mut bytes = encode(&game());
+        bytes[..4].copy_from_slice(&(VERSION + 1).to_le_bytes());
+
+        assert!(decode(&bytes).unwrap_err().contains("version"));
+        assert!(decode(&[1, 0, 0, 0, 9]).is_err());
+        assert!(decode(&[]).is_err());
+    }
+}
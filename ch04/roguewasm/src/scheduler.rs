@@ -0,0 +1,191 @@
+//! Turn order by speed and energy. Every tick each actor gains energy equal
+//! to its speed, and an actor with a full turn's worth of energy acts,
+//! spending the cost of whatever it did. Fast actors fill up sooner and
+//! cheap actions leave energy over, so actors act at different rates.
+
+/// Energy needed to act, and the speed of an ordinary actor.
+pub const TURN: i32 = 100;
+
+pub type ActorId = u32;
+
+/// What an actor spent its turn on.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    Move,
+    Attack,
+    OpenBox,
+    PickUp,
+    Drop,
+    UseItem,
+    Equip,
+    Wait,
+}
+
+impl Action {
+    pub fn cost(self) -> i32 {
+        match self {
+            Action::Move | Action::Attack | Action::UseItem | Action::Equip | Action::Wait => TURN,
+            Action::OpenBox | Action::PickUp | Action::Drop => TURN / 2,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Actor {
+    id: ActorId,
+    speed: i32,
+    energy: i32,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Scheduler {
+    actors: Vec<Actor>,
+}
+
+impl Scheduler {
+    pub fn new() -> Scheduler {
+        Scheduler::default()
+    }
+
+    /// Adds an actor with no energy saved up.
+    pub fn add(&mut self, id: ActorId, speed: i32) {
+        self.remove(id);
+        self.actors.push(Actor {
+            id,
+            speed: speed.max(1),
+            energy: 0,
+        });
+    }
+
+    pub fn remove(&mut self, id: ActorId) {
+        self.actors.retain(|actor| actor.id != id);
+    }
+
+    pub fn clear(&mut self) {
+        self.actors.clear();
+    }
+
+    pub fn speed(&self, id: ActorId) -> Option<i32> {
+        self.actor(id).map(|actor| actor.speed)
+    }
+
+    /// Changes an actor's speed, e.g. when it's hasted or slowed.
+    pub fn set_speed(&mut self, id: ActorId, speed: i32) {
+        if let Some(actor) = self.actors.iter_mut().find(|actor| actor.id == id) {
+            actor.speed = speed.max(1);
+        }
+    }
+
+    /// The actor whose turn it is, ticking time forward until someone has
+    /// enough energy. Among actors ready at once, the one with the most
+    /// energy goes first, then the one added earliest.
+    pub fn next(&mut self) -> Option<ActorId> {
+        if self.actors.is_empty() {
+            return None;
+        }
+
+        loop {
+            if let Some(actor) = self.ready() {
+                return Some(actor.id);
+            }
+
+            for actor in &mut self.actors {
+                actor.energy += actor.speed;
+            }
+        }
+    }
+
+    /// Spends the cost of `action` from the actor's energy.
+    pub fn spend(&mut self, id: ActorId, action: Action) {
+        if let Some(actor) = self.actors.iter_mut().find(|actor| actor.id == id) {
+            actor.energy -= action.cost();
+        }
+    }
+
+    fn ready(&self) -> Option<&Actor> {
+        self.actors
+            .iter()
+            .filter(|actor| actor.energy >= TURN)
+            .fold(None, |best: Option<&Actor>, actor| match best {
+                Some(best) if best.energy >= actor.energy => Some(best),
+                _ => Some(actor),
+            })
+    }
+
+    fn actor(&self, id: ActorId) -> Option<&Actor> {
+        self.actors.iter().find(|actor| actor.id == id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Action, Scheduler, TURN};
+
+    fn turns(scheduler: &mut Scheduler, count: usize) -> Vec<u32> {
+        (0..count)
+            .map(|_| {
+                let id = scheduler.next().unwrap();
+                scheduler.spend(id, Action::Move);
+                id
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fast_actors_act_more_often() {
+        let mut scheduler = Scheduler::new();
+        let (normal, fast, slow) = (0, 1, 2);
+        scheduler.add(normal, TURN);
+        scheduler.add(fast, TURN * 2);
+        scheduler.add(slow, TURN / 2);
+
+        let order = turns(&mut scheduler, 14);
+        let count = |id| order.iter().filter(|&&turn| turn == id).count();
+
+        assert_eq!(count(fast), 8);
+        assert_eq!(count(normal), 4);
+        assert_eq!(count(slow), 2);
+    }
+
+    #[test]
+    fn cheap_actions_leave_time_over() {
+        let mut scheduler = Scheduler::new();
+        let (player, monster) = (0, 1);
+        scheduler.add(player, TURN);
+        scheduler.add(monster, TURN);
+
+        let mut order = Vec::new();
+        for _ in 0..9 {
+            let id = scheduler.next().unwrap();
+            let action = if id == player {
+                Action::OpenBox
+            } else {
+                Action::Move
+            };
+            scheduler.spend(id, action);
+            order.push(id);
+        }
+
+        assert_eq!(order.iter().filter(|&&id| id == player).count(), 6);
+        assert_eq!(order.iter().filter(|&&id| id == monster).count(), 3);
+    }
+
+    #[test]
+    fn speed_changes_and_removal() {
+        let mut scheduler = Scheduler::new();
+        let (player, monster) = (0, 1);
+        scheduler.add(player, TURN);
+        scheduler.add(monster, TURN);
+
+        scheduler.set_speed(monster, TURN * 3);
+        assert_eq!(scheduler.speed(monster), Some(TURN * 3));
+        assert_eq!(
+            turns(&mut scheduler, 4),
+            vec![monster, monster, player, monster]
+        );
+
+        scheduler.remove(player);
+        assert_eq!(turns(&mut scheduler, 2), vec![monster, monster]);
+        assert_eq!(scheduler.speed(player), None);
+    }
+}
@@ -0,0 +1,293 @@
+//! Systems: the game rules, run over the entities in `GameEngine::world`.
+
+use super::combat::{self, CombatStats, Outcome};
+use super::ecs::{Ai, Entity};
+use super::items::ItemKind;
+use super::scheduler::{Action, Scheduler};
+use super::{GameEngine, GridPoint, REMEMBERED_COLOR};
+
+/// Cowardly monsters run once their hitpoints fall to this fraction (as a
+/// fraction of 10) of their maximum.
+const FLEE_HEALTH: i32 = 3;
+
+impl GameEngine {
+    /// Movement: steps `entity` onto `to`, or attacks whatever blocks the
+    /// way if it can fight. Returns what the entity did, or `None` if it
+    /// couldn't go there.
+    pub(crate) fn try_move(&mut self, entity: Entity, to: &GridPoint) -> Option<Action> {
+        if let Some(blocker) = self.world.blocker_at(to) {
+            if self.hostile(entity, blocker) {
+                self.attack(entity, blocker);
+                return Some(Action::Attack);
+            }
+            return None;
+        }
+        if !self.free_cell(to.x, to.y) {
+            return None;
+        }
+
+        let from = self.world.positions.insert(entity, to.clone())?;
+        self.draw_cell(&from);
+        self.draw_cell(to);
+
+        Some(Action::Move)
+    }
+
+    /// Combat: one attack roll, with the result logged.
+    pub(crate) fn attack(&mut self, attacker: Entity, defender: Entity) {
+        let (attack, defense) = match (self.combat_stats(attacker), self.combat_stats(defender)) {
+            (Some(attack), Some(defense)) => (attack, defense),
+            _ => return,
+        };
+        let outcome = combat::attack(&attack, &defense, &mut self.rng);
+
+        let subject = self.describe(attacker, true);
+        let object = self.describe(defender, false);
+        let message = match outcome {
+            Outcome::Miss => format!(
+                "{} {} {}.",
+                subject,
+                self.verb(attacker, "miss", "misses"),
+                object
+            ),
+            Outcome::Hit(damage) => format!(
+                "{} {} {} for {}.",
+                subject,
+                self.verb(attacker, "hit", "hits"),
+                object,
+                damage
+            ),
+            Outcome::Critical(damage) => format!(
+                "{} {} a critical hit on {} for {}!",
+                subject,
+                self.verb(attacker, "land", "lands"),
+                object,
+                damage
+            ),
+        };
+        self.log(message);
+
+        let name = self.describe(defender, true);
+        if self.damage(defender, outcome.damage()) && Some(defender) != self.player {
+            self.log(format!("{} dies.", name));
+        }
+    }
+
+    /// Combat: an entity's stats with its equipment on. A weapon adds to
+    /// attack and replaces the bare-handed damage; armor adds to defense.
+    pub(crate) fn combat_stats(&self, entity: Entity) -> Option<CombatStats> {
+        let mut stats = *self.world.combat.get(&entity)?;
+        let equipment = match self.world.equipment.get(&entity) {
+            Some(equipment) => equipment,
+            None => return Some(stats),
+        };
+
+        let worn = [equipment.weapon, equipment.armor];
+        for kind in worn
+            .iter()
+            .flatten()
+            .filter_map(|item| self.world.items.get(item))
+        {
+            match *kind {
+                ItemKind::Weapon {
+                    attack,
+                    min_damage,
+                    max_damage,
+                } => {
+                    stats.attack += attack;
+                    stats.min_damage = min_damage;
+                    stats.max_damage = max_damage;
+                }
+                ItemKind::Armor { defense } => stats.defense += defense,
+                _ => {}
+            }
+        }
+
+        Some(stats)
+    }
+
+    /// Takes hitpoints from `entity`, removing it if it dies. Returns
+    /// whether it died.
+    pub(crate) fn damage(&mut self, entity: Entity, amount: i32) -> bool {
+        let dead = match self.world.healths.get_mut(&entity) {
+            Some(health) => {
+                health.hitpoints -= amount;
+                health.is_dead()
+            }
+            None => return false,
+        };
+
+        if Some(entity) == self.player {
+            self.emit_stats();
+            if dead {
+                self.log("You die...".to_owned());
+                self.game_over = true;
+            }
+        } else if dead {
+            self.despawn(entity);
+        }
+
+        dead
+    }
+
+    /// Removes an entity from the world, the schedule and the screen.
+    pub(crate) fn despawn(&mut self, entity: Entity) {
+        let position = self.world.positions.get(&entity).cloned();
+
+        self.world.despawn(entity);
+        self.scheduler.remove(entity);
+        if let Some(position) = position {
+            self.draw_cell(&position);
+        }
+    }
+
+    /// Inventory: puts `item` in `owner`'s pack, off the floor. Returns
+    /// false if the pack is full.
+    pub(crate) fn give(&mut self, owner: Entity, item: Entity) -> bool {
+        let position = self.world.positions.get(&item).cloned();
+        if !self.world.give(owner, item) {
+            return false;
+        }
+
+        if let Some(position) = position {
+            self.draw_cell(&position);
+        }
+
+        true
+    }
+
+    /// AI: a monster's turn.
+    pub(crate) fn ai_turn(&mut self, entity: Entity) -> Action {
+        let (here, target) = match (
+            self.world.positions.get(&entity),
+            self.player
+                .and_then(|player| self.world.positions.get(&player)),
+        ) {
+            (Some(here), Some(target)) => (here.clone(), target.clone()),
+            _ => return Action::Wait,
+        };
+
+        let step = if self.should_flee(entity) {
+            self.step_away(&here, &target)
+        } else {
+            self.step_toward(&here, &target)
+        };
+
+        step.and_then(|step| self.try_move(entity, &step))
+            .unwrap_or(Action::Wait)
+    }
+
+    /// Rendering: draws whatever should be shown at `point`.
+    pub(crate) fn draw_cell(&self, point: &GridPoint) {
+        let cell = match self.level.map.get(point.x, point.y).glyph() {
+            Some(cell) if self.level.seen.contains(point) => cell,
+            _ => return self.display.draw(point.x, point.y, " "),
+        };
+
+        if !self.visible.contains(point) {
+            self.display
+                .draw_color(point.x, point.y, cell, REMEMBERED_COLOR);
+        } else if let Some(renderable) = self.world.renderable_at(point) {
+            self.display
+                .draw_color(point.x, point.y, &renderable.icon, &renderable.color);
+        } else {
+            self.display.draw(point.x, point.y, cell);
+        }
+    }
+
+    fn should_flee(&self, entity: Entity) -> bool {
+        match (self.world.ais.get(&entity), self.world.healths.get(&entity)) {
+            (Some(Ai::Cowardly), Some(health)) => {
+                health.hitpoints * 10 <= health.max_hitpoints * FLEE_HEALTH
+            }
+            _ => false,
+        }
+    }
+
+    /// Monsters fight the player, not each other.
+    fn hostile(&self, attacker: Entity, defender: Entity) -> bool {
+        (Some(attacker) == self.player) != (Some(defender) == self.player)
+    }
+
+    fn describe(&self, entity: Entity, capitalized: bool) -> String {
+        match (Some(entity) == self.player, capitalized) {
+            (true, true) => "You".to_owned(),
+            (true, false) => "you".to_owned(),
+            (false, true) => format!("The {}", self.world.name(entity)),
+            (false, false) => format!("the {}", self.world.name(entity)),
+        }
+    }
+
+    fn verb(
+        &self,
+        entity: Entity,
+        second_person: &'static str,
+        third_person: &'static str,
+    ) -> &'static str {
+        if Some(entity) == self.player {
+            second_person
+        } else {
+            third_person
+        }
+    }
+}
+
+/// Status effects: hastes `entity` for `turns` more of its turns, doubling
+/// its speed unless it's already hasted. `haste_turns` is how many it has
+/// left.
+pub(crate) fn haste(scheduler: &mut Scheduler, entity: Entity, haste_turns: &mut i32, turns: i32) {
+    if *haste_turns == 0 {
+        if let Some(speed) = scheduler.speed(entity) {
+            scheduler.set_speed(entity, speed * 2);
+        }
+    }
+    *haste_turns += turns;
+}
+
+/// Status effects: counts down haste after one of `entity`'s turns, and
+/// halves its speed again when it wears off. Returns whether it wore off.
+pub(crate) fn wear_haste(scheduler: &mut Scheduler, entity: Entity, haste_turns: &mut i32) -> bool {
+    if *haste_turns == 0 {
+        return false;
+    }
+
+    *haste_turns -= 1;
+    if *haste_turns > 0 {
+        return false;
+    }
+
+    if let Some(speed) = scheduler.speed(entity) {
+        scheduler.set_speed(entity, speed / 2);
+    }
+    true
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::scheduler::{Scheduler, TURN};
+    use super::{haste, wear_haste};
+
+    #[test]
+    fn haste_wears_off_after_its_turns() {
+        let mut scheduler = Scheduler::new();
+        scheduler.add(1, TURN);
+        let mut turns = 0;
+
+        haste(&mut scheduler, 1, &mut turns, 2);
+        assert_eq!(scheduler.speed(1), Some(2 * TURN));
+        // Another potion adds turns without stacking the speed.
+        haste(&mut scheduler, 1, &mut turns, 1);
+        assert_eq!(scheduler.speed(1), Some(2 * TURN));
+
+        assert!(!wear_haste(&mut scheduler, 1, &mut turns));
+        assert!(!wear_haste(&mut scheduler, 1, &mut turns));
+        assert_eq!(scheduler.speed(1), Some(2 * TURN));
+
+        assert!(wear_haste(&mut scheduler, 1, &mut turns));
+        assert_eq!(turns, 0);
+        assert_eq!(scheduler.speed(1), Some(TURN));
+
+        assert!(!wear_haste(&mut scheduler, 1, &mut turns));
+        assert_eq!(scheduler.speed(1), Some(TURN));
+    }
+}